@@ -100,7 +100,8 @@ export function ProfileInterface() {
 
     return {
       type: uiType,
-      value: details.receiver,
+      // Encrypted receivers are only readable by the operator.
+      value: details.receiver || "Encrypted",
       isVerified: details.configured,
     } satisfies PayPalInfo
   }, [])
@@ -116,7 +117,7 @@ export function ProfileInterface() {
       const userPk = new PublicKey(activeWallet.address)
       const details = await fetchPayPalRecipient(connection, userPk)
 
-      if (!details || !details.configured || !details.receiver) {
+      if (!details || !details.configured) {
         setPaypalInfo(null)
         return
       }
@@ -83,7 +83,7 @@ export function SubscriptionInterface() {
   )
 
   const updatePayPalState = useCallback((details: PayPalRecipientDetails | null) => {
-    setHasPayPal(Boolean(details?.configured && details.receiver))
+    setHasPayPal(Boolean(details?.configured))
   }, [])
 
   const loadYieldData = useCallback(async () => {
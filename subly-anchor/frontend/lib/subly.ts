@@ -41,6 +41,12 @@ const CONFIG_SEED = Buffer.from("config")
 const USER_POSITION_SEED = Buffer.from("user_position")
 const USER_SUBSCRIPTIONS_SEED = Buffer.from("user_subscriptions")
 const SUBSCRIPTION_REGISTRY_SEED = Buffer.from("subscription_registry")
+const BILLING_BUCKET_SEED = Buffer.from("billing_bucket")
+const TRANCHE_PLEDGE_SEED = Buffer.from("tranche_pledge")
+const MERCHANT_SEED = Buffer.from("merchant")
+const MAX_BILLING_BUCKET_ENTRIES = 256
+const SECONDS_PER_DAY = 86_400
+const BILLING_PERIOD_SECONDS = 30 * SECONDS_PER_DAY
 const DEFAULT_LOCK_OPTION = 3
 const USDC_DECIMALS = 6
 
@@ -62,6 +68,41 @@ function getCoder() {
   return new BorshAccountsCoder(SUBLY_IDL)
 }
 
+function billingBucketDay(dueTs: number): number {
+  return Math.floor(dueTs / SECONDS_PER_DAY)
+}
+
+// Chunk 0 keeps the original single-bucket address, so it has no chunk seed.
+function deriveBillingBucket(day: number, chunk: number): PublicKey {
+  const seeds = [BILLING_BUCKET_SEED, new BN(day).toArrayLike(Buffer, "le", 8)]
+  if (chunk > 0) {
+    seeds.push(new BN(chunk).toArrayLike(Buffer, "le", 2))
+  }
+  const [billingBucketPda] = PublicKey.findProgramAddressSync(seeds, PROGRAM_ID)
+  return billingBucketPda
+}
+
+// discriminator (8) + day (8) + bump (1) precede the entries length.
+const BILLING_BUCKET_ENTRY_COUNT_OFFSET = 17
+
+// Returns the first chunk of `day` with room for one more entry; `exists` is
+// false when that chunk still has to be created.
+async function findOpenBillingChunk(
+  connection: Connection,
+  day: number,
+): Promise<{ chunk: number; billingBucketPda: PublicKey; exists: boolean }> {
+  for (let chunk = 0; ; chunk += 1) {
+    const billingBucketPda = deriveBillingBucket(day, chunk)
+    const info = await connection.getAccountInfo(billingBucketPda)
+    if (!info) {
+      return { chunk, billingBucketPda, exists: false }
+    }
+    if (info.data.readUInt32LE(BILLING_BUCKET_ENTRY_COUNT_OFFSET) < MAX_BILLING_BUCKET_ENTRIES) {
+      return { chunk, billingBucketPda, exists: true }
+    }
+  }
+}
+
 export async function fetchSublyConfig(connection: Connection): Promise<SublyConfig> {
   const [configPda] = PublicKey.findProgramAddressSync([CONFIG_SEED], PROGRAM_ID)
   const accountInfo = await connection.getAccountInfo(configPda)
@@ -89,6 +130,7 @@ export async function fetchSublyConfig(connection: Connection): Promise<SublyCon
 
 export type PayPalRecipientDetails = {
   configured: boolean
+  rail: "PAYPAL" | "VENMO" | "IBAN" | "ACH" | "WALLET" | null
   recipientType: "EMAIL" | "PAYPAL_ID" | "PHONE" | "USER_HANDLE" | null
   receiver: string
 }
@@ -133,11 +175,72 @@ export async function fetchPayPalRecipient(
 
   const coder = getCoder()
   const decoded = coder.decode("UserSubscriptions", accountInfo.data) as any
+  const recipient = decoded.payout_recipient
+  if (!recipient) {
+    return { configured: false, rail: null, recipientType: null, receiver: "" }
+  }
 
-  return {
-    configured: Boolean(decoded.paypal_configured),
-    recipientType: mapRecipientType(decoded.paypal_recipient_type),
-    receiver: decoded.paypal_receiver as string,
+  const [variant, fields] = enumVariant(recipient)
+  switch (variant) {
+    // Encrypted recipients only expose their rail; the receiver stays sealed.
+    case "encrypted": {
+      const [railVariant, railFields] = enumVariant(fields.rail)
+      return {
+        configured: true,
+        rail: mapPayoutRail(fields.rail),
+        recipientType: railVariant === "paypal" ? mapRecipientType(railFields.recipient_type) : null,
+        receiver: "",
+      }
+    }
+    case "paypal":
+      return {
+        configured: true,
+        rail: "PAYPAL",
+        recipientType: mapRecipientType(fields.recipient_type),
+        receiver: fields.receiver as string,
+      }
+    case "venmo":
+      return { configured: true, rail: "VENMO", recipientType: null, receiver: fields.handle }
+    case "iban":
+      return { configured: true, rail: "IBAN", recipientType: null, receiver: fields.iban }
+    case "ach":
+      return {
+        configured: true,
+        rail: "ACH",
+        recipientType: null,
+        receiver: `${fields.routing_number}:${fields.account_number}`,
+      }
+    case "wallet":
+      return {
+        configured: true,
+        rail: "WALLET",
+        recipientType: null,
+        receiver: (fields.address as PublicKey).toBase58(),
+      }
+    default:
+      return { configured: true, rail: null, recipientType: null, receiver: "" }
+  }
+}
+
+function enumVariant(raw: any): [string, any] {
+  const [variant] = Object.keys(raw ?? {})
+  return [variant?.toLowerCase() ?? "", variant ? raw[variant] ?? {} : {}]
+}
+
+function mapPayoutRail(raw: any): PayPalRecipientDetails["rail"] {
+  switch (enumVariant(raw)[0]) {
+    case "paypal":
+      return "PAYPAL"
+    case "venmo":
+      return "VENMO"
+    case "iban":
+      return "IBAN"
+    case "ach":
+      return "ACH"
+    case "wallet":
+      return "WALLET"
+    default:
+      return null
   }
 }
 
@@ -184,6 +287,24 @@ export async function fetchSubscriptionServices(
   }))
 }
 
+// The program expects the creator's merchant PDA whether or not the creator
+// has registered as a merchant.
+async function resolveServiceMerchant(
+  connection: Connection,
+  serviceId: number,
+): Promise<PublicKey> {
+  const services = await fetchSubscriptionServices(connection)
+  const service = services.find((entry) => entry.id === serviceId)
+  if (!service) {
+    throw new Error("Service not found in the subscription registry")
+  }
+  const [merchantPda] = PublicKey.findProgramAddressSync(
+    [MERCHANT_SEED, service.creator.toBuffer()],
+    PROGRAM_ID,
+  )
+  return merchantPda
+}
+
 export type UserSubscriptionEntry = {
   id: number
   serviceId: number
@@ -332,6 +453,10 @@ export async function prepareUnstakeTransaction(
     USER_POSITION_SEED,
     user.toBuffer(),
   ], PROGRAM_ID)
+  const [tranchePledgePda] = PublicKey.findProgramAddressSync(
+    [TRANCHE_PLEDGE_SEED, user.toBuffer(), new BN(trancheId).toArrayLike(Buffer, "le", 8)],
+    PROGRAM_ID,
+  )
   const userTokenAccount = await getAssociatedTokenAddress(config.usdcMint, user)
   const userTokenAccountInfo = await connection.getAccountInfo(userTokenAccount)
 
@@ -362,6 +487,7 @@ export async function prepareUnstakeTransaction(
         { pubkey: configPda, isSigner: false, isWritable: true },
         { pubkey: user, isSigner: true, isWritable: true },
         { pubkey: userPositionPda, isSigner: false, isWritable: true },
+        { pubkey: tranchePledgePda, isSigner: false, isWritable: false },
         { pubkey: config.vault, isSigner: false, isWritable: true },
         { pubkey: userTokenAccount, isSigner: false, isWritable: true },
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -469,10 +595,43 @@ export async function prepareSubscribeServiceTransaction(
     throw new Error("No staking position found. Stake before subscribing.")
   }
 
+  const merchantPda = await resolveServiceMerchant(connection, serviceId)
+
+  // New subscriptions are queued under the day their first renewal falls due.
+  const slot = await connection.getSlot()
+  const clusterTime = (await connection.getBlockTime(slot)) ?? Math.floor(Date.now() / 1000)
+  const bucketDay = billingBucketDay(clusterTime + BILLING_PERIOD_SECONDS)
+  const { chunk, billingBucketPda, exists } = await findOpenBillingChunk(connection, bucketDay)
+
   const instructionCoder = new BorshInstructionCoder(SUBLY_IDL)
+  const transaction = new Transaction()
+
+  if (!exists) {
+    transaction.add(
+      new TransactionInstruction({
+        programId: PROGRAM_ID,
+        keys: [
+          { pubkey: user, isSigner: true, isWritable: true },
+          { pubkey: billingBucketPda, isSigner: false, isWritable: true },
+          // The full chunk before this one; chunk 0 has none.
+          {
+            pubkey: chunk > 0 ? deriveBillingBucket(bucketDay, chunk - 1) : PROGRAM_ID,
+            isSigner: false,
+            isWritable: false,
+          },
+          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        data: instructionCoder.encode("init_billing_bucket", { day: new BN(bucketDay), chunk }),
+      }),
+    )
+  }
+
   const encoded = instructionCoder.encode("subscribe_service", {
     args: {
       service_id: new BN(serviceId),
+      payout_override: null,
+      gift: null,
+      billing_chunk: chunk,
     },
   })
 
@@ -484,12 +643,18 @@ export async function prepareSubscribeServiceTransaction(
       { pubkey: userPositionPda, isSigner: false, isWritable: true },
       { pubkey: userSubscriptionsPda, isSigner: false, isWritable: true },
       { pubkey: subscriptionRegistryPda, isSigner: false, isWritable: false },
+      { pubkey: merchantPda, isSigner: false, isWritable: true },
+      { pubkey: billingBucketPda, isSigner: false, isWritable: true },
+      // Optional beneficiary gifts account, only used for gifts.
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      // Optional group budget; subscriptions here are charged to the user's own stake.
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
     data: encoded,
   })
 
-  const transaction = new Transaction().add(instruction)
+  transaction.add(instruction)
   transaction.feePayer = user
 
   const blockhash = await connection.getLatestBlockhash()
@@ -512,11 +677,38 @@ export async function prepareUnsubscribeServiceTransaction(
     PROGRAM_ID,
   )
 
+  const [subscriptionRegistryPda] = PublicKey.findProgramAddressSync(
+    [SUBSCRIPTION_REGISTRY_SEED],
+    PROGRAM_ID,
+  )
+
   const userSubscriptionsInfo = await connection.getAccountInfo(userSubscriptionsPda)
   if (!userSubscriptionsInfo) {
     throw new Error("No subscription record found for this wallet")
   }
 
+  const decoded = getCoder().decode("UserSubscriptions", userSubscriptionsInfo.data) as any
+  const subscription = ((decoded.subscriptions ?? []) as any[]).find(
+    (entry) => Number(entry.id) === subscriptionId,
+  )
+  if (!subscription) {
+    throw new Error("Subscription not found for this wallet")
+  }
+  const merchantPda = await resolveServiceMerchant(connection, Number(subscription.service_id))
+  // The bucket is required whenever the subscription has a billing slot.
+  // Subscriptions queued before chunks existed have none and sit in chunk 0.
+  const billingSlot = ((decoded.billing_slots ?? []) as any[]).find(
+    (entry) => Number(entry.subscription_id) === subscriptionId,
+  )
+  const billingBucketPda = deriveBillingBucket(
+    billingBucketDay(Number(subscription.next_billing_ts)),
+    billingSlot ? Number(billingSlot.chunk) : 0,
+  )
+  const billingBucketKey =
+    billingSlot || (await connection.getAccountInfo(billingBucketPda))
+      ? billingBucketPda
+      : PROGRAM_ID
+
   const instructionCoder = new BorshInstructionCoder(SUBLY_IDL)
   const encoded = instructionCoder.encode("unsubscribe_service", {
     args: {
@@ -529,6 +721,14 @@ export async function prepareUnsubscribeServiceTransaction(
     keys: [
       { pubkey: user, isSigner: true, isWritable: true },
       { pubkey: userSubscriptionsPda, isSigner: false, isWritable: true },
+      { pubkey: subscriptionRegistryPda, isSigner: false, isWritable: false },
+      { pubkey: merchantPda, isSigner: false, isWritable: true },
+      {
+        pubkey: billingBucketKey,
+        isSigner: false,
+        isWritable: !billingBucketKey.equals(PROGRAM_ID),
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
     data: encoded,
   })
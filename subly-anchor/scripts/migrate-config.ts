@@ -0,0 +1,45 @@
+import * as anchor from "@coral-xyz/anchor";
+import { AnchorProvider, Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+
+import { SublySolanaProgram } from "../target/types/subly_solana_program";
+
+const SEED_CONFIG = "config";
+
+async function main() {
+  const provider = AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const wallet = provider.wallet as anchor.Wallet;
+  const program = anchor.workspace.SublySolanaProgram as Program<SublySolanaProgram>;
+
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from(SEED_CONFIG)],
+    program.programId,
+  );
+  const before = await provider.connection.getAccountInfo(configPda);
+  if (!before) {
+    throw new Error(`Config ${configPda.toBase58()} does not exist`);
+  }
+
+  const signature = await program.methods
+    .migrateConfig()
+    .accounts({
+      authority: wallet.publicKey,
+      config: configPda,
+      systemProgram: SystemProgram.programId,
+    })
+    .rpc();
+
+  const after = await provider.connection.getAccountInfo(configPda);
+  console.log(
+    `Config ${configPda.toBase58()}: ${before.data.length} -> ${after?.data.length ?? 0} bytes (${signature})`,
+  );
+}
+
+main()
+  .then(() => process.exit(0))
+  .catch((err) => {
+    console.error("Failed to migrate config", err);
+    process.exit(1);
+  });
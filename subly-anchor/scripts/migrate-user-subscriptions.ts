@@ -0,0 +1,62 @@
+import * as anchor from "@coral-xyz/anchor";
+import { AnchorProvider, Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { createHash } from "crypto";
+
+import { SublySolanaProgram } from "../target/types/subly_solana_program";
+
+// Accounts are found by discriminator and the owner is read from the raw
+// bytes, so one malformed account cannot stop the sweep.
+const DISCRIMINATOR_LEN = 8;
+const OWNER_OFFSET = DISCRIMINATOR_LEN;
+
+async function main() {
+  const provider = AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const wallet = provider.wallet as anchor.Wallet;
+  const connection = provider.connection;
+  const program = anchor.workspace.SublySolanaProgram as Program<SublySolanaProgram>;
+
+  const discriminator = createHash("sha256")
+    .update("account:UserSubscriptions")
+    .digest()
+    .subarray(0, DISCRIMINATOR_LEN);
+  const accounts = await connection.getProgramAccounts(program.programId, {
+    filters: [{ memcmp: { offset: 0, bytes: anchor.utils.bytes.bs58.encode(discriminator) } }],
+  });
+  console.log(`Found ${accounts.length} user subscription accounts.`);
+
+  let migrated = 0;
+  for (const { pubkey, account } of accounts) {
+    const user = new PublicKey(account.data.subarray(OWNER_OFFSET, OWNER_OFFSET + 32));
+    if (user.equals(PublicKey.default)) {
+      continue;
+    }
+
+    try {
+      const signature = await program.methods
+        .migrateUserSubscriptions()
+        .accounts({
+          payer: wallet.publicKey,
+          user,
+          userSubscriptions: pubkey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      migrated += 1;
+      console.log(`Migrated ${pubkey.toBase58()} (user ${user.toBase58()}): ${signature}`);
+    } catch (error) {
+      console.error(`Failed to migrate ${pubkey.toBase58()}:`, error);
+    }
+  }
+
+  console.log(`Processed ${migrated}/${accounts.length} accounts. Already migrated accounts are no-ops.`);
+}
+
+main()
+  .then(() => process.exit(0))
+  .catch((err) => {
+    console.error("Failed to migrate user subscriptions", err);
+    process.exit(1);
+  });
@@ -0,0 +1,123 @@
+import { BN } from "@coral-xyz/anchor";
+import { getOrCreateAssociatedTokenAccount, transferChecked } from "@solana/spl-token";
+import { Connection, Keypair, PublicKey } from "@solana/web3.js";
+
+import { PayPalClient, buildDueEntryPayload, formatUsdc } from "./paypal-client";
+
+const USDC_DECIMALS = 6;
+
+export type PayoutRequest = {
+  rail: string;
+  recipientType: string;
+  receiver: string;
+  monthlyPriceUsdc: BN;
+  serviceName: string;
+  subscriptionId: BN;
+};
+
+export interface PayoutAdapter {
+  createPayout(request: PayoutRequest): Promise<void>;
+}
+
+export class PayPalPayoutAdapter implements PayoutAdapter {
+  constructor(private readonly client: PayPalClient) {}
+
+  public async createPayout(request: PayoutRequest): Promise<void> {
+    await this.client.createPayout(buildDueEntryPayload(request));
+  }
+}
+
+// PayPal Payouts delivers to Venmo accounts when recipient_wallet is VENMO.
+export class VenmoPayoutAdapter implements PayoutAdapter {
+  constructor(private readonly client: PayPalClient) {}
+
+  public async createPayout(request: PayoutRequest): Promise<void> {
+    await this.client.createPayout(
+      buildDueEntryPayload({
+        ...request,
+        recipientWallet: "VENMO",
+        recipientType: "USER_HANDLE",
+        receiver: request.receiver.replace(/^@/, ""),
+      }),
+    );
+  }
+}
+
+// Sends USDC from the operator's associated token account to the recipient wallet.
+export class WalletPayoutAdapter implements PayoutAdapter {
+  constructor(
+    private readonly connection: Connection,
+    private readonly operator: Keypair,
+    private readonly usdcMint: PublicKey,
+    private readonly enabled: boolean,
+  ) {}
+
+  public async createPayout(request: PayoutRequest): Promise<void> {
+    if (!this.enabled) {
+      console.log(
+        `  -> Skipping wallet payout (WALLET_PAYOUTS_ENABLED not set) to ${request.receiver} ` +
+          `amount ${formatUsdc(request.monthlyPriceUsdc)} USDC`,
+      );
+      return;
+    }
+
+    const recipient = new PublicKey(request.receiver);
+    const source = await getOrCreateAssociatedTokenAccount(
+      this.connection,
+      this.operator,
+      this.usdcMint,
+      this.operator.publicKey,
+    );
+    const destination = await getOrCreateAssociatedTokenAccount(
+      this.connection,
+      this.operator,
+      this.usdcMint,
+      recipient,
+    );
+    const signature = await transferChecked(
+      this.connection,
+      this.operator,
+      source.address,
+      this.usdcMint,
+      destination.address,
+      this.operator,
+      BigInt(request.monthlyPriceUsdc.toString()),
+      USDC_DECIMALS,
+    );
+    console.log(`  -> Wallet payout sent: ${signature}`);
+  }
+}
+
+// Routes each payout to the adapter for its rail. Rails without an adapter
+// (IBAN and ACH until a bank provider is wired up) are reported, not paid.
+export class PayoutRouter {
+  constructor(private readonly adapters: Partial<Record<string, PayoutAdapter>>) {}
+
+  public async createPayout(request: PayoutRequest): Promise<boolean> {
+    const adapter = this.adapters[request.rail];
+    if (!adapter) {
+      console.warn(`  -> No payout adapter for rail ${request.rail}. Leaving subscription due.`);
+      return false;
+    }
+    await adapter.createPayout(request);
+    return true;
+  }
+}
+
+export function createPayoutRouter(
+  payPalClient: PayPalClient,
+  connection: Connection,
+  operator: Keypair,
+  usdcMint: PublicKey,
+): PayoutRouter {
+  return new PayoutRouter({
+    PAYPAL: new PayPalPayoutAdapter(payPalClient),
+    VENMO: new VenmoPayoutAdapter(payPalClient),
+    WALLET: new WalletPayoutAdapter(
+      connection,
+      operator,
+      usdcMint,
+      process.env.WALLET_PAYOUTS_ENABLED === "true",
+    ),
+  });
+}
@@ -9,6 +9,7 @@ type PayPalConfig = {
 };
 
 type DueEntryPayload = {
+  recipientWallet?: "PAYPAL" | "VENMO";
   recipientType: string;
   receiver: string;
   monthlyPriceUsdc: BN;
@@ -63,6 +64,7 @@ export class PayPalClient {
           note: `Subly payout for ${entry.serviceName}`,
           sender_item_id: `sub-${entry.subscriptionId.toString()}`,
           receiver: entry.receiver,
+          ...(entry.recipientWallet ? { recipient_wallet: entry.recipientWallet } : {}),
         },
       ],
     };
@@ -180,6 +182,7 @@ export function usdcToUsdString(amount: BN): string {
 }
 
 export type DueEntryPayloadInput = {
+  recipientWallet?: "PAYPAL" | "VENMO";
   recipientType: string;
   receiver: string;
   monthlyPriceUsdc: BN;
@@ -189,6 +192,7 @@ export type DueEntryPayloadInput = {
 
 export function buildDueEntryPayload(entry: DueEntryPayloadInput): DueEntryPayload {
   return {
+    recipientWallet: entry.recipientWallet,
     recipientType: entry.recipientType,
     receiver: entry.receiver,
     monthlyPriceUsdc: entry.monthlyPriceUsdc,
@@ -1,16 +1,16 @@
 import * as anchor from "@coral-xyz/anchor";
 import { AnchorProvider, BN, Program } from "@coral-xyz/anchor";
-import { Finality, PublicKey } from "@solana/web3.js";
+import { Finality, PublicKey, SystemProgram } from "@solana/web3.js";
 
 import { SublySolanaProgram } from "../target/types/subly_solana_program";
-import {
-  PayPalClient,
-  buildDueEntryPayload,
-} from "./paypal-client";
+import { PayPalClient } from "./paypal-client";
+import { PayoutRouter, createPayoutRouter } from "./payout-router";
+import { loadOperatorSecretKey, payoutRecipientFor, resolveReceiver } from "./receiver-crypto";
 
 const SEED_CONFIG = "config";
 const SEED_USER_SUBSCRIPTIONS = "user_subscriptions";
 const SEED_REGISTRY = "subscription_registry";
+const SEED_MERCHANT = "merchant";
 const finality: Finality = (process.env.COMMITMENT as Finality) ?? "confirmed";
 const START_SLOT = Number(process.env.NEW_SUBS_START_SLOT ?? 0);
 const FETCH_LIMIT = Number(process.env.NEW_SUBS_FETCH_LIMIT ?? 100);
@@ -49,11 +49,20 @@ async function main() {
     clientId: PAYPAL_CLIENT_ID,
     clientSecret: PAYPAL_CLIENT_SECRET,
   });
+  const payoutRouter = createPayoutRouter(
+    payPalClient,
+    provider.connection,
+    wallet.payer,
+    config.usdcMint,
+  );
+  const operatorSecretKey = loadOperatorSecretKey();
 
   const registry = await program.account.subscriptionRegistry.fetch(registryPda);
   const serviceNameById = new Map<number, string>();
+  const creatorByServiceId = new Map<number, PublicKey>();
   registry.services.forEach((service) => {
     serviceNameById.set(service.id.toNumber(), service.name);
+    creatorByServiceId.set(service.id.toNumber(), service.creator);
   });
 
   let processed = 0;
@@ -93,8 +102,11 @@ async function main() {
         await handleActivation(
           program,
           configPda,
-          payPalClient,
+          registryPda,
+          payoutRouter,
+          operatorSecretKey,
           serviceNameById,
+          creatorByServiceId,
           evt.data,
           info.signature,
         );
@@ -115,15 +127,19 @@ type ActivationEvent = {
   subscriptionId: BN;
   serviceId: BN;
   monthlyPriceUsdc: BN;
+  rail: string;
   recipientType: string;
-  receiver: string;
+  receiverHash: number[];
 };
 
 async function handleActivation(
   program: Program<SublySolanaProgram>,
   configPda: PublicKey,
-  payPalClient: PayPalClient,
+  registryPda: PublicKey,
+  payoutRouter: PayoutRouter,
+  operatorSecretKey: Uint8Array | null,
   serviceNameById: Map<number, string>,
+  creatorByServiceId: Map<number, PublicKey>,
   activation: ActivationEvent,
   signature: string,
 ) {
@@ -166,32 +182,69 @@ async function handleActivation(
     return;
   }
 
-  await payPalClient.createPayout(
-    buildDueEntryPayload({
-      recipientType: activation.recipientType,
-      receiver: activation.receiver,
-      monthlyPriceUsdc: activation.monthlyPriceUsdc,
-      serviceName,
-      subscriptionId: activation.subscriptionId,
-    }),
+  const receiver = resolveReceiver(
+    activation.user,
+    payoutRecipientFor(userSubscriptionsAccount, activation.subscriptionId),
+    operatorSecretKey,
   );
+  if (!receiver) {
+    console.warn("  -> Unable to resolve payout receiver. Skipping payout.");
+    return;
+  }
+
+  const paid = await payoutRouter.createPayout({
+    rail: activation.rail,
+    recipientType: activation.recipientType,
+    receiver,
+    monthlyPriceUsdc: activation.monthlyPriceUsdc,
+    serviceName,
+    subscriptionId: activation.subscriptionId,
+  });
+  if (!paid) {
+    return;
+  }
+
+  const merchantPda = deriveMerchant(program, creatorByServiceId.get(serviceIdNum));
 
   const paymentSig = await program.methods
     .recordSubscriptionPayment({
       subscriptionId: activation.subscriptionId,
       paymentTs: null,
+      nextBillingChunk: 0,
     })
     .accountsStrict({
       config: configPda,
       operator: program.provider.wallet.publicKey,
       user: activation.user,
       userSubscriptions: userSubscriptionsPda,
+      subscriptionRegistry: registryPda,
+      merchant: merchantPda,
+      // Initial payouts leave the renewal schedule untouched.
+      billingBucket: null,
+      nextBillingBucket: null,
+      systemProgram: SystemProgram.programId,
     })
     .rpc();
 
   console.log(`Initial payment recorded on-chain. Tx: ${paymentSig}`);
 }
 
+// The program expects the creator's merchant PDA whether or not the creator
+// has registered as a merchant.
+function deriveMerchant(
+  program: Program<SublySolanaProgram>,
+  creator: PublicKey | undefined,
+): PublicKey {
+  if (!creator) {
+    throw new Error("Service creator not found in the subscription registry");
+  }
+  const [merchantPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from(SEED_MERCHANT), creator.toBuffer()],
+    program.programId,
+  );
+  return merchantPda;
+}
+
 async function decodeEvents(
   provider: AnchorProvider,
   eventCoder: anchor.BorshEventCoder,
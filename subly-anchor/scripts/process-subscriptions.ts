@@ -1,25 +1,34 @@
 import * as anchor from "@coral-xyz/anchor";
 import { AnchorProvider, BN, Program } from "@coral-xyz/anchor";
-import { ConfirmOptions, PublicKey } from "@solana/web3.js";
+import { ConfirmOptions, PublicKey, SystemProgram } from "@solana/web3.js";
 
 import { SublySolanaProgram } from "../target/types/subly_solana_program";
 import {
   PayPalClient,
-  buildDueEntryPayload,
   formatUsdc,
   httpRequest,
 } from "./paypal-client";
+import { PayoutRouter, createPayoutRouter } from "./payout-router";
+import { loadOperatorSecretKey, payoutRecipientFor, resolveReceiver } from "./receiver-crypto";
 
 const DEFAULT_LOOK_AHEAD_SECONDS = 24 * 60 * 60; // 24 hours
 const DEFAULT_CHUNK_SIZE = 16;
+const DEFAULT_LOOK_BACK_DAYS = 7;
+const SECONDS_PER_DAY = 24 * 60 * 60;
+const BILLING_PERIOD_SECONDS = 30 * SECONDS_PER_DAY;
 
 const SEED_CONFIG = "config";
 const SEED_REGISTRY = "subscription_registry";
 const SEED_USER_SUBSCRIPTIONS = "user_subscriptions";
+const SEED_MERCHANT = "merchant";
+const SEED_BILLING_BUCKET = "billing_bucket";
+const MAX_BILLING_BUCKET_ENTRIES = 256;
 
 const commitment: ConfirmOptions["commitment"] = (process.env.COMMITMENT as ConfirmOptions["commitment"]) ?? "confirmed";
 const LOOK_AHEAD_SECONDS = Number(process.env.LOOK_AHEAD_SECONDS ?? DEFAULT_LOOK_AHEAD_SECONDS);
 const CHUNK_SIZE = Number(process.env.BATCH_SIZE ?? DEFAULT_CHUNK_SIZE);
+const LOOK_BACK_DAYS = Number(process.env.LOOK_BACK_DAYS ?? DEFAULT_LOOK_BACK_DAYS);
+const FULL_SCAN = process.env.FULL_SCAN === "true";
 const PAYPAL_CLIENT_ID = process.env.PAYPAL_CLIENT_ID;
 const PAYPAL_CLIENT_SECRET = process.env.PAYPAL_CLIENT_SECRET;
 const PAYPAL_API_BASE = process.env.PAYPAL_API_BASE ?? "https://api-m.sandbox.paypal.com";
@@ -48,14 +57,16 @@ async function main() {
     );
   }
 
-  const allUserSubscriptions = await program.account.userSubscriptions.all();
-  if (allUserSubscriptions.length === 0) {
+  const userSubscriptionPdas = FULL_SCAN
+    ? (await program.account.userSubscriptions.all()).map((account) => account.publicKey)
+    : await collectQueuedUserSubscriptions(program);
+  if (userSubscriptionPdas.length === 0) {
     console.log("No user subscription accounts found. Nothing to do.");
     return;
   }
 
   console.log(
-    `Scanning ${allUserSubscriptions.length} user subscription accounts with look-ahead ${LOOK_AHEAD_SECONDS} seconds...`,
+    `Scanning ${userSubscriptionPdas.length} user subscription accounts with look-ahead ${LOOK_AHEAD_SECONDS} seconds...`,
   );
 
   const eventCoder = new anchor.BorshEventCoder(program.idl);
@@ -64,8 +75,21 @@ async function main() {
     clientId: PAYPAL_CLIENT_ID,
     clientSecret: PAYPAL_CLIENT_SECRET,
   });
+  const payoutRouter = createPayoutRouter(
+    payPalClient,
+    provider.connection,
+    wallet.payer,
+    config.usdcMint,
+  );
+  const operatorSecretKey = loadOperatorSecretKey();
+
+  const registry = await program.account.subscriptionRegistry.fetch(registryPda);
+  const creatorByServiceId = new Map<number, PublicKey>();
+  registry.services.forEach((service) => {
+    creatorByServiceId.set(service.id.toNumber(), service.creator);
+  });
 
-  const chunks = chunkAccounts(allUserSubscriptions.map((account) => account.publicKey), CHUNK_SIZE);
+  const chunks = chunkAccounts(userSubscriptionPdas, CHUNK_SIZE);
   for (const batch of chunks) {
     const remainingAccounts = batch.map((pda) => ({
       pubkey: pda,
@@ -73,46 +97,90 @@ async function main() {
       isWritable: false,
     }));
 
-    const signature = await program.methods
-      .findDueSubscriptions({ lookAheadSeconds: new BN(LOOK_AHEAD_SECONDS) })
-      .accounts({
-        config: configPda,
-        subscriptionRegistry: registryPda,
-      })
-      .remainingAccounts(remainingAccounts)
-      .rpc({ commitment });
-
-    const events = await decodeEvents(provider, eventCoder, signature);
-    const dueEvent = events.find((event) => event.name.toLowerCase() === "subscriptionsdue");
-    if (!dueEvent || dueEvent.data.entries.length === 0) {
-      continue;
-    }
+    let cursor: DueCursorRaw | null = { userIndex: 0, subscriptionId: new BN(0) };
+    while (cursor) {
+      const signature = await program.methods
+        .findDueSubscriptions({
+          lookAheadSeconds: new BN(LOOK_AHEAD_SECONDS),
+          startUserIndex: cursor.userIndex,
+          startSubscriptionId: cursor.subscriptionId,
+          maxEntries: 0,
+        })
+        .accounts({
+          config: configPda,
+          subscriptionRegistry: registryPda,
+        })
+        .remainingAccounts(remainingAccounts)
+        .rpc({ commitment });
+
+      const events = await decodeEvents(provider, eventCoder, signature);
+      const dueEvent = events.find((event) => event.name.toLowerCase() === "subscriptionsdue");
+      if (!dueEvent) {
+        throw new Error(`SubscriptionsDue event missing from ${signature}`);
+      }
+
+      for (const skipped of dueEvent.data.skipped as Array<SkippedDueEntryRaw>) {
+        console.warn(
+          `Skipped ${skipped.account.toBase58()}` +
+            (skipped.subscriptionId ? ` subscription ${skipped.subscriptionId.toNumber()}` : "") +
+            `: ${skipped.reason}`,
+        );
+      }
 
-    console.log(`Found ${dueEvent.data.entries.length} subscriptions due in tx ${signature}`);
+      if (dueEvent.data.entries.length > 0) {
+        console.log(`Found ${dueEvent.data.entries.length} subscriptions due in tx ${signature}`);
+      }
+
+      for (const rawEntry of dueEvent.data.entries as Array<DueEntryRaw>) {
+        await handleDueEntry(
+          program,
+          configPda,
+          registryPda,
+          creatorByServiceId,
+          payoutRouter,
+          operatorSecretKey,
+          rawEntry,
+        );
+      }
 
-    for (const rawEntry of dueEvent.data.entries as Array<DueEntryRaw>) {
-      await handleDueEntry(program, configPda, payPalClient, rawEntry);
+      cursor = dueEvent.data.nextCursor as DueCursorRaw | null;
     }
   }
 
   console.log("Batch processing completed.");
 }
 
+type DueCursorRaw = {
+  userIndex: number;
+  subscriptionId: BN;
+};
+
+type SkippedDueEntryRaw = {
+  account: PublicKey;
+  userIndex: number;
+  subscriptionId: BN | null;
+  reason: string;
+};
+
 type DueEntryRaw = {
   user: PublicKey;
   subscriptionId: BN;
   serviceId: BN;
   serviceName: string;
   monthlyPriceUsdc: BN;
+  rail: string;
   recipientType: string;
-  receiver: string;
+  receiverHash: number[];
   dueTs: BN;
 };
 
 async function handleDueEntry(
   program: Program<SublySolanaProgram>,
   configPda: PublicKey,
-  payPalClient: PayPalClient,
+  registryPda: PublicKey,
+  creatorByServiceId: Map<number, PublicKey>,
+  payoutRouter: PayoutRouter,
+  operatorSecretKey: Uint8Array | null,
   entry: DueEntryRaw,
 ) {
   console.log(
@@ -120,37 +188,216 @@ async function handleDueEntry(
       `(${entry.serviceName}) due at ${entry.dueTs.toNumber()}`,
   );
 
-  await payPalClient.createPayout(
-    buildDueEntryPayload({
-      recipientType: entry.recipientType,
-      receiver: entry.receiver,
-      monthlyPriceUsdc: entry.monthlyPriceUsdc,
-      serviceName: entry.serviceName,
-      subscriptionId: entry.subscriptionId,
-    }),
-  );
-
   const [userSubscriptionsPda] = PublicKey.findProgramAddressSync(
     [Buffer.from(SEED_USER_SUBSCRIPTIONS), entry.user.toBuffer()],
     program.programId,
   );
 
+  // Events only carry the receiver hash; the receiver itself is read from the
+  // account and decrypted with the operator key when it was stored encrypted.
+  const userSubscriptions = await program.account.userSubscriptions.fetch(userSubscriptionsPda);
+  const receiver = resolveReceiver(
+    entry.user,
+    payoutRecipientFor(userSubscriptions, entry.subscriptionId),
+    operatorSecretKey,
+  );
+  if (!receiver) {
+    console.warn("  -> Unable to resolve payout receiver. Skipping payout.");
+    return;
+  }
+
+  const paid = await payoutRouter.createPayout({
+    rail: entry.rail,
+    recipientType: entry.recipientType,
+    receiver,
+    monthlyPriceUsdc: entry.monthlyPriceUsdc,
+    serviceName: entry.serviceName,
+    subscriptionId: entry.subscriptionId,
+  });
+  if (!paid) {
+    return;
+  }
+
+  const merchantPda = deriveMerchant(
+    program,
+    creatorByServiceId.get(entry.serviceId.toNumber()),
+  );
+  const { billingBucket, nextBillingBucket, nextBillingChunk } = await resolveBillingBuckets(
+    program,
+    userSubscriptionsPda,
+    entry.subscriptionId,
+  );
+
   const signature = await program.methods
     .recordSubscriptionPayment({
       subscriptionId: entry.subscriptionId,
       paymentTs: null,
+      nextBillingChunk,
     })
     .accounts({
       config: configPda,
       operator: program.provider.wallet.publicKey,
       user: entry.user,
       userSubscriptions: userSubscriptionsPda,
+      subscriptionRegistry: registryPda,
+      merchant: merchantPda,
+      billingBucket,
+      nextBillingBucket,
+      systemProgram: SystemProgram.programId,
     })
     .rpc();
 
   console.log(`Payment recorded on-chain. Tx: ${signature}`);
 }
 
+// Chunk 0 keeps the original single-bucket address, so it has no chunk seed.
+function deriveBillingBucket(
+  program: Program<SublySolanaProgram>,
+  day: number,
+  bucketChunk: number,
+): PublicKey {
+  const seeds = [Buffer.from(SEED_BILLING_BUCKET), new BN(day).toArrayLike(Buffer, "le", 8)];
+  if (bucketChunk > 0) {
+    seeds.push(new BN(bucketChunk).toArrayLike(Buffer, "le", 2));
+  }
+  const [billingBucketPda] = PublicKey.findProgramAddressSync(seeds, program.programId);
+  return billingBucketPda;
+}
+
+async function collectQueuedUserSubscriptions(
+  program: Program<SublySolanaProgram>,
+): Promise<PublicKey[]> {
+  const now = Math.floor(Date.now() / 1000);
+  const firstDay = Math.floor(now / SECONDS_PER_DAY) - LOOK_BACK_DAYS;
+  const lastDay = Math.floor((now + LOOK_AHEAD_SECONDS) / SECONDS_PER_DAY);
+
+  const users = new Map<string, PublicKey>();
+  for (let day = firstDay; day <= lastDay; day += 1) {
+    // Chunks are created in order, so the first missing one ends the day.
+    for (let bucketChunk = 0; ; bucketChunk += 1) {
+      const billingBucket = deriveBillingBucket(program, day, bucketChunk);
+      if (!(await program.account.billingBucket.fetchNullable(billingBucket))) {
+        break;
+      }
+
+      let offset: number | null = 0;
+      while (offset !== null) {
+        const page: any = await program.methods
+          .crankDueBucket(bucketChunk, { offset, limit: 0 })
+          .accounts({ billingBucket })
+          .view();
+        for (const queued of page.entries as Array<{ user: PublicKey }>) {
+          users.set(queued.user.toBase58(), queued.user);
+        }
+        offset = page.nextOffset;
+      }
+    }
+  }
+
+  return Array.from(users.values()).map((user) => {
+    const [userSubscriptionsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_USER_SUBSCRIPTIONS), user.toBuffer()],
+      program.programId,
+    );
+    return userSubscriptionsPda;
+  });
+}
+
+// Returns the first chunk of `day` with room for one more entry, creating it
+// when every existing chunk is full.
+async function findOpenBillingChunk(
+  program: Program<SublySolanaProgram>,
+  day: number,
+): Promise<{ billingBucket: PublicKey; bucketChunk: number }> {
+  for (let bucketChunk = 0; ; bucketChunk += 1) {
+    const billingBucket = deriveBillingBucket(program, day, bucketChunk);
+    const bucket = await program.account.billingBucket.fetchNullable(billingBucket);
+    if (!bucket) {
+      await program.methods
+        .initBillingBucket(new BN(day), bucketChunk)
+        .accounts({
+          payer: program.provider.wallet.publicKey,
+          billingBucket,
+          previousBucket:
+            bucketChunk > 0 ? deriveBillingBucket(program, day, bucketChunk - 1) : null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      return { billingBucket, bucketChunk };
+    }
+    if (bucket.entries.length < MAX_BILLING_BUCKET_ENTRIES) {
+      return { billingBucket, bucketChunk };
+    }
+  }
+}
+
+async function resolveBillingBuckets(
+  program: Program<SublySolanaProgram>,
+  userSubscriptionsPda: PublicKey,
+  subscriptionId: BN,
+): Promise<{
+  billingBucket: PublicKey | null;
+  nextBillingBucket: PublicKey | null;
+  nextBillingChunk: number;
+}> {
+  const account = await program.account.userSubscriptions.fetch(userSubscriptionsPda);
+  const subscription = account.subscriptions.find((candidate) =>
+    candidate.id.eq(subscriptionId),
+  );
+  // Initial payouts do not move the subscription in the billing queue.
+  if (!subscription || !subscription.initialPaymentRecorded) {
+    return { billingBucket: null, nextBillingBucket: null, nextBillingChunk: 0 };
+  }
+
+  // The program requires the bucket whenever the subscription has a slot.
+  // Subscriptions queued before chunks existed have none and sit in chunk 0.
+  const dueTs = subscription.nextBillingTs.toNumber();
+  const slot = account.billingSlots.find((candidate) =>
+    candidate.subscriptionId.eq(subscriptionId),
+  );
+  const currentBucket = deriveBillingBucket(
+    program,
+    Math.floor(dueTs / SECONDS_PER_DAY),
+    slot?.chunk ?? 0,
+  );
+  const billingBucket =
+    slot || (await program.account.billingBucket.fetchNullable(currentBucket))
+      ? currentBucket
+      : null;
+  if (!("active" in subscription.status)) {
+    return { billingBucket, nextBillingBucket: null, nextBillingChunk: 0 };
+  }
+
+  const now = Math.floor(Date.now() / 1000);
+  let nextDueTs = dueTs + BILLING_PERIOD_SECONDS;
+  while (nextDueTs <= now) {
+    nextDueTs += BILLING_PERIOD_SECONDS;
+  }
+  const next = await findOpenBillingChunk(program, Math.floor(nextDueTs / SECONDS_PER_DAY));
+
+  return {
+    billingBucket,
+    nextBillingBucket: next.billingBucket,
+    nextBillingChunk: next.bucketChunk,
+  };
+}
+
+// The program expects the creator's merchant PDA whether or not the creator
+// has registered as a merchant.
+function deriveMerchant(
+  program: Program<SublySolanaProgram>,
+  creator: PublicKey | undefined,
+): PublicKey {
+  if (!creator) {
+    throw new Error("Service creator not found in the subscription registry");
+  }
+  const [merchantPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from(SEED_MERCHANT), creator.toBuffer()],
+    program.programId,
+  );
+  return merchantPda;
+}
+
 async function decodeEvents(
   provider: AnchorProvider,
   eventCoder: anchor.BorshEventCoder,
@@ -0,0 +1,133 @@
+import { createHash } from "crypto";
+import { PublicKey } from "@solana/web3.js";
+import nacl from "tweetnacl";
+
+// Receivers are sealed with a NaCl box from a throwaway X25519 key to the
+// operator key published in SublyConfig.receiverEncryptionKey. The sealed
+// plaintext is the rail's canonical receiver string (see canonicalReceiver).
+
+export type EncryptedReceiverRaw = {
+  operatorKey: number[];
+  ephemeralKey: number[];
+  nonce: number[];
+  ciphertext: Buffer | number[];
+};
+
+// Decoded `PayoutRecipient` enum as returned by the Anchor coder.
+export type PayoutRecipientRaw = {
+  payPal?: { recipientType: Record<string, unknown>; receiver: string };
+  venmo?: { handle: string };
+  iban?: { iban: string };
+  ach?: { routingNumber: string; accountNumber: string };
+  wallet?: { address: PublicKey };
+  encrypted?: { rail: Record<string, unknown>; receiverHash: number[]; receiver: EncryptedReceiverRaw };
+};
+
+// Mirrors PayoutRecipient::canonical_receiver on-chain.
+export function canonicalReceiver(recipient: PayoutRecipientRaw): string | null {
+  if (recipient.payPal) {
+    return recipient.payPal.receiver;
+  }
+  if (recipient.venmo) {
+    return recipient.venmo.handle;
+  }
+  if (recipient.iban) {
+    return recipient.iban.iban;
+  }
+  if (recipient.ach) {
+    return `${recipient.ach.routingNumber}:${recipient.ach.accountNumber}`;
+  }
+  if (recipient.wallet) {
+    return recipient.wallet.address.toBase58();
+  }
+  return null;
+}
+
+export type PayoutOverrideRaw = {
+  subscriptionId: { toString(): string };
+  recipient: PayoutRecipientRaw;
+};
+
+// A per-subscription override wins over the account wide recipient.
+export function payoutRecipientFor(
+  account: { payoutRecipient: PayoutRecipientRaw | null; payoutOverrides?: PayoutOverrideRaw[] },
+  subscriptionId: { toString(): string },
+): PayoutRecipientRaw | null {
+  const payoutOverride = (account.payoutOverrides ?? []).find(
+    (entry) => entry.subscriptionId.toString() === subscriptionId.toString(),
+  );
+  return payoutOverride?.recipient ?? account.payoutRecipient;
+}
+
+export function receiverHash(user: PublicKey, receiver: string): Buffer {
+  return createHash("sha256").update(user.toBuffer()).update(Buffer.from(receiver, "utf8")).digest();
+}
+
+export function encryptReceiver(user: PublicKey, receiver: string, operatorKey: Uint8Array) {
+  const ephemeral = nacl.box.keyPair();
+  const nonce = nacl.randomBytes(nacl.box.nonceLength);
+  const ciphertext = nacl.box(Buffer.from(receiver, "utf8"), nonce, operatorKey, ephemeral.secretKey);
+  return {
+    receiverHash: Array.from(receiverHash(user, receiver)),
+    operatorKey: Array.from(operatorKey),
+    ephemeralKey: Array.from(ephemeral.publicKey),
+    nonce: Array.from(nonce),
+    ciphertext: Buffer.from(ciphertext),
+  };
+}
+
+export function loadOperatorSecretKey(): Uint8Array | null {
+  const raw = process.env.RECEIVER_ENCRYPTION_SECRET_KEY?.trim();
+  if (!raw) {
+    return null;
+  }
+  const secretKey = Buffer.from(raw, "base64");
+  if (secretKey.length !== nacl.box.secretKeyLength) {
+    throw new Error("RECEIVER_ENCRYPTION_SECRET_KEY must be a base64 encoded 32-byte X25519 secret key");
+  }
+  return new Uint8Array(secretKey);
+}
+
+// Returns the canonical receiver, or null when it cannot be recovered or does
+// not match the hash committed on-chain.
+export function resolveReceiver(
+  user: PublicKey,
+  recipient: PayoutRecipientRaw | null,
+  operatorSecretKey: Uint8Array | null,
+): string | null {
+  if (!recipient) {
+    return null;
+  }
+  const encrypted = recipient.encrypted;
+  if (!encrypted) {
+    return canonicalReceiver(recipient);
+  }
+
+  if (!operatorSecretKey) {
+    console.warn("  -> Receiver is encrypted but RECEIVER_ENCRYPTION_SECRET_KEY is not set.");
+    return null;
+  }
+  const sealed = encrypted.receiver;
+  const operatorPublicKey = nacl.box.keyPair.fromSecretKey(operatorSecretKey).publicKey;
+  if (!Buffer.from(sealed.operatorKey).equals(Buffer.from(operatorPublicKey))) {
+    console.warn("  -> Receiver was sealed to a different operator key.");
+    return null;
+  }
+  const plaintext = nacl.box.open(
+    Uint8Array.from(sealed.ciphertext),
+    Uint8Array.from(sealed.nonce),
+    Uint8Array.from(sealed.ephemeralKey),
+    operatorSecretKey,
+  );
+  if (!plaintext) {
+    console.warn("  -> Failed to decrypt receiver.");
+    return null;
+  }
+
+  const receiver = Buffer.from(plaintext).toString("utf8");
+  if (!receiverHash(user, receiver).equals(Buffer.from(encrypted.receiverHash))) {
+    console.warn("  -> Receiver does not match the on-chain receiver hash.");
+    return null;
+  }
+  return receiver;
+}
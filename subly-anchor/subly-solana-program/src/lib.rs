@@ -5,43 +5,110 @@ use anchor_lang::prelude::*;
 pub use subly::constants as subly_constants;
 pub use subly::instructions::claim_operator::ClaimOperator;
 pub use subly::instructions::claim_user::ClaimUser;
+pub use subly::instructions::clear_paypal_recipient::{
+    ClearPayPalRecipient, PayPalRecipientCleared,
+};
+pub use subly::instructions::contribute_to_group::{ContributeToGroup, GroupContributionAdded};
+pub use subly::instructions::crank_due_bucket::{
+    BillingBucketPage, CrankDueBucket, DueBucketCranked,
+};
+pub use subly::instructions::create_group_budget::{CreateGroupBudget, GroupBudgetCreated};
 pub use subly::instructions::find_due_subscriptions::{
-    DueSubscriptionInfo, FindDueSubscriptions, FindDueSubscriptionsArgs, SubscriptionsDue,
+    DueCursor, DueSubscriptionInfo, DueSubscriptionsScan, FindDueSubscriptions,
+    FindDueSubscriptionsArgs, SkippedDueEntry, SubscriptionsDue,
 };
 pub use subly::instructions::fund_rewards::FundRewards;
-pub use subly::instructions::get_paypal_recipient::{GetPayPalRecipient, PayPalRecipientFetched};
+pub use subly::instructions::get_gifted_subscriptions::{
+    GetGiftedSubscriptions, GiftedSubscriptionInfo, GiftedSubscriptionsFetched,
+    GiftedSubscriptionsPage,
+};
+pub use subly::instructions::get_merchant_subscribers::{
+    GetMerchantSubscribers, MerchantSubscriberInfo, MerchantSubscribersFetched,
+    MerchantSubscribersPage,
+};
+pub use subly::instructions::get_paypal_recipient::{
+    GetPayPalRecipient, PayPalRecipientFetched, PayPalRecipientInfo,
+};
 pub use subly::instructions::get_subscription_services::{
     GetSubscriptionServices, SubscriptionServiceInfo, SubscriptionServicesFetched,
+    SubscriptionServicesPage,
 };
 pub use subly::instructions::get_user_available_services::{
-    GetUserAvailableServices, UserAvailableServicesFetched,
+    GetUserAvailableServices, UserAvailableServicesFetched, UserAvailableServicesPage,
+};
+pub use subly::instructions::get_user_stake::{
+    GetUserStake, StakeEntrySnapshot, UserStakeFetched, UserStakePage,
 };
-pub use subly::instructions::get_user_stake::{GetUserStake, StakeEntrySnapshot, UserStakeFetched};
 pub use subly::instructions::get_user_subscriptions::{
-    GetUserSubscriptions, UserSubscriptionInfo, UserSubscriptionsFetched,
+    GetUserSubscriptions, UserSubscriptionInfo, UserSubscriptionsFetched, UserSubscriptionsPage,
 };
+pub use subly::instructions::init_beneficiary_gifts::InitBeneficiaryGifts;
+pub use subly::instructions::init_billing_bucket::InitBillingBucket;
+pub use subly::instructions::init_user_subscriptions::InitUserSubscriptions;
 pub use subly::instructions::initialize::{Initialize, InitializeArgs};
+pub use subly::instructions::migrate_config::{ConfigMigrated, MigrateConfig};
+pub use subly::instructions::migrate_user_subscriptions::{
+    MigrateUserSubscriptions, UserSubscriptionsMigrated,
+};
+pub use subly::instructions::quote_stake_for_services::{
+    QuoteStakeForServices, QuoteStakeForServicesArgs, StakeQuote, StakeQuoted,
+};
 pub use subly::instructions::record_subscription_payment::{
     RecordSubscriptionPayment, RecordSubscriptionPaymentArgs, SubscriptionPaymentRecorded,
 };
+pub use subly::instructions::register_encrypted_paypal_recipient::{
+    RegisterEncryptedPayPalRecipient, RegisterEncryptedPayPalRecipientArgs,
+};
+pub use subly::instructions::register_merchant::{
+    MerchantRegistered, RegisterMerchant, RegisterMerchantArgs,
+};
+pub use subly::instructions::register_payout_recipient::{
+    PayoutRecipientRegistered, RegisterPayoutRecipient, RegisterPayoutRecipientArgs,
+};
 pub use subly::instructions::register_paypal_recipient::{
-    PayPalRecipientRegistered, RegisterPayPalRecipient, RegisterPayPalRecipientArgs,
+    RegisterPayPalRecipient, RegisterPayPalRecipientArgs,
 };
 pub use subly::instructions::register_subscription_service::{
     RegisterSubscriptionService, RegisterSubscriptionServiceArgs, SubscriptionServiceRegistered,
 };
+pub use subly::instructions::remove_group_member::{GroupMemberRemoved, RemoveGroupMember};
+pub use subly::instructions::set_group_member::{
+    GroupMemberUpdated, SetGroupMember, SetGroupMemberArgs,
+};
+pub use subly::instructions::set_protocol_fee::{ProtocolFeeUpdated, SetProtocolFee};
+pub use subly::instructions::set_receiver_encryption_key::{
+    ReceiverEncryptionKeyUpdated, SetReceiverEncryptionKey,
+};
+pub use subly::instructions::set_spending_caps::{SetSpendingCaps, SpendingCapsUpdated};
+pub use subly::instructions::set_subscription_payout_override::{
+    SetSubscriptionPayoutOverride, SetSubscriptionPayoutOverrideArgs,
+    SubscriptionPayoutOverrideUpdated,
+};
 pub use subly::instructions::stake::Stake;
 pub use subly::instructions::subscribe_service::{
-    SubscribeService, SubscribeServiceArgs, SubscriptionActivated,
+    GiftArgs, SubscribeService, SubscribeServiceArgs, SubscriptionActivated, SubscriptionGifted,
 };
 pub use subly::instructions::sync_yield::{SyncYield, YieldSnapshot};
+pub use subly::instructions::transfer_position::{
+    PositionTransferred, TransferPosition, TransferPositionArgs,
+};
 pub use subly::instructions::unstake::Unstake;
 pub use subly::instructions::unsubscribe_service::{
     SubscriptionCancellationRequested, UnsubscribeService, UnsubscribeServiceArgs,
 };
+pub use subly::instructions::withdraw_group_contribution::{
+    GroupContributionWithdrawn, WithdrawGroupContribution,
+};
+pub use subly::instructions::withdraw_protocol_fees::{
+    ProtocolFeesWithdrawn, WithdrawProtocolFees,
+};
+pub use subly::pagination::{PageArgs, SkippedAccount};
 pub use subly::state::{
-    PayPalRecipientType, StakeEntry, SublyConfig, SubscriptionRegistry, SubscriptionService,
-    SubscriptionStatus, UserStake, UserSubscription, UserSubscriptions,
+    BeneficiaryGifts, BillingBucket, BillingBucketEntry, BillingSlot, EncryptedReceiver, Gift,
+    GroupBudget, GroupCharge, GroupContribution, GroupMember, Merchant, PayPalRecipientType,
+    PayoutOverride, PayoutRail, PayoutRecipient, ProtocolFeesAccrued, SpendingCaps, StakeEntry,
+    SublyConfig, SubscriptionRegistry, SubscriptionService, SubscriptionStatus, TranchePledge,
+    UserStake, UserSubscription, UserSubscriptions,
 };
 
 pub mod __client_accounts_initialize {
@@ -100,6 +167,10 @@ pub mod __client_accounts_get_user_stake {
     pub use crate::subly::instructions::get_user_stake::__client_accounts_get_user_stake::*;
 }
 
+pub mod __client_accounts_init_user_subscriptions {
+    pub use crate::subly::instructions::init_user_subscriptions::__client_accounts_init_user_subscriptions::*;
+}
+
 pub mod __client_accounts_register_pay_pal_recipient {
     pub use crate::subly::instructions::register_paypal_recipient::__client_accounts_register_pay_pal_recipient::*;
 }
@@ -116,6 +187,98 @@ pub mod __client_accounts_unsubscribe_service {
     pub use crate::subly::instructions::unsubscribe_service::__client_accounts_unsubscribe_service::*;
 }
 
+pub mod __client_accounts_register_merchant {
+    pub use crate::subly::instructions::register_merchant::__client_accounts_register_merchant::*;
+}
+
+pub mod __client_accounts_get_merchant_subscribers {
+    pub use crate::subly::instructions::get_merchant_subscribers::__client_accounts_get_merchant_subscribers::*;
+}
+
+pub mod __client_accounts_set_protocol_fee {
+    pub use crate::subly::instructions::set_protocol_fee::__client_accounts_set_protocol_fee::*;
+}
+
+pub mod __client_accounts_withdraw_protocol_fees {
+    pub use crate::subly::instructions::withdraw_protocol_fees::__client_accounts_withdraw_protocol_fees::*;
+}
+
+pub mod __client_accounts_init_billing_bucket {
+    pub use crate::subly::instructions::init_billing_bucket::__client_accounts_init_billing_bucket::*;
+}
+
+pub mod __client_accounts_crank_due_bucket {
+    pub use crate::subly::instructions::crank_due_bucket::__client_accounts_crank_due_bucket::*;
+}
+
+pub mod __client_accounts_clear_pay_pal_recipient {
+    pub use crate::subly::instructions::clear_paypal_recipient::__client_accounts_clear_pay_pal_recipient::*;
+}
+
+pub mod __client_accounts_set_receiver_encryption_key {
+    pub use crate::subly::instructions::set_receiver_encryption_key::__client_accounts_set_receiver_encryption_key::*;
+}
+
+pub mod __client_accounts_register_encrypted_pay_pal_recipient {
+    pub use crate::subly::instructions::register_encrypted_paypal_recipient::__client_accounts_register_encrypted_pay_pal_recipient::*;
+}
+
+pub mod __client_accounts_register_payout_recipient {
+    pub use crate::subly::instructions::register_payout_recipient::__client_accounts_register_payout_recipient::*;
+}
+
+pub mod __client_accounts_migrate_config {
+    pub use crate::subly::instructions::migrate_config::__client_accounts_migrate_config::*;
+}
+
+pub mod __client_accounts_migrate_user_subscriptions {
+    pub use crate::subly::instructions::migrate_user_subscriptions::__client_accounts_migrate_user_subscriptions::*;
+}
+
+pub mod __client_accounts_set_subscription_payout_override {
+    pub use crate::subly::instructions::set_subscription_payout_override::__client_accounts_set_subscription_payout_override::*;
+}
+
+pub mod __client_accounts_init_beneficiary_gifts {
+    pub use crate::subly::instructions::init_beneficiary_gifts::__client_accounts_init_beneficiary_gifts::*;
+}
+
+pub mod __client_accounts_get_gifted_subscriptions {
+    pub use crate::subly::instructions::get_gifted_subscriptions::__client_accounts_get_gifted_subscriptions::*;
+}
+
+pub mod __client_accounts_create_group_budget {
+    pub use crate::subly::instructions::create_group_budget::__client_accounts_create_group_budget::*;
+}
+
+pub mod __client_accounts_set_group_member {
+    pub use crate::subly::instructions::set_group_member::__client_accounts_set_group_member::*;
+}
+
+pub mod __client_accounts_remove_group_member {
+    pub use crate::subly::instructions::remove_group_member::__client_accounts_remove_group_member::*;
+}
+
+pub mod __client_accounts_contribute_to_group {
+    pub use crate::subly::instructions::contribute_to_group::__client_accounts_contribute_to_group::*;
+}
+
+pub mod __client_accounts_withdraw_group_contribution {
+    pub use crate::subly::instructions::withdraw_group_contribution::__client_accounts_withdraw_group_contribution::*;
+}
+
+pub mod __client_accounts_set_spending_caps {
+    pub use crate::subly::instructions::set_spending_caps::__client_accounts_set_spending_caps::*;
+}
+
+pub mod __client_accounts_quote_stake_for_services {
+    pub use crate::subly::instructions::quote_stake_for_services::__client_accounts_quote_stake_for_services::*;
+}
+
+pub mod __client_accounts_transfer_position {
+    pub use crate::subly::instructions::transfer_position::__client_accounts_transfer_position::*;
+}
+
 declare_id!("C1gJtFGfd2Tt3omV6eWvezeofymZbp7RYj94Hg4drWq1");
 
 #[program]
@@ -157,8 +320,11 @@ pub mod subly_solana_program {
         subly::instructions::register_subscription_service::handler(ctx, args)
     }
 
-    pub fn get_subscription_services(ctx: Context<GetSubscriptionServices>) -> Result<()> {
-        subly::instructions::get_subscription_services::handler(ctx)
+    pub fn get_subscription_services(
+        ctx: Context<GetSubscriptionServices>,
+        page: PageArgs,
+    ) -> Result<SubscriptionServicesPage> {
+        subly::instructions::get_subscription_services::handler(ctx, page)
     }
 
     pub fn subscribe_service(
@@ -168,20 +334,34 @@ pub mod subly_solana_program {
         subly::instructions::subscribe_service::handler(ctx, args)
     }
 
-    pub fn get_user_available_services(ctx: Context<GetUserAvailableServices>) -> Result<()> {
-        subly::instructions::get_user_available_services::handler(ctx)
+    pub fn get_user_available_services(
+        ctx: Context<GetUserAvailableServices>,
+        page: PageArgs,
+    ) -> Result<UserAvailableServicesPage> {
+        subly::instructions::get_user_available_services::handler(ctx, page)
     }
 
-    pub fn get_user_subscriptions(ctx: Context<GetUserSubscriptions>) -> Result<()> {
-        subly::instructions::get_user_subscriptions::handler(ctx)
+    pub fn get_user_subscriptions(
+        ctx: Context<GetUserSubscriptions>,
+        page: PageArgs,
+    ) -> Result<UserSubscriptionsPage> {
+        subly::instructions::get_user_subscriptions::handler(ctx, page)
     }
 
-    pub fn get_paypal_recipient(ctx: Context<GetPayPalRecipient>) -> Result<()> {
+    pub fn get_paypal_recipient(ctx: Context<GetPayPalRecipient>) -> Result<PayPalRecipientInfo> {
         subly::instructions::get_paypal_recipient::handler(ctx)
     }
 
-    pub fn get_user_stake(ctx: Context<GetUserStake>) -> Result<()> {
-        subly::instructions::get_user_stake::handler(ctx)
+    pub fn get_user_stake(ctx: Context<GetUserStake>, page: PageArgs) -> Result<UserStakePage> {
+        subly::instructions::get_user_stake::handler(ctx, page)
+    }
+
+    pub fn clear_paypal_recipient(ctx: Context<ClearPayPalRecipient>) -> Result<()> {
+        subly::instructions::clear_paypal_recipient::handler(ctx)
+    }
+
+    pub fn init_user_subscriptions(ctx: Context<InitUserSubscriptions>) -> Result<()> {
+        subly::instructions::init_user_subscriptions::handler(ctx)
     }
 
     pub fn register_paypal_recipient(
@@ -194,7 +374,7 @@ pub mod subly_solana_program {
     pub fn find_due_subscriptions(
         ctx: Context<FindDueSubscriptions>,
         args: FindDueSubscriptionsArgs,
-    ) -> Result<()> {
+    ) -> Result<DueSubscriptionsScan> {
         subly::instructions::find_due_subscriptions::handler(ctx, args)
     }
 
@@ -211,4 +391,133 @@ pub mod subly_solana_program {
     ) -> Result<()> {
         subly::instructions::unsubscribe_service::handler(ctx, args)
     }
+
+    pub fn register_merchant(
+        ctx: Context<RegisterMerchant>,
+        args: RegisterMerchantArgs,
+    ) -> Result<()> {
+        subly::instructions::register_merchant::handler(ctx, args)
+    }
+
+    pub fn get_merchant_subscribers(
+        ctx: Context<GetMerchantSubscribers>,
+        page: PageArgs,
+    ) -> Result<MerchantSubscribersPage> {
+        subly::instructions::get_merchant_subscribers::handler(ctx, page)
+    }
+
+    pub fn set_protocol_fee(ctx: Context<SetProtocolFee>, fee_bps: u16) -> Result<()> {
+        subly::instructions::set_protocol_fee::handler(ctx, fee_bps)
+    }
+
+    pub fn withdraw_protocol_fees(ctx: Context<WithdrawProtocolFees>, amount: u64) -> Result<()> {
+        subly::instructions::withdraw_protocol_fees::handler(ctx, amount)
+    }
+
+    pub fn init_billing_bucket(
+        ctx: Context<InitBillingBucket>,
+        day: i64,
+        chunk: u16,
+    ) -> Result<()> {
+        subly::instructions::init_billing_bucket::handler(ctx, day, chunk)
+    }
+
+    pub fn crank_due_bucket(
+        ctx: Context<CrankDueBucket>,
+        chunk: u16,
+        page: PageArgs,
+    ) -> Result<BillingBucketPage> {
+        subly::instructions::crank_due_bucket::handler(ctx, chunk, page)
+    }
+
+    pub fn set_receiver_encryption_key(
+        ctx: Context<SetReceiverEncryptionKey>,
+        key: [u8; 32],
+    ) -> Result<()> {
+        subly::instructions::set_receiver_encryption_key::handler(ctx, key)
+    }
+
+    pub fn register_encrypted_paypal_recipient(
+        ctx: Context<RegisterEncryptedPayPalRecipient>,
+        args: RegisterEncryptedPayPalRecipientArgs,
+    ) -> Result<()> {
+        subly::instructions::register_encrypted_paypal_recipient::handler(ctx, args)
+    }
+
+    pub fn register_payout_recipient(
+        ctx: Context<RegisterPayoutRecipient>,
+        args: RegisterPayoutRecipientArgs,
+    ) -> Result<()> {
+        subly::instructions::register_payout_recipient::handler(ctx, args)
+    }
+
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        subly::instructions::migrate_config::handler(ctx)
+    }
+
+    pub fn migrate_user_subscriptions(ctx: Context<MigrateUserSubscriptions>) -> Result<()> {
+        subly::instructions::migrate_user_subscriptions::handler(ctx)
+    }
+
+    pub fn set_subscription_payout_override(
+        ctx: Context<SetSubscriptionPayoutOverride>,
+        args: SetSubscriptionPayoutOverrideArgs,
+    ) -> Result<()> {
+        subly::instructions::set_subscription_payout_override::handler(ctx, args)
+    }
+
+    pub fn init_beneficiary_gifts(
+        ctx: Context<InitBeneficiaryGifts>,
+        beneficiary: Pubkey,
+    ) -> Result<()> {
+        subly::instructions::init_beneficiary_gifts::handler(ctx, beneficiary)
+    }
+
+    pub fn get_gifted_subscriptions(
+        ctx: Context<GetGiftedSubscriptions>,
+        page: PageArgs,
+    ) -> Result<GiftedSubscriptionsPage> {
+        subly::instructions::get_gifted_subscriptions::handler(ctx, page)
+    }
+
+    pub fn create_group_budget(ctx: Context<CreateGroupBudget>, group_id: u64) -> Result<()> {
+        subly::instructions::create_group_budget::handler(ctx, group_id)
+    }
+
+    pub fn set_group_member(ctx: Context<SetGroupMember>, args: SetGroupMemberArgs) -> Result<()> {
+        subly::instructions::set_group_member::handler(ctx, args)
+    }
+
+    pub fn remove_group_member(ctx: Context<RemoveGroupMember>, member: Pubkey) -> Result<()> {
+        subly::instructions::remove_group_member::handler(ctx, member)
+    }
+
+    pub fn contribute_to_group(ctx: Context<ContributeToGroup>, tranche_id: u64) -> Result<()> {
+        subly::instructions::contribute_to_group::handler(ctx, tranche_id)
+    }
+
+    pub fn withdraw_group_contribution(
+        ctx: Context<WithdrawGroupContribution>,
+        tranche_id: u64,
+    ) -> Result<()> {
+        subly::instructions::withdraw_group_contribution::handler(ctx, tranche_id)
+    }
+
+    pub fn set_spending_caps(ctx: Context<SetSpendingCaps>, caps: SpendingCaps) -> Result<()> {
+        subly::instructions::set_spending_caps::handler(ctx, caps)
+    }
+
+    pub fn quote_stake_for_services(
+        ctx: Context<QuoteStakeForServices>,
+        args: QuoteStakeForServicesArgs,
+    ) -> Result<StakeQuote> {
+        subly::instructions::quote_stake_for_services::handler(ctx, args)
+    }
+
+    pub fn transfer_position(
+        ctx: Context<TransferPosition>,
+        args: TransferPositionArgs,
+    ) -> Result<()> {
+        subly::instructions::transfer_position::handler(ctx, args)
+    }
 }
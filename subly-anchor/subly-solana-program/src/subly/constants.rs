@@ -15,6 +15,25 @@ pub const SUBSCRIPTION_REGISTRY_SEED: &str = "subscription_registry";
 #[constant]
 pub const USER_SUBSCRIPTIONS_SEED: &str = "user_subscriptions";
 
+#[constant]
+pub const MERCHANT_SEED: &str = "merchant";
+
+#[constant]
+pub const BILLING_BUCKET_SEED: &str = "billing_bucket";
+
+/// Entries per billing bucket chunk; a busy day spills into further chunks.
+#[constant]
+pub const MAX_BILLING_BUCKET_ENTRIES: usize = 256;
+
+#[constant]
+pub const BENEFICIARY_GIFTS_SEED: &str = "beneficiary_gifts";
+
+#[constant]
+pub const GROUP_BUDGET_SEED: &str = "group_budget";
+
+#[constant]
+pub const TRANCHE_PLEDGE_SEED: &str = "tranche_pledge";
+
 #[constant]
 pub const INDEX_SCALE: u128 = 1_000_000_000_000u128;
 
@@ -27,6 +46,14 @@ pub const SECONDS_PER_YEAR: u64 = 31_536_000;
 #[constant]
 pub const DEFAULT_APY_BPS: u16 = 1_000;
 
+#[constant]
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 2_000;
+
+/// Protocol fee changes remembered for positions that have not synced since.
+/// Older changes are merged at the lower of their rates.
+#[constant]
+pub const MAX_FEE_CHECKPOINTS: usize = 8;
+
 #[constant]
 pub const SECONDS_PER_DAY: i64 = 86_400;
 
@@ -56,4 +83,24 @@ pub const MAX_SERVICE_PROVIDER_LEN: usize = 128;
 #[constant]
 pub const BILLING_PERIOD_SECONDS: i64 = 30 * SECONDS_PER_DAY;
 
-pub const MAX_PAYPAL_RECEIVER_LEN: usize = 256;
+pub const MAX_PAYOUT_RECEIVER_LEN: usize = 256;
+
+/// NaCl box ciphertexts carry a 16-byte Poly1305 tag on top of the plaintext.
+pub const MAX_ENCRYPTED_RECEIVER_LEN: usize = MAX_PAYOUT_RECEIVER_LEN + 16;
+
+/// Stored right after the subscriptions vector. The legacy PayPal layout kept
+/// a bool there, so any value above 1 marks a migrated account.
+pub const PAYOUT_LAYOUT_VERSION: u8 = 2;
+
+pub const MAX_MERCHANT_NAME_LEN: usize = 64;
+
+/// Bounds the member accounts a group-funded subscription has to load.
+pub const MAX_GROUP_MEMBERS: usize = 16;
+
+pub const MAX_VIEW_PAGE_SIZE: usize = 32;
+
+pub const MAX_DUE_PAGE_SIZE: usize = 16;
+
+/// Skipped entries reported per due scan, so bad accounts cannot overflow
+/// the `SubscriptionsDue` event.
+pub const MAX_DUE_SKIPPED: usize = 16;
@@ -46,8 +46,84 @@ pub enum ErrorCode {
     InvalidSubscriptionAccount,
     #[msg("Invalid PayPal recipient type")]
     InvalidPayPalRecipientType,
-    #[msg("PayPal recipient information is not configured")]
+    #[msg("Payout recipient information is not configured")]
     PayPalInfoMissing,
     #[msg("Subscription is not payable in its current state")]
     SubscriptionNotPayable,
+    #[msg("Invalid merchant account for the selected service")]
+    InvalidMerchantAccount,
+    #[msg("Protocol fee exceeds the allowed maximum")]
+    ProtocolFeeTooHigh,
+    #[msg("A single view entry does not fit in the return data buffer")]
+    ViewPageTooLarge,
+    #[msg("Billing bucket does not match the subscription due day")]
+    InvalidBillingBucket,
+    #[msg("Billing bucket accounts are required to reschedule this subscription")]
+    BillingBucketMissing,
+    #[msg("PayPal email receiver is not a valid email address")]
+    InvalidPayPalEmail,
+    #[msg("PayPal phone receiver must be an E.164 number such as +14155550123")]
+    InvalidPayPalPhone,
+    #[msg("PayPal ID may only contain letters and digits")]
+    InvalidPayPalId,
+    #[msg("PayPal user handle may only contain letters, digits, '.', '_' and '-'")]
+    InvalidPayPalUserHandle,
+    #[msg("PayPal recipient cannot be cleared while a subscription is active")]
+    PayPalRecipientInUse,
+    #[msg("The operator has not published a receiver encryption key")]
+    ReceiverEncryptionKeyNotSet,
+    #[msg("Encrypted receiver was sealed to a stale operator key")]
+    ReceiverEncryptionKeyMismatch,
+    #[msg("Encrypted receiver payload is empty or too large")]
+    InvalidEncryptedReceiver,
+    #[msg("Unknown payout rail")]
+    InvalidPayoutRail,
+    #[msg("Venmo handle must be 5-30 letters, digits, '_' or '-'")]
+    InvalidVenmoHandle,
+    #[msg("IBAN must be compact, upper case and pass the mod-97 check")]
+    InvalidIban,
+    #[msg("ACH recipient needs a valid 9-digit routing number and a 4-17 digit account number")]
+    InvalidAchAccount,
+    #[msg("Payout wallet must be a non-default, on-curve address")]
+    InvalidPayoutWallet,
+    #[msg("Gifts must carry a payout recipient override for the beneficiary")]
+    GiftPayoutOverrideMissing,
+    #[msg("Gift beneficiary must be a wallet other than the sponsor")]
+    InvalidGiftBeneficiary,
+    #[msg("Beneficiary gifts account does not match the gift beneficiary")]
+    InvalidBeneficiaryGifts,
+    #[msg("Group budget account does not match its admin and id")]
+    InvalidGroupBudget,
+    #[msg("Wallet is not a member of this group budget")]
+    GroupMemberNotFound,
+    #[msg("Group budget already has the maximum number of members")]
+    GroupMemberLimitReached,
+    #[msg("Every group member's subscriptions account must be provided exactly once")]
+    GroupMembersMissing,
+    #[msg("Subscription exceeds the member's group spending limit")]
+    GroupSpendingLimitExceeded,
+    #[msg("Group member still has contributions or group-funded subscriptions")]
+    GroupMemberInUse,
+    #[msg("Tranche is pledged to a group budget")]
+    TranchePledged,
+    #[msg("Budget share must be at most 10000 basis points")]
+    InvalidSpendingCaps,
+    #[msg("Subscription exceeds the user's spending cap")]
+    SpendingCapExceeded,
+    #[msg("Service price exceeds the user's per-service ceiling")]
+    ServicePriceAboveCap,
+    #[msg("No yield is being paid, so no stake can fund subscriptions")]
+    YieldUnavailable,
+    #[msg(
+        "Encrypted payout receivers are bound to the old wallet; seal them again for the new one"
+    )]
+    EncryptedRecipientRebindRequired,
+    #[msg("Config account is not a Subly config")]
+    InvalidConfigAccount,
+    #[msg("Billing bucket chunk is full; use the next chunk for this day")]
+    BillingBucketFull,
+    #[msg("A billing bucket chunk can only be opened after the previous chunk fills")]
+    BillingBucketChunkOutOfOrder,
+    #[msg("Payout receiver is empty or too long")]
+    InvalidPayoutReceiver,
 }
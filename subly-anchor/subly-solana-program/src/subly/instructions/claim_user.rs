@@ -47,7 +47,8 @@ pub fn handler(ctx: Context<ClaimUser>, amount: u64) -> Result<()> {
     config.accrue_to(now)?;
 
     let user_position = &mut ctx.accounts.user_position;
-    user_position.sync_against_index(config.acc_index, now)?;
+    let (accrued, protocol_fees) = user_position.sync_against_index(config, now)?;
+    config.record_protocol_fees(user_position.owner, accrued, protocol_fees)?;
 
     let available = user_position.available_yield_for_user(now)?;
     let desired = if amount == 0 {
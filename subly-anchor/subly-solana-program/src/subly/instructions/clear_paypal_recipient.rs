@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::USER_SUBSCRIPTIONS_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::UserSubscriptions;
+
+#[event]
+pub struct PayPalRecipientCleared {
+    pub user: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct ClearPayPalRecipient<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_subscriptions.bump,
+    )]
+    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<ClearPayPalRecipient>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let user_key = ctx.accounts.user.key();
+
+    require_keys_eq!(
+        ctx.accounts.user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    // Subscriptions still awaiting payouts need somewhere to send them.
+    ctx.accounts.user_subscriptions.refresh(now)?;
+    require!(
+        !ctx.accounts.user_subscriptions.has_active_or_pending(),
+        ErrorCode::PayPalRecipientInUse
+    );
+
+    ctx.accounts.user_subscriptions.clear_payout_recipient();
+    // Older layouts are upgraded on write, which may need more space.
+    ctx.accounts.user_subscriptions.reserve_tail(
+        &ctx.accounts.user_subscriptions.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    emit!(PayPalRecipientCleared { user: user_key });
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anchor_lang::{prelude::*, AccountDeserialize};
+
+use crate::subly::constants::{
+    CONFIG_SEED, GROUP_BUDGET_SEED, TRANCHE_PLEDGE_SEED, USER_POSITION_SEED,
+    USER_SUBSCRIPTIONS_SEED,
+};
+use crate::subly::error::ErrorCode;
+use crate::subly::instructions::subscribe_service::compute_monthly_budget;
+use crate::subly::state::{GroupBudget, SublyConfig, TranchePledge, UserStake, UserSubscriptions};
+
+#[event]
+pub struct GroupContributionAdded {
+    pub group: Pubkey,
+    pub member: Pubkey,
+    pub tranche_id: u64,
+    pub principal: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(tranche_id: u64)]
+pub struct ContributeToGroup<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            GROUP_BUDGET_SEED.as_bytes(),
+            group_budget.admin.as_ref(),
+            &group_budget.group_id.to_le_bytes(),
+        ],
+        bump = group_budget.bump,
+    )]
+    pub group_budget: Account<'info, GroupBudget>,
+    #[account(
+        mut,
+        seeds = [USER_POSITION_SEED.as_bytes(), member.key().as_ref()],
+        bump = user_position.bump,
+        constraint = user_position.owner == member.key() @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub user_position: Account<'info, UserStake>,
+    /// CHECK: May not exist yet; read only to keep the member's own
+    /// subscriptions covered by what stays in their position.
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), member.key().as_ref()],
+        bump,
+    )]
+    pub user_subscriptions: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = member,
+        space = TranchePledge::LEN,
+        seeds = [
+            TRANCHE_PLEDGE_SEED.as_bytes(),
+            member.key().as_ref(),
+            &tranche_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub tranche_pledge: Account<'info, TranchePledge>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<ContributeToGroup>, tranche_id: u64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let member_key = ctx.accounts.member.key();
+    let group_key = ctx.accounts.group_budget.key();
+
+    ctx.accounts.config.ensure_active()?;
+    ctx.accounts.group_budget.member(member_key)?;
+
+    let principal = ctx
+        .accounts
+        .user_position
+        .find_entry_mut(tranche_id)
+        .ok_or(ErrorCode::InvalidTranche)?
+        .principal;
+    require!(principal > 0, ErrorCode::InvalidTranche);
+
+    let group_budget = &mut ctx.accounts.group_budget;
+    group_budget.add_contribution(member_key, tranche_id, principal);
+    group_budget.reserve(
+        &group_budget.to_account_info(),
+        &ctx.accounts.member.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // The tranche keeps accruing in the position but no longer backs the
+    // member's own budget.
+    let user_position = &mut ctx.accounts.user_position;
+    user_position.total_principal = user_position
+        .total_principal
+        .checked_sub(principal)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let subscriptions_info = ctx.accounts.user_subscriptions.to_account_info();
+    if !subscriptions_info.data_is_empty() {
+        let data_ref = subscriptions_info.try_borrow_data()?;
+        let mut data_slice: &[u8] = &data_ref;
+        let mut user_subscriptions = UserSubscriptions::try_deserialize(&mut data_slice)?;
+        drop(data_ref);
+        user_subscriptions.refresh(now)?;
+        let monthly_budget = compute_monthly_budget(
+            user_position.total_principal,
+            ctx.accounts.config.net_apy_bps(),
+        )?;
+        require!(
+            user_subscriptions.personal_committed()? <= monthly_budget,
+            ErrorCode::SubscriptionBudgetExceeded
+        );
+    }
+
+    let tranche_pledge = &mut ctx.accounts.tranche_pledge;
+    tranche_pledge.owner = member_key;
+    tranche_pledge.tranche_id = tranche_id;
+    tranche_pledge.group = group_key;
+    tranche_pledge.principal = principal;
+    tranche_pledge.bump = ctx.bumps.tranche_pledge;
+
+    emit!(GroupContributionAdded {
+        group: group_key,
+        member: member_key,
+        tranche_id,
+        principal,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::BILLING_BUCKET_SEED;
+use crate::subly::pagination::{Page, PageArgs};
+use crate::subly::state::{BillingBucket, BillingBucketEntry};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BillingBucketPage {
+    pub day: i64,
+    pub chunk: u16,
+    pub entries: Vec<BillingBucketEntry>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+}
+
+#[event]
+pub struct DueBucketCranked {
+    pub day: i64,
+    pub chunk: u16,
+    pub entries: Vec<BillingBucketEntry>,
+}
+
+#[derive(Accounts)]
+#[instruction(chunk: u16)]
+pub struct CrankDueBucket<'info> {
+    #[account(
+        seeds = [
+            BILLING_BUCKET_SEED.as_bytes(),
+            &billing_bucket.day.to_le_bytes(),
+            &BillingBucket::chunk_seed(chunk),
+        ],
+        bump = billing_bucket.bump,
+    )]
+    pub billing_bucket: Account<'info, BillingBucket>,
+}
+
+/// Lists one chunk of a day's bucket. Keepers walk chunks from 0 until the
+/// next one does not exist.
+pub fn handler(
+    ctx: Context<CrankDueBucket>,
+    chunk: u16,
+    page: PageArgs,
+) -> Result<BillingBucketPage> {
+    let bucket = &ctx.accounts.billing_bucket;
+    // day (8) and chunk (2) precede the paged entries.
+    let page = Page::from_items(bucket.entries.clone(), page, 8 + 2)?;
+
+    emit!(DueBucketCranked {
+        day: bucket.day,
+        chunk,
+        entries: page.items.clone(),
+    });
+
+    Ok(BillingBucketPage {
+        day: bucket.day,
+        chunk,
+        entries: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+    })
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::GROUP_BUDGET_SEED;
+use crate::subly::state::GroupBudget;
+
+#[event]
+pub struct GroupBudgetCreated {
+    pub group: Pubkey,
+    pub admin: Pubkey,
+    pub group_id: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: u64)]
+pub struct CreateGroupBudget<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = GroupBudget::INITIAL_SIZE,
+        seeds = [GROUP_BUDGET_SEED.as_bytes(), admin.key().as_ref(), &group_id.to_le_bytes()],
+        bump,
+    )]
+    pub group_budget: Account<'info, GroupBudget>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<CreateGroupBudget>, group_id: u64) -> Result<()> {
+    let admin_key = ctx.accounts.admin.key();
+    let group_budget = &mut ctx.accounts.group_budget;
+    group_budget.admin = admin_key;
+    group_budget.group_id = group_id;
+    group_budget.bump = ctx.bumps.group_budget;
+    // The admin is a member without a limit of their own.
+    group_budget.set_member(admin_key, 0)?;
+
+    emit!(GroupBudgetCreated {
+        group: group_budget.key(),
+        admin: admin_key,
+        group_id,
+    });
+
+    Ok(())
+}
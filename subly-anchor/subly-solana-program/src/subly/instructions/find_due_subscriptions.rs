@@ -1,6 +1,8 @@
-use anchor_lang::{prelude::*, AccountDeserialize};
+use anchor_lang::prelude::*;
 
-use crate::subly::constants::{CONFIG_SEED, SUBSCRIPTION_REGISTRY_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::constants::{
+    CONFIG_SEED, MAX_DUE_PAGE_SIZE, MAX_DUE_SKIPPED, SUBSCRIPTION_REGISTRY_SEED,
+};
 use crate::subly::error::ErrorCode;
 use crate::subly::state::{
     SublyConfig, SubscriptionRegistry, SubscriptionStatus, UserSubscriptions,
@@ -9,6 +11,23 @@ use crate::subly::state::{
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct FindDueSubscriptionsArgs {
     pub look_ahead_seconds: i64,
+    pub start_user_index: u32,
+    pub start_subscription_id: u64,
+    pub max_entries: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct DueCursor {
+    pub user_index: u32,
+    pub subscription_id: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SkippedDueEntry {
+    pub account: Pubkey,
+    pub user_index: u32,
+    pub subscription_id: Option<u64>,
+    pub reason: String,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -18,15 +37,27 @@ pub struct DueSubscriptionInfo {
     pub service_id: u64,
     pub service_name: String,
     pub monthly_price_usdc: u64,
+    pub rail: String,
     pub recipient_type: String,
-    pub receiver: String,
+    pub receiver_hash: [u8; 32],
     pub due_ts: i64,
     pub initial_payment_recorded: bool,
 }
 
+/// Returned alongside the `SubscriptionsDue` event so keepers can page
+/// without decoding logs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct DueSubscriptionsScan {
+    pub entry_count: u32,
+    pub skipped_count: u32,
+    pub next_cursor: Option<DueCursor>,
+}
+
 #[event]
 pub struct SubscriptionsDue {
     pub entries: Vec<DueSubscriptionInfo>,
+    pub skipped: Vec<SkippedDueEntry>,
+    pub next_cursor: Option<DueCursor>,
 }
 
 #[derive(Accounts)]
@@ -43,7 +74,10 @@ pub struct FindDueSubscriptions<'info> {
     pub subscription_registry: Account<'info, SubscriptionRegistry>,
 }
 
-pub fn handler(ctx: Context<FindDueSubscriptions>, args: FindDueSubscriptionsArgs) -> Result<()> {
+pub fn handler(
+    ctx: Context<FindDueSubscriptions>,
+    args: FindDueSubscriptionsArgs,
+) -> Result<DueSubscriptionsScan> {
     let now = Clock::get()?.unix_timestamp;
     let upper_bound = now
         .checked_add(args.look_ahead_seconds)
@@ -51,38 +85,45 @@ pub fn handler(ctx: Context<FindDueSubscriptions>, args: FindDueSubscriptionsArg
 
     ctx.accounts.config.ensure_active()?;
 
-    let mut due_entries: Vec<DueSubscriptionInfo> = Vec::new();
+    let max_entries = match args.max_entries as usize {
+        0 => MAX_DUE_PAGE_SIZE,
+        max_entries => max_entries.min(MAX_DUE_PAGE_SIZE),
+    };
 
-    for account_info in ctx.remaining_accounts.iter() {
-        let account_info = account_info.clone();
+    let mut due_entries: Vec<DueSubscriptionInfo> = Vec::new();
+    let mut skipped: Vec<SkippedDueEntry> = Vec::new();
+    let mut next_cursor: Option<DueCursor> = None;
+
+    'accounts: for (index, account_info) in ctx
+        .remaining_accounts
+        .iter()
+        .enumerate()
+        .skip(args.start_user_index as usize)
+    {
+        let user_index = index as u32;
         let account_key = *account_info.key;
-        let data_ref = account_info.try_borrow_data()?;
-        let mut data_slice: &[u8] = &data_ref;
-        let user_subscriptions_account = UserSubscriptions::try_deserialize(&mut data_slice)?;
-        drop(data_ref);
-        let user_key = user_subscriptions_account.owner;
-
-        let (expected_pda, _) = Pubkey::find_program_address(
-            &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
-            &crate::ID,
-        );
-        require_keys_eq!(
-            expected_pda,
-            account_key,
-            ErrorCode::InvalidSubscriptionAccount
-        );
-
-        if !user_subscriptions_account.paypal_configured {
+        let Some(user_subscriptions_account) = UserSubscriptions::load_view(account_info) else {
+            if skipped.len() >= MAX_DUE_SKIPPED {
+                next_cursor = Some(DueCursor {
+                    user_index,
+                    subscription_id: 0,
+                });
+                break;
+            }
+            skipped.push(SkippedDueEntry {
+                account: account_key,
+                user_index,
+                subscription_id: None,
+                reason: "INVALID_ACCOUNT".to_string(),
+            });
             continue;
-        }
-
-        let recipient_type = user_subscriptions_account
-            .paypal_recipient_type
-            .as_str()
-            .to_string();
-        let receiver = user_subscriptions_account.paypal_receiver.clone();
+        };
+        let user_key = user_subscriptions_account.owner;
 
         for subscription in user_subscriptions_account.subscriptions.iter() {
+            if user_index == args.start_user_index && subscription.id < args.start_subscription_id {
+                continue;
+            }
             if subscription.status != SubscriptionStatus::Active {
                 continue;
             }
@@ -90,14 +131,43 @@ pub fn handler(ctx: Context<FindDueSubscriptions>, args: FindDueSubscriptionsArg
             if !initial_payment_pending && subscription.next_billing_ts > upper_bound {
                 continue;
             }
-
+            let recipient = user_subscriptions_account.payout_recipient_for(subscription.id);
             let service = ctx
                 .accounts
                 .subscription_registry
                 .services
                 .iter()
-                .find(|service| service.id == subscription.service_id)
-                .ok_or(ErrorCode::SubscriptionServiceNotFound)?;
+                .find(|service| service.id == subscription.service_id);
+            let (Some(recipient), Some(service)) = (recipient, service) else {
+                if skipped.len() >= MAX_DUE_SKIPPED {
+                    next_cursor = Some(DueCursor {
+                        user_index,
+                        subscription_id: subscription.id,
+                    });
+                    break 'accounts;
+                }
+                let reason = if recipient.is_none() {
+                    "RECIPIENT_MISSING"
+                } else {
+                    "SERVICE_NOT_FOUND"
+                };
+                skipped.push(SkippedDueEntry {
+                    account: account_key,
+                    user_index,
+                    subscription_id: Some(subscription.id),
+                    reason: reason.to_string(),
+                });
+                continue;
+            };
+            let rail = recipient.rail();
+
+            if due_entries.len() >= max_entries {
+                next_cursor = Some(DueCursor {
+                    user_index,
+                    subscription_id: subscription.id,
+                });
+                break 'accounts;
+            }
 
             due_entries.push(DueSubscriptionInfo {
                 user: user_key,
@@ -105,19 +175,26 @@ pub fn handler(ctx: Context<FindDueSubscriptions>, args: FindDueSubscriptionsArg
                 service_id: subscription.service_id,
                 service_name: service.name.clone(),
                 monthly_price_usdc: subscription.monthly_price_usdc,
-                recipient_type: recipient_type.clone(),
-                receiver: receiver.clone(),
+                rail: rail.as_str().to_string(),
+                recipient_type: rail.recipient_type().to_string(),
+                receiver_hash: recipient.receiver_hash(&user_key),
                 due_ts: subscription.next_billing_ts,
                 initial_payment_recorded: subscription.initial_payment_recorded,
             });
         }
     }
 
-    if !due_entries.is_empty() {
-        emit!(SubscriptionsDue {
-            entries: due_entries
-        });
-    }
+    let scan = DueSubscriptionsScan {
+        entry_count: due_entries.len() as u32,
+        skipped_count: skipped.len() as u32,
+        next_cursor,
+    };
+
+    emit!(SubscriptionsDue {
+        entries: due_entries,
+        skipped,
+        next_cursor,
+    });
 
-    Ok(())
+    Ok(scan)
 }
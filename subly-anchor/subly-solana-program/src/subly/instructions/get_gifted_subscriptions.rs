@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{BENEFICIARY_GIFTS_SEED, SUBSCRIPTION_REGISTRY_SEED};
+use crate::subly::pagination::{Page, PageArgs, SkippedAccount};
+use crate::subly::state::{
+    BeneficiaryGifts, SubscriptionRegistry, SubscriptionStatus, UserSubscriptions,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct GiftedSubscriptionInfo {
+    pub sponsor: Pubkey,
+    pub subscription_id: u64,
+    pub service_id: u64,
+    pub service_name: String,
+    pub service_logo_url: String,
+    pub monthly_price_usdc: u64,
+    pub status: String,
+    pub started_at: i64,
+    pub next_billing_ts: i64,
+    pub pending_until_ts: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct GiftedSubscriptionsPage {
+    pub beneficiary: Pubkey,
+    pub subscriptions: Vec<GiftedSubscriptionInfo>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+    pub skipped: Vec<SkippedAccount>,
+}
+
+#[event]
+pub struct GiftedSubscriptionsFetched {
+    pub beneficiary: Pubkey,
+    pub subscriptions: Vec<GiftedSubscriptionInfo>,
+    pub skipped: Vec<SkippedAccount>,
+}
+
+/// Lists the gifts made to `beneficiary`. Pass the `UserSubscriptions` PDAs of
+/// the sponsors recorded in `beneficiary_gifts` as remaining accounts.
+#[derive(Accounts)]
+pub struct GetGiftedSubscriptions<'info> {
+    /// CHECK: Any wallet may be queried; the key is only used to derive PDAs.
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        seeds = [BENEFICIARY_GIFTS_SEED.as_bytes(), beneficiary.key().as_ref()],
+        bump = beneficiary_gifts.bump,
+    )]
+    pub beneficiary_gifts: Account<'info, BeneficiaryGifts>,
+    #[account(
+        seeds = [SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, SubscriptionRegistry>,
+}
+
+pub fn handler(
+    ctx: Context<GetGiftedSubscriptions>,
+    page: PageArgs,
+) -> Result<GiftedSubscriptionsPage> {
+    let now = Clock::get()?.unix_timestamp;
+    let beneficiary_key = ctx.accounts.beneficiary.key();
+    let services = &ctx.accounts.subscription_registry.services;
+
+    let mut gifted: Vec<GiftedSubscriptionInfo> = Vec::new();
+    let mut skipped: Vec<SkippedAccount> = Vec::new();
+    for account_info in ctx.remaining_accounts.iter() {
+        let account_key = *account_info.key;
+        let Some(mut sponsor_subscriptions) = UserSubscriptions::load_view(account_info) else {
+            skipped.push(SkippedAccount::new(account_key, None, "INVALID_ACCOUNT"));
+            continue;
+        };
+        let sponsor_key = sponsor_subscriptions.owner;
+        if !ctx.accounts.beneficiary_gifts.contains(sponsor_key) {
+            skipped.push(SkippedAccount::new(account_key, None, "NOT_A_SPONSOR"));
+            continue;
+        }
+
+        // Refresh the copy so lapsed cancellations are not reported.
+        if sponsor_subscriptions.refresh(now).is_err() {
+            skipped.push(SkippedAccount::new(account_key, None, "INVALID_ACCOUNT"));
+            continue;
+        }
+
+        for subscription in sponsor_subscriptions.subscriptions.iter() {
+            if subscription.status == SubscriptionStatus::Cancelled {
+                continue;
+            }
+            let is_gift_to_beneficiary = sponsor_subscriptions
+                .gift(subscription.id)
+                .is_some_and(|gift| gift.beneficiary == Some(beneficiary_key));
+            if !is_gift_to_beneficiary {
+                continue;
+            }
+
+            let Some(service) = services
+                .iter()
+                .find(|service| service.id == subscription.service_id)
+            else {
+                skipped.push(SkippedAccount::new(
+                    account_key,
+                    Some(subscription.id),
+                    "SERVICE_NOT_FOUND",
+                ));
+                continue;
+            };
+
+            gifted.push(GiftedSubscriptionInfo {
+                sponsor: sponsor_key,
+                subscription_id: subscription.id,
+                service_id: subscription.service_id,
+                service_name: service.name.clone(),
+                service_logo_url: service.logo_url.clone(),
+                monthly_price_usdc: subscription.monthly_price_usdc,
+                status: subscription.status.as_str().to_string(),
+                started_at: subscription.started_at,
+                next_billing_ts: subscription.next_billing_ts,
+                pending_until_ts: subscription.pending_until_ts,
+            });
+        }
+    }
+
+    // The beneficiary pubkey and the skipped accounts share the return data
+    // with the page.
+    let page = Page::from_items(gifted, page, 32 + borsh::to_vec(&skipped)?.len())?;
+
+    emit!(GiftedSubscriptionsFetched {
+        beneficiary: beneficiary_key,
+        subscriptions: page.items.clone(),
+        skipped: skipped.clone(),
+    });
+
+    Ok(GiftedSubscriptionsPage {
+        beneficiary: beneficiary_key,
+        subscriptions: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+        skipped,
+    })
+}
@@ -0,0 +1,138 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{MERCHANT_SEED, SUBSCRIPTION_REGISTRY_SEED};
+use crate::subly::pagination::{Page, PageArgs, SkippedAccount};
+use crate::subly::state::{Merchant, SubscriptionRegistry, SubscriptionStatus, UserSubscriptions};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MerchantSubscriberInfo {
+    pub user: Pubkey,
+    pub subscription_id: u64,
+    pub service_id: u64,
+    pub service_name: String,
+    pub monthly_price_usdc: u64,
+    pub status: String,
+    pub started_at: i64,
+    pub next_billing_ts: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MerchantSubscribersPage {
+    pub merchant: Pubkey,
+    pub display_name: String,
+    pub active_subscribers: u64,
+    pub lifetime_revenue_usdc: u64,
+    pub pending_revenue_usdc: u64,
+    pub subscribers: Vec<MerchantSubscriberInfo>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+    pub skipped: Vec<SkippedAccount>,
+}
+
+#[event]
+pub struct MerchantSubscribersFetched {
+    pub merchant: Pubkey,
+    pub display_name: String,
+    pub active_subscribers: u64,
+    pub lifetime_revenue_usdc: u64,
+    pub pending_revenue_usdc: u64,
+    pub subscribers: Vec<MerchantSubscriberInfo>,
+    pub skipped: Vec<SkippedAccount>,
+}
+
+#[derive(Accounts)]
+pub struct GetMerchantSubscribers<'info> {
+    #[account(
+        seeds = [MERCHANT_SEED.as_bytes(), merchant.owner.as_ref()],
+        bump = merchant.bump,
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        seeds = [SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, SubscriptionRegistry>,
+}
+
+pub fn handler(
+    ctx: Context<GetMerchantSubscribers>,
+    page: PageArgs,
+) -> Result<MerchantSubscribersPage> {
+    let merchant = &ctx.accounts.merchant;
+    let services: Vec<_> = ctx
+        .accounts
+        .subscription_registry
+        .services
+        .iter()
+        .filter(|service| service.creator == merchant.owner)
+        .collect();
+
+    let mut subscribers: Vec<MerchantSubscriberInfo> = Vec::new();
+    let mut skipped: Vec<SkippedAccount> = Vec::new();
+
+    for account_info in ctx.remaining_accounts.iter() {
+        let Some(user_subscriptions_account) = UserSubscriptions::load_view(account_info) else {
+            skipped.push(SkippedAccount::new(
+                *account_info.key,
+                None,
+                "INVALID_ACCOUNT",
+            ));
+            continue;
+        };
+        let user_key = user_subscriptions_account.owner;
+
+        for subscription in user_subscriptions_account.subscriptions.iter() {
+            if subscription.status == SubscriptionStatus::Cancelled {
+                continue;
+            }
+
+            let Some(service) = services
+                .iter()
+                .find(|service| service.id == subscription.service_id)
+            else {
+                continue;
+            };
+
+            subscribers.push(MerchantSubscriberInfo {
+                user: user_key,
+                subscription_id: subscription.id,
+                service_id: subscription.service_id,
+                service_name: service.name.clone(),
+                monthly_price_usdc: subscription.monthly_price_usdc,
+                status: subscription.status.as_str().to_string(),
+                started_at: subscription.started_at,
+                next_billing_ts: subscription.next_billing_ts,
+            });
+        }
+    }
+
+    // The merchant summary and the skipped accounts share the return data
+    // with the page.
+    let reserved = 32 // merchant
+        + borsh::to_vec(&merchant.display_name)?.len()
+        + 8 * 3 // subscriber count and revenue totals
+        + borsh::to_vec(&skipped)?.len();
+    let page = Page::from_items(subscribers, page, reserved)?;
+
+    emit!(MerchantSubscribersFetched {
+        merchant: merchant.owner,
+        display_name: merchant.display_name.clone(),
+        active_subscribers: merchant.active_subscribers,
+        lifetime_revenue_usdc: merchant.lifetime_revenue_usdc,
+        pending_revenue_usdc: merchant.pending_revenue_usdc,
+        subscribers: page.items.clone(),
+        skipped: skipped.clone(),
+    });
+
+    Ok(MerchantSubscribersPage {
+        merchant: merchant.owner,
+        display_name: merchant.display_name.clone(),
+        active_subscribers: merchant.active_subscribers,
+        lifetime_revenue_usdc: merchant.lifetime_revenue_usdc,
+        pending_revenue_usdc: merchant.pending_revenue_usdc,
+        subscribers: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+        skipped,
+    })
+}
@@ -2,32 +2,44 @@ use anchor_lang::prelude::*;
 
 use crate::subly::constants::USER_SUBSCRIPTIONS_SEED;
 use crate::subly::error::ErrorCode;
-use crate::subly::state::UserSubscriptions;
+use crate::subly::state::{EncryptedReceiver, PayoutRecipient, UserSubscriptions};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PayPalRecipientInfo {
+    pub user: Pubkey,
+    pub configured: bool,
+    pub rail: String,
+    pub recipient_type: String,
+    pub receiver: String,
+    pub receiver_hash: [u8; 32],
+    pub encrypted_receiver: Option<EncryptedReceiver>,
+}
 
 #[event]
 pub struct PayPalRecipientFetched {
     pub user: Pubkey,
     pub configured: bool,
+    pub rail: String,
     pub recipient_type: String,
-    pub receiver: String,
+    pub receiver_hash: [u8; 32],
+    pub encrypted: bool,
 }
 
 #[derive(Accounts)]
 pub struct GetPayPalRecipient<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// CHECK: Any wallet may be queried; the key is only used to derive PDAs.
+    pub user: UncheckedAccount<'info>,
     #[account(
-        mut,
         seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
         bump = user_subscriptions.bump,
     )]
     pub user_subscriptions: Account<'info, UserSubscriptions>,
 }
 
-pub fn handler(ctx: Context<GetPayPalRecipient>) -> Result<()> {
+pub fn handler(ctx: Context<GetPayPalRecipient>) -> Result<PayPalRecipientInfo> {
     let user_key = ctx.accounts.user.key();
 
-    let (expected_pda, subscriptions_bump) = Pubkey::find_program_address(
+    let (expected_pda, _) = Pubkey::find_program_address(
         &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
         &crate::ID,
     );
@@ -37,18 +49,38 @@ pub fn handler(ctx: Context<GetPayPalRecipient>) -> Result<()> {
         ErrorCode::InvalidSubscriptionAccount
     );
 
-    ctx.accounts
-        .user_subscriptions
-        .ensure_owner(user_key, subscriptions_bump);
+    require_keys_eq!(
+        ctx.accounts.user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
 
     let account = &ctx.accounts.user_subscriptions;
+    let recipient = account.payout_recipient.as_ref();
+    let rail = recipient.map(PayoutRecipient::rail);
+    let info = PayPalRecipientInfo {
+        user: user_key,
+        configured: account.payout_configured(),
+        rail: rail.map_or("", |rail| rail.as_str()).to_string(),
+        recipient_type: rail.map_or("", |rail| rail.recipient_type()).to_string(),
+        receiver: recipient
+            .and_then(PayoutRecipient::canonical_receiver)
+            .unwrap_or_default(),
+        receiver_hash: recipient.map_or([0u8; 32], |recipient| recipient.receiver_hash(&user_key)),
+        encrypted_receiver: match recipient {
+            Some(PayoutRecipient::Encrypted { receiver, .. }) => Some(receiver.clone()),
+            _ => None,
+        },
+    };
 
     emit!(PayPalRecipientFetched {
-        user: user_key,
-        configured: account.paypal_configured,
-        recipient_type: account.paypal_recipient_type.as_str().to_string(),
-        receiver: account.paypal_receiver.clone(),
+        user: info.user,
+        configured: info.configured,
+        rail: info.rail.clone(),
+        recipient_type: info.recipient_type.clone(),
+        receiver_hash: info.receiver_hash,
+        encrypted: info.encrypted_receiver.is_some(),
     });
 
-    Ok(())
+    Ok(info)
 }
@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 
 use crate::subly::constants::SUBSCRIPTION_REGISTRY_SEED;
+use crate::subly::pagination::{Page, PageArgs};
 use crate::subly::state::SubscriptionRegistry;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -15,6 +16,13 @@ pub struct SubscriptionServiceInfo {
     pub created_at: i64,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SubscriptionServicesPage {
+    pub services: Vec<SubscriptionServiceInfo>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+}
+
 #[event]
 pub struct SubscriptionServicesFetched {
     pub services: Vec<SubscriptionServiceInfo>,
@@ -29,7 +37,10 @@ pub struct GetSubscriptionServices<'info> {
     pub subscription_registry: Account<'info, SubscriptionRegistry>,
 }
 
-pub fn handler(ctx: Context<GetSubscriptionServices>) -> Result<()> {
+pub fn handler(
+    ctx: Context<GetSubscriptionServices>,
+    page: PageArgs,
+) -> Result<SubscriptionServicesPage> {
     let registry = &ctx.accounts.subscription_registry;
     let services: Vec<SubscriptionServiceInfo> = registry
         .services
@@ -46,7 +57,15 @@ pub fn handler(ctx: Context<GetSubscriptionServices>) -> Result<()> {
         })
         .collect();
 
-    emit!(SubscriptionServicesFetched { services });
+    let page = Page::from_items(services, page, 0)?;
+
+    emit!(SubscriptionServicesFetched {
+        services: page.items.clone(),
+    });
 
-    Ok(())
+    Ok(SubscriptionServicesPage {
+        services: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+    })
 }
@@ -1,12 +1,24 @@
 use anchor_lang::prelude::*;
 
 use crate::subly::constants::{
-    BASIS_POINTS_DIVISOR, CONFIG_SEED, SUBSCRIPTION_REGISTRY_SEED, USER_POSITION_SEED,
-    USER_SUBSCRIPTIONS_SEED,
+    CONFIG_SEED, SUBSCRIPTION_REGISTRY_SEED, USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED,
 };
 use crate::subly::error::ErrorCode;
 use crate::subly::instructions::get_subscription_services::SubscriptionServiceInfo;
-use crate::subly::state::{SublyConfig, SubscriptionRegistry, UserStake, UserSubscriptions};
+use crate::subly::instructions::subscribe_service::compute_monthly_budget;
+use crate::subly::pagination::{Page, PageArgs};
+use crate::subly::state::{
+    SpendingCaps, SublyConfig, SubscriptionRegistry, UserStake, UserSubscriptions,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UserAvailableServicesPage {
+    pub user: Pubkey,
+    pub available_budget_usdc: u64,
+    pub services: Vec<SubscriptionServiceInfo>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+}
 
 #[event]
 pub struct UserAvailableServicesFetched {
@@ -22,31 +34,29 @@ pub struct GetUserAvailableServices<'info> {
         bump = config.bump,
     )]
     pub config: Account<'info, SublyConfig>,
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// CHECK: Any wallet may be queried; the key is only used to derive PDAs.
+    pub user: UncheckedAccount<'info>,
     #[account(
-        mut,
         seeds = [USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
         bump = user_position.bump,
     )]
     pub user_position: Account<'info, UserStake>,
     #[account(
         seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
-        bump,
-        init_if_needed,
-        payer = user,
-        space = UserSubscriptions::INITIAL_SIZE,
+        bump = user_subscriptions.bump,
     )]
-    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub user_subscriptions: Option<Account<'info, UserSubscriptions>>,
     #[account(
         seeds = [SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
         bump = subscription_registry.bump,
     )]
     pub subscription_registry: Account<'info, SubscriptionRegistry>,
-    pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<GetUserAvailableServices>) -> Result<()> {
+pub fn handler(
+    ctx: Context<GetUserAvailableServices>,
+    page: PageArgs,
+) -> Result<UserAvailableServicesPage> {
     let now = Clock::get()?.unix_timestamp;
 
     let user_key = ctx.accounts.user.key();
@@ -66,7 +76,7 @@ pub fn handler(ctx: Context<GetUserAvailableServices>) -> Result<()> {
         ErrorCode::InvalidSubscriptionAccount
     );
 
-    let (expected_user_position, position_bump) = Pubkey::find_program_address(
+    let (expected_user_position, _) = Pubkey::find_program_address(
         &[USER_POSITION_SEED.as_bytes(), user_key.as_ref()],
         &crate::ID,
     );
@@ -75,31 +85,51 @@ pub fn handler(ctx: Context<GetUserAvailableServices>) -> Result<()> {
         ctx.accounts.user_position.key(),
         ErrorCode::InvalidPositionOwner
     );
-    ctx.accounts
-        .user_position
-        .ensure_owner(user_key, position_bump);
-
-    let (expected_user_subscriptions, subscriptions_bump) = Pubkey::find_program_address(
-        &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
-        &crate::ID,
-    );
     require_keys_eq!(
-        expected_user_subscriptions,
-        ctx.accounts.user_subscriptions.key(),
-        ErrorCode::InvalidSubscriptionAccount
+        ctx.accounts.user_position.owner,
+        user_key,
+        ErrorCode::InvalidPositionOwner
     );
-    ctx.accounts
-        .user_subscriptions
-        .ensure_owner(user_key, subscriptions_bump);
 
-    ctx.accounts.user_subscriptions.refresh(now)?;
+    // Wallets that never subscribed have no subscriptions account yet.
+    let user_subscriptions = match ctx.accounts.user_subscriptions.as_ref() {
+        Some(account) => {
+            let (expected_user_subscriptions, _) = Pubkey::find_program_address(
+                &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                expected_user_subscriptions,
+                account.key(),
+                ErrorCode::InvalidSubscriptionAccount
+            );
+            require_keys_eq!(
+                account.owner,
+                user_key,
+                ErrorCode::InvalidSubscriptionAccount
+            );
+
+            let mut user_subscriptions = (**account).clone();
+            user_subscriptions.refresh(now)?;
+            Some(user_subscriptions)
+        }
+        None => None,
+    };
 
     let monthly_budget = compute_monthly_budget(
         ctx.accounts.user_position.total_principal,
-        ctx.accounts.config.apy_bps,
+        ctx.accounts.config.net_apy_bps(),
     )?;
-    let committed = ctx.accounts.user_subscriptions.total_committed()?;
-    let available_budget = monthly_budget.saturating_sub(committed);
+    let (committed, spending_caps) = match user_subscriptions.as_ref() {
+        Some(user_subscriptions) => (
+            user_subscriptions.personal_committed()?,
+            user_subscriptions.spending_caps,
+        ),
+        None => (0, SpendingCaps::default()),
+    };
+    let available_budget = spending_caps
+        .cap_budget(monthly_budget)?
+        .saturating_sub(committed);
 
     let services: Vec<SubscriptionServiceInfo> = ctx
         .accounts
@@ -108,10 +138,12 @@ pub fn handler(ctx: Context<GetUserAvailableServices>) -> Result<()> {
         .iter()
         .filter(|service| {
             service.monthly_price_usdc <= available_budget
-                && !ctx
-                    .accounts
-                    .user_subscriptions
-                    .has_active_or_pending_for_service(service.id)
+                && spending_caps.allows_price(service.monthly_price_usdc)
+                && !user_subscriptions
+                    .as_ref()
+                    .is_some_and(|user_subscriptions| {
+                        user_subscriptions.has_active_or_pending_for_service(service.id)
+                    })
         })
         .map(|service| SubscriptionServiceInfo {
             id: service.id,
@@ -125,29 +157,20 @@ pub fn handler(ctx: Context<GetUserAvailableServices>) -> Result<()> {
         })
         .collect();
 
+    // user (32) + available_budget_usdc (8) precede the paged services.
+    let page = Page::from_items(services, page, 40)?;
+
     emit!(UserAvailableServicesFetched {
         user: user_key,
         available_budget_usdc: available_budget,
-        services,
+        services: page.items.clone(),
     });
 
-    Ok(())
-}
-
-fn compute_monthly_budget(total_principal: u64, apy_bps: u16) -> Result<u64> {
-    if total_principal == 0 || apy_bps == 0 {
-        return Ok(0);
-    }
-
-    let annual_yield = (total_principal as u128)
-        .checked_mul(apy_bps as u128)
-        .ok_or(ErrorCode::MathOverflow)?
-        .checked_div(BASIS_POINTS_DIVISOR as u128)
-        .ok_or(ErrorCode::MathOverflow)?;
-
-    let monthly_yield = annual_yield
-        .checked_div(12)
-        .ok_or(ErrorCode::MathOverflow)?;
-
-    u64::try_from(monthly_yield).map_err(|_| ErrorCode::MathOverflow.into())
+    Ok(UserAvailableServicesPage {
+        user: user_key,
+        available_budget_usdc: available_budget,
+        services: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+    })
 }
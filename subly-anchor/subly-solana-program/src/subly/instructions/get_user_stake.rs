@@ -2,6 +2,7 @@ use anchor_lang::prelude::*;
 
 use crate::subly::constants::{CONFIG_SEED, USER_POSITION_SEED};
 use crate::subly::error::ErrorCode;
+use crate::subly::pagination::{Page, PageArgs};
 use crate::subly::state::{StakeEntry, SublyConfig, UserStake};
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -31,6 +32,15 @@ impl From<&StakeEntry> for StakeEntrySnapshot {
     }
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UserStakePage {
+    pub user: Pubkey,
+    pub total_principal: u64,
+    pub stake_entries: Vec<StakeEntrySnapshot>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+}
+
 #[event]
 pub struct UserStakeFetched {
     pub user: Pubkey,
@@ -42,17 +52,16 @@ pub struct UserStakeFetched {
 pub struct GetUserStake<'info> {
     #[account(seeds = [CONFIG_SEED.as_bytes()], bump = config.bump)]
     pub config: Account<'info, SublyConfig>,
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// CHECK: Any wallet may be queried; the key is only used to derive PDAs.
+    pub user: UncheckedAccount<'info>,
     #[account(
-        mut,
         seeds = [USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
         bump = user_position.bump,
     )]
     pub user_position: Account<'info, UserStake>,
 }
 
-pub fn handler(ctx: Context<GetUserStake>) -> Result<()> {
+pub fn handler(ctx: Context<GetUserStake>, page: PageArgs) -> Result<UserStakePage> {
     let user_key = ctx.accounts.user.key();
 
     let (expected_config, _) = Pubkey::find_program_address(&[CONFIG_SEED.as_bytes()], &crate::ID);
@@ -62,7 +71,7 @@ pub fn handler(ctx: Context<GetUserStake>) -> Result<()> {
         ErrorCode::InvalidSubscriptionAccount
     );
 
-    let (expected_user_position, position_bump) = Pubkey::find_program_address(
+    let (expected_user_position, _) = Pubkey::find_program_address(
         &[USER_POSITION_SEED.as_bytes(), user_key.as_ref()],
         &crate::ID,
     );
@@ -72,9 +81,11 @@ pub fn handler(ctx: Context<GetUserStake>) -> Result<()> {
         ErrorCode::InvalidPositionOwner
     );
 
-    ctx.accounts
-        .user_position
-        .ensure_owner(user_key, position_bump);
+    require_keys_eq!(
+        ctx.accounts.user_position.owner,
+        user_key,
+        ErrorCode::InvalidPositionOwner
+    );
 
     let snapshots: Vec<StakeEntrySnapshot> = ctx
         .accounts
@@ -84,11 +95,21 @@ pub fn handler(ctx: Context<GetUserStake>) -> Result<()> {
         .map(StakeEntrySnapshot::from)
         .collect();
 
+    let total_principal = ctx.accounts.user_position.total_principal;
+    // user (32) + total_principal (8) precede the paged entries.
+    let page = Page::from_items(snapshots, page, 40)?;
+
     emit!(UserStakeFetched {
         user: user_key,
-        total_principal: ctx.accounts.user_position.total_principal,
-        stake_entries: snapshots,
+        total_principal,
+        stake_entries: page.items.clone(),
     });
 
-    Ok(())
+    Ok(UserStakePage {
+        user: user_key,
+        total_principal,
+        stake_entries: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+    })
 }
@@ -2,7 +2,10 @@ use anchor_lang::prelude::*;
 
 use crate::subly::constants::{SUBSCRIPTION_REGISTRY_SEED, USER_SUBSCRIPTIONS_SEED};
 use crate::subly::error::ErrorCode;
-use crate::subly::state::{SubscriptionRegistry, SubscriptionStatus, UserSubscriptions};
+use crate::subly::pagination::{Page, PageArgs};
+use crate::subly::state::{
+    Gift, PayoutRecipient, SubscriptionRegistry, SubscriptionStatus, UserSubscriptions,
+};
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct UserSubscriptionInfo {
@@ -19,6 +22,17 @@ pub struct UserSubscriptionInfo {
     pub next_billing_ts: i64,
     pub pending_until_ts: i64,
     pub initial_payment_recorded: bool,
+    pub payout_override: Option<PayoutRecipient>,
+    pub gift: Option<Gift>,
+    pub group: Option<Pubkey>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UserSubscriptionsPage {
+    pub user: Pubkey,
+    pub subscriptions: Vec<UserSubscriptionInfo>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
 }
 
 #[event]
@@ -29,10 +43,9 @@ pub struct UserSubscriptionsFetched {
 
 #[derive(Accounts)]
 pub struct GetUserSubscriptions<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// CHECK: Any wallet may be queried; the key is only used to derive PDAs.
+    pub user: UncheckedAccount<'info>,
     #[account(
-        mut,
         seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
         bump = user_subscriptions.bump,
     )]
@@ -44,11 +57,14 @@ pub struct GetUserSubscriptions<'info> {
     pub subscription_registry: Account<'info, SubscriptionRegistry>,
 }
 
-pub fn handler(ctx: Context<GetUserSubscriptions>) -> Result<()> {
+pub fn handler(
+    ctx: Context<GetUserSubscriptions>,
+    page: PageArgs,
+) -> Result<UserSubscriptionsPage> {
     let now = Clock::get()?.unix_timestamp;
     let user_key = ctx.accounts.user.key();
 
-    let (expected_pda, subscriptions_bump) = Pubkey::find_program_address(
+    let (expected_pda, _) = Pubkey::find_program_address(
         &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
         &crate::ID,
     );
@@ -58,15 +74,20 @@ pub fn handler(ctx: Context<GetUserSubscriptions>) -> Result<()> {
         ErrorCode::InvalidSubscriptionAccount
     );
 
-    ctx.accounts
-        .user_subscriptions
-        .ensure_owner(user_key, subscriptions_bump);
-    ctx.accounts.user_subscriptions.refresh(now)?;
+    require_keys_eq!(
+        ctx.accounts.user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    // Refresh a copy so lapsed cancellations are reported without writing back.
+    let mut user_subscriptions = (*ctx.accounts.user_subscriptions).clone();
+    user_subscriptions.refresh(now)?;
 
     let services = &ctx.accounts.subscription_registry.services;
 
     let mut subscription_infos: Vec<UserSubscriptionInfo> = Vec::new();
-    for subscription in ctx.accounts.user_subscriptions.subscriptions.iter() {
+    for subscription in user_subscriptions.subscriptions.iter() {
         let include = matches!(
             subscription.status,
             SubscriptionStatus::Active | SubscriptionStatus::PendingCancellation
@@ -80,12 +101,6 @@ pub fn handler(ctx: Context<GetUserSubscriptions>) -> Result<()> {
             .find(|service| service.id == subscription.service_id)
             .ok_or(ErrorCode::SubscriptionServiceNotFound)?;
 
-        let status = match subscription.status {
-            SubscriptionStatus::Active => "ACTIVE",
-            SubscriptionStatus::PendingCancellation => "PENDING_CANCELLATION",
-            SubscriptionStatus::Cancelled => "CANCELLED",
-        };
-
         subscription_infos.push(UserSubscriptionInfo {
             subscription_id: subscription.id,
             service_id: subscription.service_id,
@@ -94,19 +109,32 @@ pub fn handler(ctx: Context<GetUserSubscriptions>) -> Result<()> {
             service_logo_url: service.logo_url.clone(),
             service_provider: service.provider.clone(),
             monthly_price_usdc: subscription.monthly_price_usdc,
-            status: status.to_string(),
+            status: subscription.status.as_str().to_string(),
             started_at: subscription.started_at,
             last_payment_ts: subscription.last_payment_ts,
             next_billing_ts: subscription.next_billing_ts,
             pending_until_ts: subscription.pending_until_ts,
             initial_payment_recorded: subscription.initial_payment_recorded,
+            payout_override: user_subscriptions.payout_override(subscription.id).cloned(),
+            gift: user_subscriptions.gift(subscription.id).copied(),
+            group: user_subscriptions
+                .group_charge(subscription.id)
+                .map(|charge| charge.group),
         });
     }
 
+    // The user pubkey is the only fixed-size field ahead of the page.
+    let page = Page::from_items(subscription_infos, page, 32)?;
+
     emit!(UserSubscriptionsFetched {
         user: user_key,
-        subscriptions: subscription_infos,
+        subscriptions: page.items.clone(),
     });
 
-    Ok(())
+    Ok(UserSubscriptionsPage {
+        user: user_key,
+        subscriptions: page.items,
+        total: page.total,
+        next_offset: page.next_offset,
+    })
 }
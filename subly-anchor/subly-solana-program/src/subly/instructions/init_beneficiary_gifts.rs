@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::BENEFICIARY_GIFTS_SEED;
+use crate::subly::state::BeneficiaryGifts;
+
+#[derive(Accounts)]
+#[instruction(beneficiary: Pubkey)]
+pub struct InitBeneficiaryGifts<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [BENEFICIARY_GIFTS_SEED.as_bytes(), beneficiary.as_ref()],
+        bump,
+        init_if_needed,
+        payer = payer,
+        space = BeneficiaryGifts::INITIAL_SIZE,
+    )]
+    pub beneficiary_gifts: Account<'info, BeneficiaryGifts>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitBeneficiaryGifts>, beneficiary: Pubkey) -> Result<()> {
+    let beneficiary_gifts = &mut ctx.accounts.beneficiary_gifts;
+    beneficiary_gifts.beneficiary = beneficiary;
+    beneficiary_gifts.bump = ctx.bumps.beneficiary_gifts;
+
+    Ok(())
+}
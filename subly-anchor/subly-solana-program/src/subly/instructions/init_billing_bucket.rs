@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{BILLING_BUCKET_SEED, MAX_BILLING_BUCKET_ENTRIES};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::BillingBucket;
+
+#[derive(Accounts)]
+#[instruction(day: i64, chunk: u16)]
+pub struct InitBillingBucket<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [
+            BILLING_BUCKET_SEED.as_bytes(),
+            &day.to_le_bytes(),
+            &BillingBucket::chunk_seed(chunk),
+        ],
+        bump,
+        init_if_needed,
+        payer = payer,
+        space = BillingBucket::INITIAL_SIZE,
+    )]
+    pub billing_bucket: Account<'info, BillingBucket>,
+    /// The day's chunk before `chunk`; required for every chunk but the first.
+    #[account(
+        seeds = [
+            BILLING_BUCKET_SEED.as_bytes(),
+            &day.to_le_bytes(),
+            &BillingBucket::chunk_seed(chunk.saturating_sub(1)),
+        ],
+        bump = previous_bucket.bump,
+    )]
+    pub previous_bucket: Option<Account<'info, BillingBucket>>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Keepers walk a day's chunks from 0 until the next one does not exist, so
+/// an empty chunk may only be opened behind a full one.
+pub fn handler(ctx: Context<InitBillingBucket>, day: i64, chunk: u16) -> Result<()> {
+    if chunk > 0 && ctx.accounts.billing_bucket.entries.is_empty() {
+        let previous_bucket = ctx
+            .accounts
+            .previous_bucket
+            .as_ref()
+            .ok_or(ErrorCode::BillingBucketChunkOutOfOrder)?;
+        require!(
+            previous_bucket.entries.len() >= MAX_BILLING_BUCKET_ENTRIES,
+            ErrorCode::BillingBucketChunkOutOfOrder
+        );
+    }
+
+    let bucket = &mut ctx.accounts.billing_bucket;
+    bucket.day = day;
+    bucket.bump = ctx.bumps.billing_bucket;
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::USER_SUBSCRIPTIONS_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::UserSubscriptions;
+
+#[derive(Accounts)]
+pub struct InitUserSubscriptions<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+        init_if_needed,
+        payer = user,
+        space = UserSubscriptions::INITIAL_SIZE,
+    )]
+    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitUserSubscriptions>) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+
+    let (expected_pda, subscriptions_bump) = Pubkey::find_program_address(
+        &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(
+        expected_pda,
+        ctx.accounts.user_subscriptions.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    ctx.accounts
+        .user_subscriptions
+        .ensure_owner(user_key, subscriptions_bump);
+
+    Ok(())
+}
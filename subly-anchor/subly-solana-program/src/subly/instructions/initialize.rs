@@ -61,6 +61,11 @@ pub fn handler(ctx: Context<Initialize>, args: InitializeArgs) -> Result<()> {
     config.paused = false;
     config.bump = ctx.bumps.config;
     config.vault_bump = ctx.bumps.vault;
+    config.protocol_fee_bps = 0;
+    config.protocol_fees = 0;
+    config.receiver_encryption_key = [0u8; 32];
+    config.fee_checkpoints = Default::default();
+    config.fee_checkpoint_count = 0;
 
     let registry = &mut ctx.accounts.subscription_registry;
     registry.next_service_id = 0;
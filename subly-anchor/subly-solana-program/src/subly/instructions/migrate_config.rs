@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_lang::Discriminator;
+
+use crate::subly::constants::{CONFIG_SEED, MAX_FEE_CHECKPOINTS};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{FeeCheckpoint, SublyConfig};
+
+/// Size of the config before `fee_checkpoints` and `fee_checkpoint_count`
+/// were appended.
+const WITHOUT_FEE_CHECKPOINTS_LEN: usize =
+    SublyConfig::LEN - FeeCheckpoint::SIZE * MAX_FEE_CHECKPOINTS - 1;
+/// Size of the config before `protocol_fee_bps`, `protocol_fees` and
+/// `receiver_encryption_key` were appended.
+const LEGACY_CONFIG_LEN: usize = WITHOUT_FEE_CHECKPOINTS_LEN - 2 - 8 - 32;
+
+#[event]
+pub struct ConfigMigrated {
+    pub authority: Pubkey,
+    pub previous_len: u32,
+    pub len: u32,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Legacy configs are too short to deserialize as `SublyConfig`;
+    /// the handler checks the owner program, discriminator and authority.
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub config: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a config written by an earlier layout to `SublyConfig::LEN`. The
+/// appended fields start zeroed: no protocol fee, no fees accrued, no
+/// receiver encryption key and no fee checkpoints.
+pub fn handler(ctx: Context<MigrateConfig>) -> Result<()> {
+    let config_info = ctx.accounts.config.to_account_info();
+    require_keys_eq!(
+        *config_info.owner,
+        crate::ID,
+        ErrorCode::InvalidConfigAccount
+    );
+
+    let previous_len = config_info.data_len();
+    {
+        let data = config_info.try_borrow_data()?;
+        require!(
+            data.starts_with(SublyConfig::DISCRIMINATOR),
+            ErrorCode::InvalidConfigAccount
+        );
+        if previous_len >= SublyConfig::LEN {
+            // Already migrated; deployment scripts may run this blindly.
+            return Ok(());
+        }
+        require!(
+            previous_len >= LEGACY_CONFIG_LEN,
+            ErrorCode::InvalidConfigAccount
+        );
+        let offset = SublyConfig::DISCRIMINATOR.len();
+        let authority = Pubkey::try_from(&data[offset..offset + 32])
+            .map_err(|_| ErrorCode::InvalidConfigAccount)?;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            authority,
+            ErrorCode::UnauthorizedAuthority
+        );
+    }
+
+    let required_lamports = Rent::get()?.minimum_balance(SublyConfig::LEN);
+    let current_lamports = config_info.lamports();
+    if required_lamports > current_lamports {
+        let transfer_accounts = SystemTransfer {
+            from: ctx.accounts.authority.to_account_info(),
+            to: config_info.clone(),
+        };
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                transfer_accounts,
+            ),
+            required_lamports - current_lamports,
+        )?;
+    }
+    config_info.resize(SublyConfig::LEN)?;
+    config_info.try_borrow_mut_data()?[previous_len..].fill(0);
+
+    emit!(ConfigMigrated {
+        authority: ctx.accounts.authority.key(),
+        previous_len: previous_len as u32,
+        len: SublyConfig::LEN as u32,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::USER_SUBSCRIPTIONS_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::UserSubscriptions;
+
+#[event]
+pub struct UserSubscriptionsMigrated {
+    pub user: Pubkey,
+    pub rail: String,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserSubscriptions<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Only used to derive the subscriptions PDA.
+    pub user: UncheckedAccount<'info>,
+    /// CHECK: The handler checks the owner program and discriminator, and
+    /// rewrites the account itself once it is large enough.
+    #[account(
+        mut,
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_subscriptions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Rewrites an account in the current layout. Every instruction already
+/// reads older layouts, so this only saves later writes the resize.
+pub fn handler(ctx: Context<MigrateUserSubscriptions>) -> Result<()> {
+    let subscriptions_info = ctx.accounts.user_subscriptions.to_account_info();
+    require_keys_eq!(
+        *subscriptions_info.owner,
+        crate::ID,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    let (migrated, current) = {
+        let data = subscriptions_info.try_borrow_data()?;
+        let mut data_slice: &[u8] = &data;
+        let migrated = UserSubscriptions::try_deserialize(&mut data_slice)
+            .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+        require_keys_eq!(
+            migrated.owner,
+            ctx.accounts.user.key(),
+            ErrorCode::InvalidSubscriptionAccount
+        );
+
+        let mut serialized = Vec::new();
+        migrated.try_serialize(&mut serialized)?;
+        let current = data.starts_with(&serialized);
+        (migrated, current)
+    };
+    if current {
+        // Already migrated; keepers may sweep every account blindly.
+        return Ok(());
+    }
+
+    migrated.reserve_tail(
+        &subscriptions_info,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let mut data = subscriptions_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    migrated.try_serialize(&mut writer)?;
+
+    emit!(UserSubscriptionsMigrated {
+        user: migrated.owner,
+        rail: migrated
+            .payout_recipient
+            .as_ref()
+            .map_or("", |recipient| recipient.rail().as_str())
+            .to_string(),
+    });
+
+    Ok(())
+}
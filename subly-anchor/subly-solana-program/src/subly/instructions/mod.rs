@@ -1,18 +1,42 @@
 pub mod claim_operator;
 pub mod claim_user;
+pub mod clear_paypal_recipient;
+pub mod contribute_to_group;
+pub mod crank_due_bucket;
+pub mod create_group_budget;
 pub mod find_due_subscriptions;
 pub mod fund_rewards;
+pub mod get_gifted_subscriptions;
+pub mod get_merchant_subscribers;
 pub mod get_paypal_recipient;
 pub mod get_subscription_services;
 pub mod get_user_available_services;
 pub mod get_user_stake;
 pub mod get_user_subscriptions;
+pub mod init_beneficiary_gifts;
+pub mod init_billing_bucket;
+pub mod init_user_subscriptions;
 pub mod initialize;
+pub mod migrate_config;
+pub mod migrate_user_subscriptions;
+pub mod quote_stake_for_services;
 pub mod record_subscription_payment;
+pub mod register_encrypted_paypal_recipient;
+pub mod register_merchant;
+pub mod register_payout_recipient;
 pub mod register_paypal_recipient;
 pub mod register_subscription_service;
+pub mod remove_group_member;
+pub mod set_group_member;
+pub mod set_protocol_fee;
+pub mod set_receiver_encryption_key;
+pub mod set_spending_caps;
+pub mod set_subscription_payout_override;
 pub mod stake;
 pub mod subscribe_service;
 pub mod sync_yield;
+pub mod transfer_position;
 pub mod unstake;
 pub mod unsubscribe_service;
+pub mod withdraw_group_contribution;
+pub mod withdraw_protocol_fees;
@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{
+    lock_duration_for_index, BASIS_POINTS_DIVISOR, CONFIG_SEED, SUBSCRIPTION_REGISTRY_SEED,
+    USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED,
+};
+use crate::subly::error::ErrorCode;
+use crate::subly::instructions::subscribe_service::{
+    compute_monthly_budget, required_principal_for_budget,
+};
+use crate::subly::state::{
+    SpendingCaps, SublyConfig, SubscriptionRegistry, UserStake, UserSubscriptions,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct QuoteStakeForServicesArgs {
+    pub service_ids: Vec<u64>,
+    /// Index into `LOCK_OPTIONS` the stake would be made with. Every lock
+    /// earns the same APY, so it is only checked and echoed back; the
+    /// quoted amounts do not vary with it.
+    pub lock_option: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct StakeQuote {
+    pub user: Pubkey,
+    /// Duration of the requested lock option; informational only.
+    pub lock_duration: i64,
+    /// Net APY the budget is computed at, after the protocol fee.
+    pub apy_bps: u16,
+    /// Monthly price of the quoted services the user does not already have.
+    pub monthly_price_usdc: u64,
+    /// What the user's own stake already has to cover.
+    pub committed_usdc: u64,
+    pub current_principal: u64,
+    /// Principal needed to cover `committed_usdc` and `monthly_price_usdc`.
+    pub required_principal: u64,
+    pub shortfall: u64,
+    /// Monthly budget once the shortfall is staked.
+    pub monthly_budget_usdc: u64,
+    /// False when the absolute cap or per-service ceiling would still block
+    /// one of the services, whatever the stake.
+    pub within_spending_caps: bool,
+}
+
+#[event]
+pub struct StakeQuoted {
+    pub quote: StakeQuote,
+}
+
+#[derive(Accounts)]
+pub struct QuoteStakeForServices<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    /// CHECK: Any wallet may be queried; the key is only used to derive PDAs.
+    pub user: UncheckedAccount<'info>,
+    /// Wallets that never staked have no position yet.
+    #[account(
+        seeds = [USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_position.bump,
+    )]
+    pub user_position: Option<Account<'info, UserStake>>,
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_subscriptions.bump,
+    )]
+    pub user_subscriptions: Option<Account<'info, UserSubscriptions>>,
+    #[account(
+        seeds = [SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, SubscriptionRegistry>,
+}
+
+pub fn handler(
+    ctx: Context<QuoteStakeForServices>,
+    args: QuoteStakeForServicesArgs,
+) -> Result<StakeQuote> {
+    let now = Clock::get()?.unix_timestamp;
+    let user_key = ctx.accounts.user.key();
+    let lock_duration =
+        lock_duration_for_index(args.lock_option).ok_or(ErrorCode::InvalidLockOption)?;
+
+    let current_principal = match ctx.accounts.user_position.as_ref() {
+        Some(user_position) => {
+            require_keys_eq!(
+                user_position.owner,
+                user_key,
+                ErrorCode::InvalidPositionOwner
+            );
+            user_position.total_principal
+        }
+        None => 0,
+    };
+
+    let user_subscriptions = match ctx.accounts.user_subscriptions.as_ref() {
+        Some(account) => {
+            require_keys_eq!(
+                account.owner,
+                user_key,
+                ErrorCode::InvalidSubscriptionAccount
+            );
+            let mut user_subscriptions = (**account).clone();
+            user_subscriptions.refresh(now)?;
+            Some(user_subscriptions)
+        }
+        None => None,
+    };
+    let (committed, spending_caps) = match user_subscriptions.as_ref() {
+        Some(user_subscriptions) => (
+            user_subscriptions.personal_committed()?,
+            user_subscriptions.spending_caps,
+        ),
+        None => (0, SpendingCaps::default()),
+    };
+
+    let mut service_ids = args.service_ids;
+    service_ids.sort_unstable();
+    service_ids.dedup();
+
+    let mut monthly_price: u64 = 0;
+    let mut within_spending_caps = true;
+    for service_id in service_ids {
+        let service = ctx
+            .accounts
+            .subscription_registry
+            .services
+            .iter()
+            .find(|service| service.id == service_id)
+            .ok_or(ErrorCode::SubscriptionServiceNotFound)?;
+        let already_subscribed = user_subscriptions
+            .as_ref()
+            .is_some_and(|user_subscriptions| {
+                user_subscriptions.has_active_or_pending_for_service(service_id)
+            });
+        if already_subscribed {
+            continue;
+        }
+        within_spending_caps &= spending_caps.allows_price(service.monthly_price_usdc);
+        monthly_price = monthly_price
+            .checked_add(service.monthly_price_usdc)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    let target = committed
+        .checked_add(monthly_price)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if spending_caps.monthly_cap_usdc > 0 && target > spending_caps.monthly_cap_usdc {
+        within_spending_caps = false;
+    }
+
+    // A budget share below 100% has to be made up with a larger budget.
+    let required_budget = match spending_caps.max_budget_bps {
+        0 => target,
+        max_budget_bps => {
+            let scaled = (target as u128)
+                .checked_mul(BASIS_POINTS_DIVISOR as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                .div_ceil(max_budget_bps as u128);
+            u64::try_from(scaled).map_err(|_| ErrorCode::MathOverflow)?
+        }
+    };
+
+    let apy_bps = ctx.accounts.config.net_apy_bps();
+    let required_principal = required_principal_for_budget(required_budget, apy_bps)?;
+    let shortfall = required_principal.saturating_sub(current_principal);
+    let monthly_budget =
+        compute_monthly_budget(current_principal.max(required_principal), apy_bps)?;
+
+    let quote = StakeQuote {
+        user: user_key,
+        lock_duration,
+        apy_bps,
+        monthly_price_usdc: monthly_price,
+        committed_usdc: committed,
+        current_principal,
+        required_principal,
+        shortfall,
+        monthly_budget_usdc: monthly_budget,
+        within_spending_caps,
+    };
+
+    emit!(StakeQuoted {
+        quote: quote.clone(),
+    });
+
+    Ok(quote)
+}
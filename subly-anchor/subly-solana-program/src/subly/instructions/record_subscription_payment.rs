@@ -1,13 +1,21 @@
 use anchor_lang::prelude::*;
 
-use crate::subly::constants::{BILLING_PERIOD_SECONDS, CONFIG_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::constants::{
+    BILLING_PERIOD_SECONDS, CONFIG_SEED, SUBSCRIPTION_REGISTRY_SEED, USER_SUBSCRIPTIONS_SEED,
+};
 use crate::subly::error::ErrorCode;
-use crate::subly::state::{SublyConfig, SubscriptionStatus, UserSubscriptions};
+use crate::subly::state::{
+    BillingBucket, Merchant, SublyConfig, SubscriptionRegistry, SubscriptionStatus,
+    UserSubscriptions,
+};
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct RecordSubscriptionPaymentArgs {
     pub subscription_id: u64,
     pub payment_ts: Option<i64>,
+    /// Chunk of the next due day's bucket passed as `next_billing_bucket`;
+    /// use the first one that is not full.
+    pub next_billing_chunk: u16,
 }
 
 #[event]
@@ -26,6 +34,7 @@ pub struct RecordSubscriptionPayment<'info> {
         bump = config.bump,
     )]
     pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
     pub operator: Signer<'info>,
     /// CHECK: used only for PDA seed validation
     pub user: UncheckedAccount<'info>,
@@ -35,6 +44,20 @@ pub struct RecordSubscriptionPayment<'info> {
         bump = user_subscriptions.bump,
     )]
     pub user_subscriptions: Account<'info, UserSubscriptions>,
+    #[account(
+        seeds = [SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, SubscriptionRegistry>,
+    /// CHECK: The service creator's merchant PDA, registered or not; the
+    /// handler checks the address and loads it when it exists.
+    #[account(mut)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub billing_bucket: Option<Account<'info, BillingBucket>>,
+    #[account(mut)]
+    pub next_billing_bucket: Option<Account<'info, BillingBucket>>,
+    pub system_program: Program<'info, System>,
 }
 
 pub fn handler(
@@ -56,18 +79,106 @@ pub fn handler(
         .user_subscriptions
         .ensure_owner(user_key, user_bump);
 
+    let (previous_billing_ts, recurring) = ctx
+        .accounts
+        .user_subscriptions
+        .subscriptions
+        .iter()
+        .find(|subscription| subscription.id == args.subscription_id)
+        .map(|subscription| {
+            (
+                subscription.next_billing_ts,
+                subscription.initial_payment_recorded,
+            )
+        })
+        .ok_or(ErrorCode::SubscriptionNotFound)?;
+
     let status = ctx.accounts.user_subscriptions.record_payment(
         args.subscription_id,
         paid_ts,
         BILLING_PERIOD_SECONDS,
     )?;
 
-    let status_str = match status {
-        SubscriptionStatus::Active => "ACTIVE",
-        SubscriptionStatus::PendingCancellation => "PENDING_CANCELLATION",
-        SubscriptionStatus::Cancelled => "CANCELLED",
+    // Initial payments leave the schedule untouched; renewals move the entry to
+    // the next due day and final payments drop it from the queue. Only
+    // subscriptions queued before buckets were chunked may omit the bucket;
+    // those were all queued in chunk 0.
+    if recurring {
+        let slot = ctx
+            .accounts
+            .user_subscriptions
+            .billing_slot(args.subscription_id);
+        match (slot, ctx.accounts.billing_bucket.as_mut()) {
+            (Some(_), None) => return err!(ErrorCode::BillingBucketMissing),
+            (slot, Some(bucket)) => {
+                let day = BillingBucket::day_for(previous_billing_ts);
+                bucket.ensure_for_day(bucket.key(), day, slot.unwrap_or_default())?;
+                bucket.remove(user_key, args.subscription_id);
+            }
+            (None, None) => {}
+        }
+        ctx.accounts
+            .user_subscriptions
+            .clear_billing_slot(args.subscription_id);
+
+        if status == SubscriptionStatus::Active {
+            let next_billing_ts = ctx
+                .accounts
+                .user_subscriptions
+                .subscriptions
+                .iter()
+                .find(|subscription| subscription.id == args.subscription_id)
+                .map(|subscription| subscription.next_billing_ts)
+                .ok_or(ErrorCode::SubscriptionNotFound)?;
+            let operator_info = ctx.accounts.operator.to_account_info();
+            let system_program_info = ctx.accounts.system_program.to_account_info();
+            let next_bucket = ctx
+                .accounts
+                .next_billing_bucket
+                .as_mut()
+                .ok_or(ErrorCode::BillingBucketMissing)?;
+            let next_bucket_info = next_bucket.to_account_info();
+            next_bucket.ensure_for_day(
+                next_bucket_info.key(),
+                BillingBucket::day_for(next_billing_ts),
+                args.next_billing_chunk,
+            )?;
+            next_bucket.reserve_entry(&next_bucket_info, &operator_info, &system_program_info)?;
+            next_bucket.insert(user_key, args.subscription_id);
+            ctx.accounts
+                .user_subscriptions
+                .set_billing_slot(args.subscription_id, args.next_billing_chunk);
+        }
     }
-    .to_string();
+
+    // Older layouts are upgraded on write, and a renewal may add a slot.
+    ctx.accounts.user_subscriptions.reserve_tail(
+        &ctx.accounts.user_subscriptions.to_account_info(),
+        &ctx.accounts.operator.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let subscription = ctx
+        .accounts
+        .user_subscriptions
+        .subscriptions
+        .iter()
+        .find(|subscription| subscription.id == args.subscription_id)
+        .ok_or(ErrorCode::SubscriptionNotFound)?;
+    let service = ctx
+        .accounts
+        .subscription_registry
+        .services
+        .iter()
+        .find(|service| service.id == subscription.service_id)
+        .ok_or(ErrorCode::SubscriptionServiceNotFound)?;
+    let merchant_info = ctx.accounts.merchant.to_account_info();
+    if let Some(mut merchant) = Merchant::load_for_creator(&merchant_info, service.creator)? {
+        merchant.record_payment(subscription.monthly_price_usdc)?;
+        merchant.store(&merchant_info)?;
+    }
+
+    let status_str = status.as_str().to_string();
 
     emit!(SubscriptionPaymentRecorded {
         operator: ctx.accounts.operator.key(),
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{CONFIG_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::instructions::register_payout_recipient::store_payout_recipient;
+use crate::subly::state::{
+    EncryptedReceiver, PayPalRecipientType, PayoutRail, PayoutRecipient, SublyConfig,
+    UserSubscriptions,
+};
+
+/// `receiver_hash` must be `sha256(user || receiver)`. The program cannot see
+/// the plaintext, so the keeper re-derives the hash after decrypting and
+/// refuses to pay out when it does not match.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RegisterEncryptedPayPalRecipientArgs {
+    pub recipient_type: String,
+    pub receiver_hash: [u8; 32],
+    pub operator_key: [u8; 32],
+    pub ephemeral_key: [u8; 32],
+    pub nonce: [u8; 24],
+    pub ciphertext: Vec<u8>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterEncryptedPayPalRecipient<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+        init_if_needed,
+        payer = user,
+        space = UserSubscriptions::INITIAL_SIZE,
+    )]
+    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<RegisterEncryptedPayPalRecipient>,
+    args: RegisterEncryptedPayPalRecipientArgs,
+) -> Result<()> {
+    let recipient = PayoutRecipient::Encrypted {
+        rail: PayoutRail::PayPal {
+            recipient_type: PayPalRecipientType::from_str(&args.recipient_type)?,
+        },
+        receiver_hash: args.receiver_hash,
+        receiver: EncryptedReceiver {
+            operator_key: args.operator_key,
+            ephemeral_key: args.ephemeral_key,
+            nonce: args.nonce,
+            ciphertext: args.ciphertext,
+        },
+    };
+
+    store_payout_recipient(
+        Some(&ctx.accounts.config),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_subscriptions,
+        &ctx.accounts.system_program,
+        recipient,
+    )
+}
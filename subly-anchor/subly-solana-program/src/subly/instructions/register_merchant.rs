@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{MAX_MERCHANT_NAME_LEN, MERCHANT_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::Merchant;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RegisterMerchantArgs {
+    pub display_name: String,
+    pub payout_destination: Pubkey,
+}
+
+#[event]
+pub struct MerchantRegistered {
+    pub owner: Pubkey,
+    pub merchant: Pubkey,
+    pub display_name: String,
+    pub payout_destination: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct RegisterMerchant<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Merchant::LEN,
+        seeds = [MERCHANT_SEED.as_bytes(), owner.key().as_ref()],
+        bump
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<RegisterMerchant>, args: RegisterMerchantArgs) -> Result<()> {
+    let display_name = args.display_name.trim().to_string();
+    require!(
+        display_name.len() <= MAX_MERCHANT_NAME_LEN,
+        ErrorCode::StringTooLong
+    );
+
+    let owner = ctx.accounts.owner.key();
+    let merchant = &mut ctx.accounts.merchant;
+    if merchant.owner == Pubkey::default() {
+        merchant.owner = owner;
+        merchant.active_subscribers = 0;
+        merchant.lifetime_revenue_usdc = 0;
+        merchant.pending_revenue_usdc = 0;
+        merchant.bump = ctx.bumps.merchant;
+        merchant.registered_at = Clock::get()?.unix_timestamp;
+    }
+    require_keys_eq!(merchant.owner, owner, ErrorCode::InvalidMerchantAccount);
+
+    merchant.display_name = display_name.clone();
+    merchant.payout_destination = args.payout_destination;
+
+    emit!(MerchantRegistered {
+        owner,
+        merchant: merchant.key(),
+        display_name,
+        payout_destination: args.payout_destination,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{CONFIG_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{PayoutRecipient, SublyConfig, UserSubscriptions};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RegisterPayoutRecipientArgs {
+    pub recipient: PayoutRecipient,
+}
+
+#[event]
+pub struct PayoutRecipientRegistered {
+    pub user: Pubkey,
+    pub rail: String,
+    pub recipient_type: String,
+    pub receiver_hash: [u8; 32],
+    pub encrypted: bool,
+}
+
+#[derive(Accounts)]
+pub struct RegisterPayoutRecipient<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+        init_if_needed,
+        payer = user,
+        space = UserSubscriptions::INITIAL_SIZE,
+    )]
+    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<RegisterPayoutRecipient>,
+    args: RegisterPayoutRecipientArgs,
+) -> Result<()> {
+    store_payout_recipient(
+        Some(&ctx.accounts.config),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_subscriptions,
+        &ctx.accounts.system_program,
+        args.recipient,
+    )
+}
+
+/// Validates a recipient before it is stored anywhere. `config` is only
+/// needed to check the operator key of encrypted recipients.
+pub fn check_payout_recipient(
+    recipient: &PayoutRecipient,
+    config: Option<&SublyConfig>,
+) -> Result<()> {
+    recipient.validate()?;
+    if let PayoutRecipient::Encrypted { receiver, .. } = recipient {
+        config
+            .ok_or(ErrorCode::ReceiverEncryptionKeyNotSet)?
+            .ensure_receiver_key(receiver.operator_key)?;
+    }
+    Ok(())
+}
+
+/// Shared by every registration entry point.
+pub fn store_payout_recipient<'info>(
+    config: Option<&SublyConfig>,
+    user: &Signer<'info>,
+    user_subscriptions: &mut Account<'info, UserSubscriptions>,
+    system_program: &Program<'info, System>,
+    recipient: PayoutRecipient,
+) -> Result<()> {
+    check_payout_recipient(&recipient, config)?;
+
+    let user_key = user.key();
+    let (expected_user_subscriptions, subscriptions_bump) = Pubkey::find_program_address(
+        &[USER_SUBSCRIPTIONS_SEED.as_bytes(), user_key.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(
+        expected_user_subscriptions,
+        user_subscriptions.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    user_subscriptions.ensure_owner(user_key, subscriptions_bump);
+
+    let rail = recipient.rail();
+    let encrypted = matches!(recipient, PayoutRecipient::Encrypted { .. });
+    let receiver_hash = recipient.receiver_hash(&user_key);
+    user_subscriptions.set_payout_recipient(recipient);
+
+    user_subscriptions.reserve_tail(
+        &user_subscriptions.to_account_info(),
+        &user.to_account_info(),
+        &system_program.to_account_info(),
+    )?;
+
+    emit!(PayoutRecipientRegistered {
+        user: user_key,
+        rail: rail.as_str().to_string(),
+        recipient_type: rail.recipient_type().to_string(),
+        receiver_hash,
+        encrypted,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anchor_lang::{prelude::*, AccountDeserialize};
+
+use crate::subly::constants::{GROUP_BUDGET_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{GroupBudget, UserSubscriptions};
+
+#[event]
+pub struct GroupMemberRemoved {
+    pub group: Pubkey,
+    pub member: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(member: Pubkey)]
+pub struct RemoveGroupMember<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            GROUP_BUDGET_SEED.as_bytes(),
+            group_budget.admin.as_ref(),
+            &group_budget.group_id.to_le_bytes(),
+        ],
+        bump = group_budget.bump,
+        has_one = admin @ ErrorCode::UnauthorizedAuthority,
+    )]
+    pub group_budget: Account<'info, GroupBudget>,
+    /// CHECK: May not exist yet; read only to make sure the member has no
+    /// live subscriptions charged to the group.
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), member.as_ref()],
+        bump,
+    )]
+    pub member_subscriptions: UncheckedAccount<'info>,
+}
+
+pub fn handler(ctx: Context<RemoveGroupMember>, member: Pubkey) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let group_key = ctx.accounts.group_budget.key();
+
+    let subscriptions_info = ctx.accounts.member_subscriptions.to_account_info();
+    if !subscriptions_info.data_is_empty() {
+        let data_ref = subscriptions_info.try_borrow_data()?;
+        let mut data_slice: &[u8] = &data_ref;
+        let mut member_subscriptions = UserSubscriptions::try_deserialize(&mut data_slice)?;
+        drop(data_ref);
+        member_subscriptions.refresh(now)?;
+        require!(
+            member_subscriptions.committed_to_group(Some(group_key))? == 0,
+            ErrorCode::GroupMemberInUse
+        );
+    }
+
+    ctx.accounts.group_budget.remove_member(member)?;
+
+    emit!(GroupMemberRemoved {
+        group: group_key,
+        member,
+    });
+
+    Ok(())
+}
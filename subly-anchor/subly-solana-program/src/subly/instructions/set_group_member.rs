@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::GROUP_BUDGET_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::GroupBudget;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SetGroupMemberArgs {
+    pub member: Pubkey,
+    /// Zero leaves the member limited only by the pool.
+    pub spending_limit_usdc: u64,
+}
+
+#[event]
+pub struct GroupMemberUpdated {
+    pub group: Pubkey,
+    pub member: Pubkey,
+    pub spending_limit_usdc: u64,
+}
+
+#[derive(Accounts)]
+pub struct SetGroupMember<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            GROUP_BUDGET_SEED.as_bytes(),
+            group_budget.admin.as_ref(),
+            &group_budget.group_id.to_le_bytes(),
+        ],
+        bump = group_budget.bump,
+        has_one = admin @ ErrorCode::UnauthorizedAuthority,
+    )]
+    pub group_budget: Account<'info, GroupBudget>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetGroupMember>, args: SetGroupMemberArgs) -> Result<()> {
+    let group_budget = &mut ctx.accounts.group_budget;
+    group_budget.set_member(args.member, args.spending_limit_usdc)?;
+    group_budget.reserve(
+        &group_budget.to_account_info(),
+        &ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    emit!(GroupMemberUpdated {
+        group: group_budget.key(),
+        member: args.member,
+        spending_limit_usdc: args.spending_limit_usdc,
+    });
+
+    Ok(())
+}
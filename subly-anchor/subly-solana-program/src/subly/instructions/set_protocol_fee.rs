@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{CONFIG_SEED, MAX_PROTOCOL_FEE_BPS};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::SublyConfig;
+
+#[event]
+pub struct ProtocolFeeUpdated {
+    pub authority: Pubkey,
+    pub previous_fee_bps: u16,
+    pub fee_bps: u16,
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolFee<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    pub authority: Signer<'info>,
+}
+
+pub fn handler(ctx: Context<SetProtocolFee>, fee_bps: u16) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        config.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+    require!(
+        fee_bps <= MAX_PROTOCOL_FEE_BPS,
+        ErrorCode::ProtocolFeeTooHigh
+    );
+
+    // Yield accrued so far keeps the rate it accrued under, even for
+    // positions that only sync later.
+    let previous_fee_bps = config.protocol_fee_bps;
+    if fee_bps != previous_fee_bps {
+        config.accrue_to(Clock::get()?.unix_timestamp)?;
+        config.checkpoint_protocol_fee();
+        config.protocol_fee_bps = fee_bps;
+    }
+
+    emit!(ProtocolFeeUpdated {
+        authority: ctx.accounts.authority.key(),
+        previous_fee_bps,
+        fee_bps,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::CONFIG_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::SublyConfig;
+
+#[event]
+pub struct ReceiverEncryptionKeyUpdated {
+    pub authority: Pubkey,
+    pub previous_key: [u8; 32],
+    pub key: [u8; 32],
+}
+
+#[derive(Accounts)]
+pub struct SetReceiverEncryptionKey<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    pub authority: Signer<'info>,
+}
+
+/// Publishes the operator's X25519 public key. Passing all zeroes disables
+/// encrypted registration until a new key is set.
+pub fn handler(ctx: Context<SetReceiverEncryptionKey>, key: [u8; 32]) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        config.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+
+    let previous_key = config.receiver_encryption_key;
+    config.receiver_encryption_key = key;
+
+    emit!(ReceiverEncryptionKeyUpdated {
+        authority: ctx.accounts.authority.key(),
+        previous_key,
+        key,
+    });
+
+    Ok(())
+}
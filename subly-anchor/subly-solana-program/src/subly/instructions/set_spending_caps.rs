@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::USER_SUBSCRIPTIONS_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{SpendingCaps, UserSubscriptions};
+
+#[event]
+pub struct SpendingCapsUpdated {
+    pub user: Pubkey,
+    pub max_budget_bps: u16,
+    pub monthly_cap_usdc: u64,
+    pub max_service_price_usdc: u64,
+}
+
+#[derive(Accounts)]
+pub struct SetSpendingCaps<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+        init_if_needed,
+        payer = user,
+        space = UserSubscriptions::INITIAL_SIZE,
+    )]
+    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Caps only gate new subscriptions; existing ones above a lowered cap keep
+/// running until cancelled.
+pub fn handler(ctx: Context<SetSpendingCaps>, caps: SpendingCaps) -> Result<()> {
+    caps.validate()?;
+
+    let user_key = ctx.accounts.user.key();
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+    user_subscriptions.ensure_owner(user_key, ctx.bumps.user_subscriptions);
+    require_keys_eq!(
+        user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    user_subscriptions.spending_caps = caps;
+    user_subscriptions.reserve_tail(
+        &user_subscriptions.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    emit!(SpendingCapsUpdated {
+        user: user_key,
+        max_budget_bps: caps.max_budget_bps,
+        monthly_cap_usdc: caps.monthly_cap_usdc,
+        max_service_price_usdc: caps.max_service_price_usdc,
+    });
+
+    Ok(())
+}
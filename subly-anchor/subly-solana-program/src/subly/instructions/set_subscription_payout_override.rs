@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{CONFIG_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::instructions::register_payout_recipient::check_payout_recipient;
+use crate::subly::state::{PayoutRecipient, SublyConfig, UserSubscriptions};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SetSubscriptionPayoutOverrideArgs {
+    pub subscription_id: u64,
+    /// `None` falls back to the account wide recipient.
+    pub recipient: Option<PayoutRecipient>,
+}
+
+#[event]
+pub struct SubscriptionPayoutOverrideUpdated {
+    pub user: Pubkey,
+    pub subscription_id: u64,
+    pub rail: String,
+    pub recipient_type: String,
+    pub receiver_hash: [u8; 32],
+    pub overridden: bool,
+}
+
+#[derive(Accounts)]
+pub struct SetSubscriptionPayoutOverride<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_subscriptions.bump,
+    )]
+    pub user_subscriptions: Account<'info, UserSubscriptions>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<SetSubscriptionPayoutOverride>,
+    args: SetSubscriptionPayoutOverrideArgs,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let user_key = ctx.accounts.user.key();
+
+    require_keys_eq!(
+        ctx.accounts.user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    if let Some(recipient) = args.recipient.as_ref() {
+        check_payout_recipient(recipient, Some(&ctx.accounts.config))?;
+    }
+
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+    user_subscriptions.refresh(now)?;
+    let overridden = args.recipient.is_some();
+    user_subscriptions.set_payout_override(args.subscription_id, args.recipient)?;
+
+    // Subscriptions left without any recipient are simply skipped by keepers,
+    // the same as before a default recipient is registered.
+    let effective = user_subscriptions.payout_recipient_for(args.subscription_id);
+    let rail = effective.map(PayoutRecipient::rail);
+    let receiver_hash = user_subscriptions.receiver_hash_for(args.subscription_id);
+
+    user_subscriptions.reserve_tail(
+        &user_subscriptions.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    emit!(SubscriptionPayoutOverrideUpdated {
+        user: user_key,
+        subscription_id: args.subscription_id,
+        rail: rail.map_or("", |rail| rail.as_str()).to_string(),
+        recipient_type: rail.map_or("", |rail| rail.recipient_type()).to_string(),
+        receiver_hash,
+        overridden,
+    });
+
+    Ok(())
+}
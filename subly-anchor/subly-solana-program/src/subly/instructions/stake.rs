@@ -73,7 +73,8 @@ pub fn handler(ctx: Context<Stake>, amount: u64, lock_option: u8) -> Result<()>
         ctx.accounts.user.key(),
         ErrorCode::InvalidPositionOwner
     );
-    user_position.sync_against_index(config.acc_index, now)?;
+    let (accrued, protocol_fees) = user_position.sync_against_index(config, now)?;
+    config.record_protocol_fees(user_position.owner, accrued, protocol_fees)?;
 
     let transfer_accounts = Transfer {
         from: ctx.accounts.user_token_account.to_account_info(),
@@ -6,11 +6,30 @@ use crate::subly::constants::{
     USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED,
 };
 use crate::subly::error::ErrorCode;
-use crate::subly::state::{SublyConfig, SubscriptionRegistry, UserStake, UserSubscriptions};
+use crate::subly::instructions::register_payout_recipient::check_payout_recipient;
+use crate::subly::state::{
+    BeneficiaryGifts, BillingBucket, BillingSlot, Gift, GroupBudget, GroupCharge, Merchant,
+    PayoutRecipient, SublyConfig, SubscriptionRegistry, UserStake, UserSubscriptions,
+};
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct SubscribeServiceArgs {
     pub service_id: u64,
+    /// Pays this subscription out somewhere other than the account wide
+    /// recipient.
+    pub payout_override: Option<PayoutRecipient>,
+    /// Subscribes on someone else's behalf, paid from this staker's budget.
+    pub gift: Option<GiftArgs>,
+    /// Chunk of the renewal day's bucket passed as `billing_bucket`; use the
+    /// first one that is not full.
+    pub billing_chunk: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct GiftArgs {
+    /// Wallet that can see the gift. Gifts are always paid out through
+    /// `payout_override`, which names the beneficiary's recipient.
+    pub beneficiary: Option<Pubkey>,
 }
 
 #[event]
@@ -19,8 +38,20 @@ pub struct SubscriptionActivated {
     pub subscription_id: u64,
     pub service_id: u64,
     pub monthly_price_usdc: u64,
+    pub rail: String,
     pub recipient_type: String,
-    pub receiver: String,
+    pub receiver_hash: [u8; 32],
+    /// Group budget the subscription is charged to, if any.
+    pub group: Option<Pubkey>,
+}
+
+#[event]
+pub struct SubscriptionGifted {
+    pub sponsor: Pubkey,
+    pub beneficiary: Option<Pubkey>,
+    pub subscription_id: u64,
+    pub service_id: u64,
+    pub monthly_price_usdc: u64,
 }
 
 #[derive(Accounts)]
@@ -52,6 +83,18 @@ pub struct SubscribeService<'info> {
         bump = subscription_registry.bump,
     )]
     pub subscription_registry: Account<'info, SubscriptionRegistry>,
+    /// CHECK: The service creator's merchant PDA, registered or not; the
+    /// handler checks the address and loads it when it exists.
+    #[account(mut)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub billing_bucket: Account<'info, BillingBucket>,
+    /// Required for gifts to a beneficiary wallet.
+    #[account(mut)]
+    pub beneficiary_gifts: Option<Account<'info, BeneficiaryGifts>>,
+    /// Charges the subscription to a group pool. Pass every member's
+    /// `UserSubscriptions` PDA as remaining accounts.
+    pub group_budget: Option<Account<'info, GroupBudget>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -107,10 +150,15 @@ pub fn handler(ctx: Context<SubscribeService>, args: SubscribeServiceArgs) -> Re
         .ensure_owner(user_key, subscriptions_bump);
 
     ctx.accounts.user_subscriptions.refresh(now)?;
-    require!(
-        ctx.accounts.user_subscriptions.paypal_configured,
-        ErrorCode::PayPalInfoMissing
-    );
+    if let Some(recipient) = args.payout_override.as_ref() {
+        check_payout_recipient(recipient, Some(config))?;
+    }
+    let rail = args
+        .payout_override
+        .as_ref()
+        .or(ctx.accounts.user_subscriptions.payout_recipient.as_ref())
+        .map(PayoutRecipient::rail)
+        .ok_or(ErrorCode::PayPalInfoMissing)?;
 
     let service = ctx
         .accounts
@@ -120,18 +168,78 @@ pub fn handler(ctx: Context<SubscribeService>, args: SubscribeServiceArgs) -> Re
         .find(|service| service.id == args.service_id)
         .ok_or(ErrorCode::SubscriptionServiceNotFound)?;
 
-    require!(
-        !ctx.accounts
+    let already_subscribed = match args.gift {
+        None => ctx
+            .accounts
             .user_subscriptions
             .has_active_or_pending_for_service(service.id),
-        ErrorCode::SubscriptionAlreadyExists
+        Some(gift) => {
+            // The beneficiary is paid through the override; the sponsor's
+            // own recipient is never used for a gift.
+            let recipient = args
+                .payout_override
+                .as_ref()
+                .ok_or(ErrorCode::GiftPayoutOverrideMissing)?;
+            if let Some(beneficiary) = gift.beneficiary {
+                require_keys_neq!(beneficiary, user_key, ErrorCode::InvalidGiftBeneficiary);
+            }
+            let receiver_hash = recipient.receiver_hash(&user_key);
+            ctx.accounts.user_subscriptions.has_active_or_pending_gift(
+                service.id,
+                gift.beneficiary,
+                receiver_hash,
+            )
+        }
+    };
+    require!(!already_subscribed, ErrorCode::SubscriptionAlreadyExists);
+
+    let spending_caps = ctx.accounts.user_subscriptions.spending_caps;
+    require!(
+        spending_caps.allows_price(service.monthly_price_usdc),
+        ErrorCode::ServicePriceAboveCap
     );
 
-    let monthly_budget =
-        compute_monthly_budget(ctx.accounts.user_position.total_principal, config.apy_bps)?;
+    // Group-funded subscriptions are checked against the pooled tranches and
+    // the member's limit; everything else against the user's own stake and
+    // spending caps.
+    let (monthly_budget, committed, spending_cap) = match ctx.accounts.group_budget.as_ref() {
+        Some(group_budget) => {
+            let group_key = group_budget.key();
+            group_budget.ensure_key(group_key)?;
+            let member = group_budget.member(user_key)?;
+            let member_committed = ctx
+                .accounts
+                .user_subscriptions
+                .committed_to_group(Some(group_key))?
+                .checked_add(service.monthly_price_usdc)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(
+                member.spending_limit_usdc == 0 || member_committed <= member.spending_limit_usdc,
+                ErrorCode::GroupSpendingLimitExceeded
+            );
+            (
+                compute_monthly_budget(
+                    group_budget.contributed_principal()?,
+                    config.net_apy_bps(),
+                )?,
+                group_budget.total_committed(group_key, ctx.remaining_accounts, now)?,
+                None,
+            )
+        }
+        None => {
+            let monthly_budget = compute_monthly_budget(
+                ctx.accounts.user_position.total_principal,
+                config.net_apy_bps(),
+            )?;
+            (
+                monthly_budget,
+                ctx.accounts.user_subscriptions.personal_committed()?,
+                Some(spending_caps.cap_budget(monthly_budget)?),
+            )
+        }
+    };
     require!(monthly_budget > 0, ErrorCode::SubscriptionBudgetExceeded);
 
-    let committed = ctx.accounts.user_subscriptions.total_committed()?;
     let required_commitment = committed
         .checked_add(service.monthly_price_usdc)
         .ok_or(ErrorCode::MathOverflow)?;
@@ -139,13 +247,29 @@ pub fn handler(ctx: Context<SubscribeService>, args: SubscribeServiceArgs) -> Re
         required_commitment <= monthly_budget,
         ErrorCode::SubscriptionBudgetExceeded
     );
+    if let Some(spending_cap) = spending_cap {
+        require!(
+            required_commitment <= spending_cap,
+            ErrorCode::SpendingCapExceeded
+        );
+    }
 
-    // Ensure account has enough space and rent to append the new subscription.
+    // Ensure account has enough space and rent to append the new subscription
+    // along with its override and gift, if any.
     let desired_len = ctx.accounts.user_subscriptions.subscriptions.len() + 1;
-    let required_space = UserSubscriptions::required_size(
-        desired_len,
-        ctx.accounts.user_subscriptions.receiver_len(),
-    );
+    let tail_len = ctx.accounts.user_subscriptions.tail_len()
+        + args
+            .payout_override
+            .as_ref()
+            .map_or(0, |recipient| 8 + recipient.space())
+        + args.gift.map_or(0, |_| Gift::SIZE)
+        + ctx
+            .accounts
+            .group_budget
+            .as_ref()
+            .map_or(0, |_| GroupCharge::SIZE)
+        + BillingSlot::SIZE;
+    let required_space = UserSubscriptions::required_size(desired_len, tail_len);
     let user_subscriptions_info = ctx.accounts.user_subscriptions.to_account_info();
     if user_subscriptions_info.data_len() < required_space {
         let rent = Rent::get()?;
@@ -163,34 +287,103 @@ pub fn handler(ctx: Context<SubscribeService>, args: SubscribeServiceArgs) -> Re
         user_subscriptions_info.resize(required_space)?;
     }
 
+    // The first renewal lands in the bucket of the day one billing period from now.
+    let next_billing_ts = now
+        .checked_add(BILLING_PERIOD_SECONDS)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let bucket_info = ctx.accounts.billing_bucket.to_account_info();
+    ctx.accounts.billing_bucket.ensure_for_day(
+        bucket_info.key(),
+        BillingBucket::day_for(next_billing_ts),
+        args.billing_chunk,
+    )?;
+    ctx.accounts.billing_bucket.reserve_entry(
+        &bucket_info,
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
     let subscription_id = ctx.accounts.user_subscriptions.record_subscription(
         service.id,
         service.monthly_price_usdc,
         now,
         BILLING_PERIOD_SECONDS,
     )?;
+    if args.payout_override.is_some() {
+        ctx.accounts
+            .user_subscriptions
+            .set_payout_override(subscription_id, args.payout_override)?;
+    }
+
+    if let Some(group_budget) = ctx.accounts.group_budget.as_ref() {
+        ctx.accounts
+            .user_subscriptions
+            .record_group_charge(subscription_id, group_budget.key());
+    }
+
+    if let Some(gift) = args.gift {
+        ctx.accounts
+            .user_subscriptions
+            .record_gift(subscription_id, gift.beneficiary);
+
+        if let Some(beneficiary) = gift.beneficiary {
+            let beneficiary_gifts = ctx
+                .accounts
+                .beneficiary_gifts
+                .as_mut()
+                .ok_or(ErrorCode::InvalidBeneficiaryGifts)?;
+            let gifts_info = beneficiary_gifts.to_account_info();
+            beneficiary_gifts.ensure_for_beneficiary(gifts_info.key(), beneficiary)?;
+            beneficiary_gifts.add_sponsor(
+                &gifts_info,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                user_key,
+            )?;
+        }
+
+        emit!(SubscriptionGifted {
+            sponsor: user_key,
+            beneficiary: gift.beneficiary,
+            subscription_id,
+            service_id: service.id,
+            monthly_price_usdc: service.monthly_price_usdc,
+        });
+    }
+
+    ctx.accounts
+        .billing_bucket
+        .insert(user_key, subscription_id);
+    ctx.accounts
+        .user_subscriptions
+        .set_billing_slot(subscription_id, args.billing_chunk);
 
-    let recipient_type = ctx
+    let merchant_info = ctx.accounts.merchant.to_account_info();
+    if let Some(mut merchant) = Merchant::load_for_creator(&merchant_info, service.creator)? {
+        merchant.record_subscription_started(now, service.monthly_price_usdc)?;
+        merchant.store(&merchant_info)?;
+    }
+
+    let receiver_hash = ctx
         .accounts
         .user_subscriptions
-        .paypal_recipient_type
-        .as_str()
-        .to_string();
-    let receiver = ctx.accounts.user_subscriptions.paypal_receiver.clone();
+        .receiver_hash_for(subscription_id);
 
     emit!(SubscriptionActivated {
         user: user_key,
         subscription_id,
         service_id: service.id,
         monthly_price_usdc: service.monthly_price_usdc,
-        recipient_type,
-        receiver,
+        rail: rail.as_str().to_string(),
+        recipient_type: rail.recipient_type().to_string(),
+        receiver_hash,
+        group: ctx.accounts.group_budget.as_ref().map(|group| group.key()),
     });
 
     Ok(())
 }
 
-fn compute_monthly_budget(total_principal: u64, apy_bps: u16) -> Result<u64> {
+pub fn compute_monthly_budget(total_principal: u64, apy_bps: u16) -> Result<u64> {
     if total_principal == 0 || apy_bps == 0 {
         return Ok(0);
     }
@@ -207,3 +400,21 @@ fn compute_monthly_budget(total_principal: u64, apy_bps: u16) -> Result<u64> {
 
     u64::try_from(monthly_yield).map_err(|_| ErrorCode::MathOverflow.into())
 }
+
+/// Smallest principal whose `compute_monthly_budget` covers `monthly_budget`.
+pub fn required_principal_for_budget(monthly_budget: u64, apy_bps: u16) -> Result<u64> {
+    if monthly_budget == 0 {
+        return Ok(0);
+    }
+    require!(apy_bps > 0, ErrorCode::YieldUnavailable);
+
+    let annual_yield = (monthly_budget as u128)
+        .checked_mul(12)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let principal = annual_yield
+        .checked_mul(BASIS_POINTS_DIVISOR as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .div_ceil(apy_bps as u128);
+
+    u64::try_from(principal).map_err(|_| ErrorCode::MathOverflow.into())
+}
@@ -43,7 +43,8 @@ pub fn handler(ctx: Context<SyncYield>) -> Result<()> {
     config.accrue_to(now)?;
 
     let user_position = &mut ctx.accounts.user_position;
-    user_position.sync_against_index(config.acc_index, now)?;
+    let (accrued, protocol_fees) = user_position.sync_against_index(config, now)?;
+    config.record_protocol_fees(user_position.owner, accrued, protocol_fees)?;
 
     let total_unrealized = user_position.total_unrealized_yield()?;
     let total_generated = user_position.total_yield_generated()?;
@@ -0,0 +1,290 @@
+use anchor_lang::{prelude::*, AccountDeserialize, Discriminator};
+
+use crate::subly::constants::{CONFIG_SEED, USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::instructions::register_payout_recipient::check_payout_recipient;
+use crate::subly::state::{
+    BeneficiaryGifts, BillingBucket, PayoutRecipient, SublyConfig, SubscriptionStatus, UserStake,
+    UserSubscriptions,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct TransferPositionArgs {
+    /// Replaces the account wide recipient. Required when it is encrypted,
+    /// since sealed receivers are hashed with the old wallet.
+    pub payout_recipient: Option<PayoutRecipient>,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub tranche_count: u32,
+    pub subscription_count: u32,
+}
+
+/// Moves a position to a wallet that has none yet. Pass the billing bucket
+/// of every queued subscription and the `beneficiary_gifts` PDA of every
+/// gift beneficiary as writable remaining accounts so they follow the new
+/// owner; the transfer fails if any of them is missing. The old owner's
+/// subscriptions PDA is always required, so commitments cannot be left
+/// behind without the stake that backed them.
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
+    pub old_owner: Signer<'info>,
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+    #[account(
+        mut,
+        close = old_owner,
+        seeds = [USER_POSITION_SEED.as_bytes(), old_owner.key().as_ref()],
+        bump = old_position.bump,
+        constraint = old_position.owner == old_owner.key() @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub old_position: Account<'info, UserStake>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = UserStake::required_size(
+            old_position
+                .entries
+                .len()
+                .max(UserStake::INITIAL_ENTRY_CAPACITY)
+        ),
+        seeds = [USER_POSITION_SEED.as_bytes(), new_owner.key().as_ref()],
+        bump,
+    )]
+    pub new_position: Account<'info, UserStake>,
+    /// CHECK: The old owner's subscriptions PDA, which may never have been
+    /// created. The handler deserializes and closes it when it exists.
+    #[account(
+        mut,
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), old_owner.key().as_ref()],
+        bump,
+    )]
+    pub old_subscriptions: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = UserSubscriptions::INITIAL_SIZE,
+        seeds = [USER_SUBSCRIPTIONS_SEED.as_bytes(), new_owner.key().as_ref()],
+        bump,
+    )]
+    pub new_subscriptions: Option<Account<'info, UserSubscriptions>>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<TransferPosition>, args: TransferPositionArgs) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let old_owner = ctx.accounts.old_owner.key();
+    let new_owner = ctx.accounts.new_owner.key();
+
+    ctx.accounts.config.ensure_active()?;
+    require_keys_neq!(old_owner, new_owner, ErrorCode::InvalidPositionOwner);
+
+    // Pledges are seeded by the old wallet and recorded in the group, so they
+    // have to be withdrawn first.
+    let old_position = &ctx.accounts.old_position;
+    require!(
+        old_position.pledged_principal()? == 0,
+        ErrorCode::TranchePledged
+    );
+
+    let new_position = &mut ctx.accounts.new_position;
+    new_position.owner = new_owner;
+    new_position.bump = ctx.bumps.new_position;
+    new_position.total_principal = old_position.total_principal;
+    new_position.last_updated_ts = old_position.last_updated_ts;
+    new_position.next_tranche_id = old_position.next_tranche_id;
+    new_position.entries = old_position.entries.clone();
+    let tranche_count = old_position.entries.len() as u32;
+
+    let old_subscriptions_info = ctx.accounts.old_subscriptions.to_account_info();
+    let old_subscriptions = load_old_subscriptions(&old_subscriptions_info)?;
+    let had_subscriptions = old_subscriptions.is_some();
+
+    let subscription_count = match (old_subscriptions, ctx.accounts.new_subscriptions.as_mut()) {
+        (Some(mut moved), Some(new_subscriptions)) => {
+            require_keys_eq!(
+                moved.owner,
+                old_owner,
+                ErrorCode::InvalidSubscriptionAccount
+            );
+
+            moved.refresh(now)?;
+            require!(moved.group_charges.is_empty(), ErrorCode::GroupMemberInUse);
+
+            let replaced = args.payout_recipient.is_some();
+            if let Some(recipient) = args.payout_recipient {
+                check_payout_recipient(&recipient, Some(&ctx.accounts.config))?;
+                moved.set_payout_recipient(recipient);
+            }
+            let stale_recipient = !replaced
+                && matches!(
+                    moved.payout_recipient,
+                    Some(PayoutRecipient::Encrypted { .. })
+                );
+            let stale_override = moved
+                .payout_overrides
+                .iter()
+                .any(|entry| matches!(entry.recipient, PayoutRecipient::Encrypted { .. }));
+            require!(
+                !stale_recipient && !stale_override,
+                ErrorCode::EncryptedRecipientRebindRequired
+            );
+
+            rekey_remaining_accounts(&mut moved, old_owner, new_owner, ctx.remaining_accounts)?;
+
+            moved.owner = new_owner;
+            moved.bump = ctx
+                .bumps
+                .new_subscriptions
+                .ok_or(ErrorCode::InvalidSubscriptionAccount)?;
+            let subscription_count = moved.subscriptions.len() as u32;
+            new_subscriptions.set_inner(moved);
+            new_subscriptions.reserve_tail(
+                &new_subscriptions.to_account_info(),
+                &ctx.accounts.new_owner.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+            subscription_count
+        }
+        (None, None) => 0,
+        _ => return err!(ErrorCode::InvalidSubscriptionAccount),
+    };
+
+    if had_subscriptions {
+        close_account(
+            &old_subscriptions_info,
+            &ctx.accounts.old_owner.to_account_info(),
+        )?;
+    }
+
+    emit!(PositionTransferred {
+        old_owner,
+        new_owner,
+        tranche_count,
+        subscription_count,
+    });
+
+    Ok(())
+}
+
+/// Reads the old owner's subscriptions, or `None` when the PDA was never
+/// created. Lamports sent to the bare address do not make it exist.
+fn load_old_subscriptions(account_info: &AccountInfo) -> Result<Option<UserSubscriptions>> {
+    if account_info.owner == &System::id() && account_info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(
+        *account_info.owner,
+        crate::ID,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+    let data = account_info.try_borrow_data()?;
+    let mut data_slice: &[u8] = &data;
+    let subscriptions = UserSubscriptions::try_deserialize(&mut data_slice)
+        .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+    Ok(Some(subscriptions))
+}
+
+fn close_account<'info>(
+    account_info: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let refunded = destination
+        .lamports()
+        .checked_add(account_info.lamports())
+        .ok_or(ErrorCode::MathOverflow)?;
+    **destination.try_borrow_mut_lamports()? = refunded;
+    **account_info.try_borrow_mut_lamports()? = 0;
+    account_info.assign(&System::id());
+    account_info.resize(0)?;
+    Ok(())
+}
+
+/// Moves the old owner's billing bucket entries and gift inbox listings over
+/// to the new owner. Every queued subscription's bucket and every gift
+/// beneficiary's inbox must be present.
+fn rekey_remaining_accounts(
+    moved: &mut UserSubscriptions,
+    old_owner: Pubkey,
+    new_owner: Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<()> {
+    let mut rekeyed_subscriptions: Vec<u64> = Vec::new();
+    let mut rekeyed_beneficiaries: Vec<Pubkey> = Vec::new();
+
+    for account_info in remaining_accounts.iter() {
+        require_keys_eq!(
+            *account_info.owner,
+            crate::ID,
+            ErrorCode::InvalidSubscriptionAccount
+        );
+        require!(
+            account_info.is_writable,
+            ErrorCode::InvalidSubscriptionAccount
+        );
+
+        let mut data = account_info.try_borrow_mut_data()?;
+        if data.starts_with(BillingBucket::DISCRIMINATOR) {
+            let mut data_slice: &[u8] = &data;
+            let mut bucket = BillingBucket::try_deserialize(&mut data_slice)?;
+            for subscription in moved.subscriptions.iter() {
+                if subscription.status != SubscriptionStatus::Cancelled
+                    && bucket.replace_user(old_owner, new_owner, subscription.id)
+                {
+                    rekeyed_subscriptions.push(subscription.id);
+                }
+            }
+            let mut writer: &mut [u8] = &mut data;
+            bucket.try_serialize(&mut writer)?;
+        } else if data.starts_with(BeneficiaryGifts::DISCRIMINATOR) {
+            let mut data_slice: &[u8] = &data;
+            let mut gifts = BeneficiaryGifts::try_deserialize(&mut data_slice)?;
+            if gifts.replace_sponsor(old_owner, new_owner) {
+                rekeyed_beneficiaries.push(gifts.beneficiary);
+            }
+            let mut writer: &mut [u8] = &mut data;
+            gifts.try_serialize(&mut writer)?;
+        } else {
+            return err!(ErrorCode::InvalidSubscriptionAccount);
+        }
+    }
+
+    // An entry left on the old wallet would never be billed again.
+    // `refresh` already dropped the slots of cancelled subscriptions.
+    for slot in moved.billing_slots.iter() {
+        require!(
+            rekeyed_subscriptions.contains(&slot.subscription_id),
+            ErrorCode::BillingBucketMissing
+        );
+    }
+    // Entries queued before slots were tracked all live in chunk 0; record
+    // them so later payments require their bucket too.
+    for subscription_id in rekeyed_subscriptions {
+        if moved.billing_slot(subscription_id).is_none() {
+            moved.set_billing_slot(subscription_id, 0);
+        }
+    }
+
+    // `refresh` already dropped the gifts of cancelled subscriptions.
+    for gift in moved.gifts.iter() {
+        if let Some(beneficiary) = gift.beneficiary {
+            // The new wallet would end up sponsoring itself.
+            require_keys_neq!(beneficiary, new_owner, ErrorCode::InvalidGiftBeneficiary);
+            require!(
+                rekeyed_beneficiaries.contains(&beneficiary),
+                ErrorCode::InvalidBeneficiaryGifts
+            );
+        }
+    }
+
+    Ok(())
+}
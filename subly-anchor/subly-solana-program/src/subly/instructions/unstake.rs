@@ -1,11 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::subly::constants::{CONFIG_SEED, USER_POSITION_SEED, VAULT_SEED};
+use crate::subly::constants::{CONFIG_SEED, TRANCHE_PLEDGE_SEED, USER_POSITION_SEED, VAULT_SEED};
 use crate::subly::error::ErrorCode;
 use crate::subly::state::{SublyConfig, UserStake};
 
 #[derive(Accounts)]
+#[instruction(tranche_id: u64)]
 pub struct Unstake<'info> {
     #[account(
         mut,
@@ -22,6 +23,17 @@ pub struct Unstake<'info> {
         constraint = user_position.owner == user.key() @ ErrorCode::InvalidPositionOwner,
     )]
     pub user_position: Account<'info, UserStake>,
+    /// CHECK: Only checked to be empty; a pledged tranche stays in its group
+    /// until the contribution is withdrawn.
+    #[account(
+        seeds = [
+            TRANCHE_PLEDGE_SEED.as_bytes(),
+            user.key().as_ref(),
+            &tranche_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub tranche_pledge: UncheckedAccount<'info>,
     #[account(
         mut,
         seeds = [VAULT_SEED.as_bytes()],
@@ -42,12 +54,18 @@ pub fn handler(ctx: Context<Unstake>, tranche_id: u64) -> Result<()> {
     let clock = Clock::get()?;
     let now = clock.unix_timestamp;
 
+    require!(
+        ctx.accounts.tranche_pledge.data_is_empty(),
+        ErrorCode::TranchePledged
+    );
+
     let config = &mut ctx.accounts.config;
     config.ensure_active()?;
     config.accrue_to(now)?;
 
     let user_position = &mut ctx.accounts.user_position;
-    user_position.sync_against_index(config.acc_index, now)?;
+    let (accrued, protocol_fees) = user_position.sync_against_index(config, now)?;
+    config.record_protocol_fees(user_position.owner, accrued, protocol_fees)?;
 
     let principal = user_position.unstake_tranche(tranche_id, now)?;
     require!(principal > 0, ErrorCode::NothingToUnstake);
@@ -1,7 +1,10 @@
 use anchor_lang::prelude::*;
 
-use crate::subly::constants::{BILLING_PERIOD_SECONDS, USER_SUBSCRIPTIONS_SEED};
-use crate::subly::state::UserSubscriptions;
+use crate::subly::constants::{
+    BILLING_PERIOD_SECONDS, SUBSCRIPTION_REGISTRY_SEED, USER_SUBSCRIPTIONS_SEED,
+};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{BillingBucket, Merchant, SubscriptionRegistry, UserSubscriptions};
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct UnsubscribeServiceArgs {
@@ -27,6 +30,18 @@ pub struct UnsubscribeService<'info> {
         bump = user_subscriptions.bump,
     )]
     pub user_subscriptions: Account<'info, UserSubscriptions>,
+    #[account(
+        seeds = [SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, SubscriptionRegistry>,
+    /// CHECK: The service creator's merchant PDA, registered or not; the
+    /// handler checks the address and loads it when it exists.
+    #[account(mut)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub billing_bucket: Option<Account<'info, BillingBucket>>,
+    pub system_program: Program<'info, System>,
 }
 
 pub fn handler(ctx: Context<UnsubscribeService>, args: UnsubscribeServiceArgs) -> Result<()> {
@@ -44,6 +59,53 @@ pub fn handler(ctx: Context<UnsubscribeService>, args: UnsubscribeServiceArgs) -
         .accounts
         .user_subscriptions
         .begin_cancellation(args.subscription_id, now, BILLING_PERIOD_SECONDS)?;
+    // Older layouts are upgraded on write, which may need more space.
+    ctx.accounts.user_subscriptions.reserve_tail(
+        &ctx.accounts.user_subscriptions.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let (started_at, next_billing_ts) = ctx
+        .accounts
+        .user_subscriptions
+        .subscriptions
+        .iter()
+        .find(|subscription| subscription.id == args.subscription_id)
+        .map(|subscription| (subscription.started_at, subscription.next_billing_ts))
+        .ok_or(ErrorCode::SubscriptionNotFound)?;
+
+    // Only subscriptions queued before buckets were chunked may omit the
+    // bucket; those were all queued in chunk 0.
+    let slot = ctx
+        .accounts
+        .user_subscriptions
+        .billing_slot(args.subscription_id);
+    match (slot, ctx.accounts.billing_bucket.as_mut()) {
+        (Some(_), None) => return err!(ErrorCode::BillingBucketMissing),
+        (slot, Some(bucket)) => {
+            let day = BillingBucket::day_for(next_billing_ts);
+            bucket.ensure_for_day(bucket.key(), day, slot.unwrap_or_default())?;
+            bucket.remove(user_key, args.subscription_id);
+        }
+        (None, None) => {}
+    }
+    ctx.accounts
+        .user_subscriptions
+        .clear_billing_slot(args.subscription_id);
+
+    let service = ctx
+        .accounts
+        .subscription_registry
+        .services
+        .iter()
+        .find(|service| service.id == service_id)
+        .ok_or(ErrorCode::SubscriptionServiceNotFound)?;
+    let merchant_info = ctx.accounts.merchant.to_account_info();
+    if let Some(mut merchant) = Merchant::load_for_creator(&merchant_info, service.creator)? {
+        merchant.record_subscription_cancelled(started_at, monthly_price_usdc)?;
+        merchant.store(&merchant_info)?;
+    }
 
     emit!(SubscriptionCancellationRequested {
         user: user_key,
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+
+use crate::subly::constants::{
+    CONFIG_SEED, GROUP_BUDGET_SEED, TRANCHE_PLEDGE_SEED, USER_POSITION_SEED,
+};
+use crate::subly::error::ErrorCode;
+use crate::subly::instructions::subscribe_service::compute_monthly_budget;
+use crate::subly::state::{GroupBudget, SublyConfig, TranchePledge, UserStake};
+
+#[event]
+pub struct GroupContributionWithdrawn {
+    pub group: Pubkey,
+    pub member: Pubkey,
+    pub tranche_id: u64,
+    pub principal: u64,
+}
+
+/// Returns a pledged tranche to its owner. Pass every member's
+/// `UserSubscriptions` PDA as remaining accounts so the pool can be checked
+/// to still cover what its members committed.
+#[derive(Accounts)]
+#[instruction(tranche_id: u64)]
+pub struct WithdrawGroupContribution<'info> {
+    #[account(
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            GROUP_BUDGET_SEED.as_bytes(),
+            group_budget.admin.as_ref(),
+            &group_budget.group_id.to_le_bytes(),
+        ],
+        bump = group_budget.bump,
+    )]
+    pub group_budget: Account<'info, GroupBudget>,
+    #[account(
+        mut,
+        seeds = [USER_POSITION_SEED.as_bytes(), member.key().as_ref()],
+        bump = user_position.bump,
+        constraint = user_position.owner == member.key() @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub user_position: Account<'info, UserStake>,
+    #[account(
+        mut,
+        close = member,
+        seeds = [
+            TRANCHE_PLEDGE_SEED.as_bytes(),
+            member.key().as_ref(),
+            &tranche_id.to_le_bytes(),
+        ],
+        bump = tranche_pledge.bump,
+        constraint = tranche_pledge.group == group_budget.key() @ ErrorCode::InvalidGroupBudget,
+    )]
+    pub tranche_pledge: Account<'info, TranchePledge>,
+}
+
+pub fn handler(ctx: Context<WithdrawGroupContribution>, tranche_id: u64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let member_key = ctx.accounts.member.key();
+    let group_key = ctx.accounts.group_budget.key();
+
+    let group_budget = &mut ctx.accounts.group_budget;
+    let principal = group_budget.remove_contribution(member_key, tranche_id)?;
+
+    let monthly_budget = compute_monthly_budget(
+        group_budget.contributed_principal()?,
+        ctx.accounts.config.net_apy_bps(),
+    )?;
+    require!(
+        group_budget.total_committed(group_key, ctx.remaining_accounts, now)? <= monthly_budget,
+        ErrorCode::SubscriptionBudgetExceeded
+    );
+
+    let user_position = &mut ctx.accounts.user_position;
+    user_position.total_principal = user_position
+        .total_principal
+        .checked_add(principal)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(GroupContributionWithdrawn {
+        group: group_key,
+        member: member_key,
+        tranche_id,
+        principal,
+    });
+
+    Ok(())
+}
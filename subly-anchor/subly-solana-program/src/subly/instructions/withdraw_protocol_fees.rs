@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::subly::constants::{CONFIG_SEED, VAULT_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::SublyConfig;
+
+#[event]
+pub struct ProtocolFeesWithdrawn {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub remaining_protocol_fees: u64,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFees<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, SublyConfig>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = authority_token_account.owner == authority.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = authority_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<WithdrawProtocolFees>, amount: u64) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        config.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+
+    let withdrawn = if amount == 0 {
+        config.protocol_fees
+    } else {
+        amount.min(config.protocol_fees)
+    };
+    require!(withdrawn > 0, ErrorCode::NothingToClaim);
+
+    config.decrease_reward_pool(withdrawn)?;
+    config.protocol_fees -= withdrawn;
+    let remaining_protocol_fees = config.protocol_fees;
+
+    let config_seed = CONFIG_SEED.as_bytes();
+    let bump = [config.bump];
+    let signer_seeds: &[&[u8]] = &[config_seed, &bump];
+    let signer_seeds = &[signer_seeds];
+
+    let transfer_accounts = Transfer {
+        from: ctx.accounts.vault.to_account_info(),
+        to: ctx.accounts.authority_token_account.to_account_info(),
+        authority: ctx.accounts.config.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        transfer_accounts,
+        signer_seeds,
+    );
+    token::transfer(cpi_ctx, withdrawn)?;
+
+    emit!(ProtocolFeesWithdrawn {
+        authority: ctx.accounts.authority.key(),
+        amount: withdrawn,
+        remaining_protocol_fees,
+    });
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 pub mod constants;
 pub mod error;
 pub mod instructions;
+pub mod pagination;
 pub mod state;
 
 pub use constants::*;
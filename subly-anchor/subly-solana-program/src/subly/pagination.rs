@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::MAX_RETURN_DATA;
+
+use crate::subly::constants::MAX_VIEW_PAGE_SIZE;
+use crate::subly::error::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct PageArgs {
+    pub offset: u32,
+    pub limit: u16,
+}
+
+/// A remaining account a view could not read, reported instead of failing
+/// the whole call.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SkippedAccount {
+    pub account: Pubkey,
+    pub subscription_id: Option<u64>,
+    pub reason: String,
+}
+
+impl SkippedAccount {
+    pub fn new(account: Pubkey, subscription_id: Option<u64>, reason: &str) -> Self {
+        Self {
+            account,
+            subscription_id,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u32,
+    pub next_offset: Option<u32>,
+}
+
+impl<T: AnchorSerialize> Page<T> {
+    /// Bytes taken by the `items` length prefix, `total` and `next_offset`.
+    const OVERHEAD: usize = 4 + 4 + 1 + 4;
+
+    /// Slices `items` starting at `args.offset`, stopping at the requested limit
+    /// or once the page would no longer fit in the return data buffer alongside
+    /// `reserved` bytes of other fields.
+    pub fn from_items(items: Vec<T>, args: PageArgs, reserved: usize) -> Result<Self> {
+        let total = items.len();
+        let limit = match args.limit as usize {
+            0 => MAX_VIEW_PAGE_SIZE,
+            limit => limit.min(MAX_VIEW_PAGE_SIZE),
+        };
+        let start = (args.offset as usize).min(total);
+
+        let mut used = reserved + Self::OVERHEAD;
+        let mut page: Vec<T> = Vec::new();
+        for item in items.into_iter().skip(start) {
+            if page.len() >= limit {
+                break;
+            }
+            let item_len = borsh::to_vec(&item)?.len();
+            if used + item_len > MAX_RETURN_DATA {
+                break;
+            }
+            used += item_len;
+            page.push(item);
+        }
+
+        let end = start + page.len();
+        require!(end > start || end == total, ErrorCode::ViewPageTooLarge);
+
+        Ok(Self {
+            items: page,
+            total: total as u32,
+            next_offset: if end < total { Some(end as u32) } else { None },
+        })
+    }
+}
@@ -1,8 +1,14 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_lang::AccountDeserialize;
 
 use crate::subly::constants::{
-    BASIS_POINTS_DIVISOR, INDEX_SCALE, MAX_SERVICE_DETAILS_LEN, MAX_SERVICE_LOGO_URL_LEN,
-    MAX_SERVICE_NAME_LEN, MAX_SERVICE_PROVIDER_LEN, SECONDS_PER_YEAR,
+    BASIS_POINTS_DIVISOR, BENEFICIARY_GIFTS_SEED, BILLING_BUCKET_SEED, GROUP_BUDGET_SEED,
+    INDEX_SCALE, MAX_BILLING_BUCKET_ENTRIES, MAX_ENCRYPTED_RECEIVER_LEN, MAX_FEE_CHECKPOINTS,
+    MAX_GROUP_MEMBERS, MAX_MERCHANT_NAME_LEN, MAX_PAYOUT_RECEIVER_LEN, MAX_SERVICE_DETAILS_LEN,
+    MAX_SERVICE_LOGO_URL_LEN, MAX_SERVICE_NAME_LEN, MAX_SERVICE_PROVIDER_LEN, MERCHANT_SEED,
+    PAYOUT_LAYOUT_VERSION, SECONDS_PER_DAY, SECONDS_PER_YEAR, USER_SUBSCRIPTIONS_SEED,
 };
 use crate::subly::error::ErrorCode;
 
@@ -19,6 +25,36 @@ pub struct SublyConfig {
     pub paused: bool,
     pub bump: u8,
     pub vault_bump: u8,
+    pub protocol_fee_bps: u16,
+    pub protocol_fees: u64,
+    pub receiver_encryption_key: [u8; 32],
+    /// Earlier protocol fee rates, oldest first, so positions that sync
+    /// late are charged the rate that was in force while they accrued.
+    pub fee_checkpoints: [FeeCheckpoint; MAX_FEE_CHECKPOINTS],
+    pub fee_checkpoint_count: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeCheckpoint {
+    /// `acc_index` when the rate changed.
+    pub acc_index: u128,
+    /// Rate in force up to `acc_index`.
+    pub fee_bps: u16,
+}
+
+impl FeeCheckpoint {
+    pub const SIZE: usize = 16 // acc_index
+        + 2; // fee_bps
+}
+
+#[event]
+pub struct ProtocolFeesAccrued {
+    pub owner: Pubkey,
+    /// Rate `amount` came to over the synced yield. Differs from the current
+    /// rate when that changed since the position last synced.
+    pub fee_bps: u16,
+    pub amount: u64,
+    pub total_protocol_fees: u64,
 }
 
 impl SublyConfig {
@@ -33,13 +69,30 @@ impl SublyConfig {
         + 8  // last_update_ts
         + 1  // paused
         + 1  // bump
-        + 1; // vault_bump
+        + 1  // vault_bump
+        + 2  // protocol_fee_bps
+        + 8  // protocol_fees
+        + 32 // receiver_encryption_key
+        + FeeCheckpoint::SIZE * MAX_FEE_CHECKPOINTS // fee_checkpoints
+        + 1; // fee_checkpoint_count
 
     pub fn ensure_active(&self) -> Result<()> {
         require!(!self.paused, ErrorCode::ProgramPaused);
         Ok(())
     }
 
+    pub fn ensure_receiver_key(&self, operator_key: [u8; 32]) -> Result<()> {
+        require!(
+            self.receiver_encryption_key != [0u8; 32],
+            ErrorCode::ReceiverEncryptionKeyNotSet
+        );
+        require!(
+            operator_key == self.receiver_encryption_key,
+            ErrorCode::ReceiverEncryptionKeyMismatch
+        );
+        Ok(())
+    }
+
     pub fn accrue_to(&mut self, now: i64) -> Result<()> {
         if now <= self.last_update_ts {
             return Ok(());
@@ -97,6 +150,90 @@ impl SublyConfig {
         self.reward_pool -= amount;
         Ok(())
     }
+
+    /// `accrued` is the yield left to the position after `amount` was taken.
+    pub fn record_protocol_fees(&mut self, owner: Pubkey, accrued: u64, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        self.protocol_fees = self
+            .protocol_fees
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // The fee may span several rates, so report the one it came to.
+        let gross = accrued as u128 + amount as u128;
+        let fee_bps = (amount as u128 * BASIS_POINTS_DIVISOR as u128 + gross / 2) / gross;
+        emit!(ProtocolFeesAccrued {
+            owner,
+            fee_bps: fee_bps as u16,
+            amount,
+            total_protocol_fees: self.protocol_fees,
+        });
+        Ok(())
+    }
+
+    /// Remembers the current rate up to the current `acc_index`, before the
+    /// rate changes. Call `accrue_to` first.
+    pub fn checkpoint_protocol_fee(&mut self) {
+        let count = (self.fee_checkpoint_count as usize).min(MAX_FEE_CHECKPOINTS);
+        if count > 0 && self.fee_checkpoints[count - 1].acc_index == self.acc_index {
+            // Nothing accrued at the current rate; it never applied.
+            return;
+        }
+
+        let checkpoint = FeeCheckpoint {
+            acc_index: self.acc_index,
+            fee_bps: self.protocol_fee_bps,
+        };
+        if count < MAX_FEE_CHECKPOINTS {
+            self.fee_checkpoints[count] = checkpoint;
+            self.fee_checkpoint_count = (count + 1) as u8;
+            return;
+        }
+
+        // Merge the two oldest stretches at the lower rate, so a late sync is
+        // never charged more than the rate that was in force.
+        let merged_fee_bps = self.fee_checkpoints[0]
+            .fee_bps
+            .min(self.fee_checkpoints[1].fee_bps);
+        self.fee_checkpoints.rotate_left(1);
+        self.fee_checkpoints[0].fee_bps = merged_fee_bps;
+        self.fee_checkpoints[MAX_FEE_CHECKPOINTS - 1] = checkpoint;
+    }
+
+    /// Index growth over `(from_index, to_index]` weighted by the fee rate in
+    /// force over each stretch, in index units times basis points.
+    pub fn weighted_fee(&self, from_index: u128, to_index: u128) -> Result<u128> {
+        let count = (self.fee_checkpoint_count as usize).min(MAX_FEE_CHECKPOINTS);
+        let mut weighted: u128 = 0;
+        let mut cursor = from_index;
+        for checkpoint in self.fee_checkpoints[..count].iter() {
+            if checkpoint.acc_index <= cursor {
+                continue;
+            }
+            let end = checkpoint.acc_index.min(to_index);
+            weighted = (end - cursor)
+                .checked_mul(checkpoint.fee_bps as u128)
+                .and_then(|fee| weighted.checked_add(fee))
+                .ok_or(ErrorCode::MathOverflow)?;
+            cursor = end;
+        }
+        if to_index > cursor {
+            weighted = (to_index - cursor)
+                .checked_mul(self.protocol_fee_bps as u128)
+                .and_then(|fee| weighted.checked_add(fee))
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(weighted)
+    }
+
+    /// APY left to stakers once the protocol fee is taken out of accrued yield.
+    pub fn net_apy_bps(&self) -> u16 {
+        let net = (self.apy_bps as u64) * (BASIS_POINTS_DIVISOR - self.protocol_fee_bps as u64)
+            / BASIS_POINTS_DIVISOR;
+        net as u16
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
@@ -150,9 +287,10 @@ impl StakeEntry {
         })
     }
 
-    pub fn sync_to_index(&mut self, acc_index: u128) -> Result<u64> {
+    pub fn sync_to_index(&mut self, config: &SublyConfig) -> Result<(u64, u64)> {
+        let acc_index = config.acc_index;
         if acc_index <= self.last_acc_index || self.principal == 0 {
-            return Ok(0);
+            return Ok((0, 0));
         }
 
         let delta_index = acc_index
@@ -164,14 +302,26 @@ impl StakeEntry {
             .checked_div(INDEX_SCALE)
             .ok_or(ErrorCode::MathOverflow)?;
         let accrual_u64: u64 = accrual.try_into().map_err(|_| ErrorCode::MathOverflow)?;
+        // Each stretch is charged the rate in force while it accrued, so a
+        // rate change never reaches back into yield accrued before it.
+        let protocol_fee: u64 = (self.principal as u128)
+            .checked_mul(config.weighted_fee(self.last_acc_index, acc_index)?)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(INDEX_SCALE * BASIS_POINTS_DIVISOR as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let net_accrual = accrual_u64
+            .checked_sub(protocol_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         self.unrealized_yield = self
             .unrealized_yield
-            .checked_add(accrual_u64)
+            .checked_add(net_accrual)
             .ok_or(ErrorCode::MathOverflow)?;
         self.last_acc_index = acc_index;
 
-        Ok(accrual_u64)
+        Ok((net_accrual, protocol_fee))
     }
 
     pub fn available_for_operator(&self) -> u64 {
@@ -355,6 +505,590 @@ impl SubscriptionRegistry {
     }
 }
 
+#[account]
+pub struct Merchant {
+    pub owner: Pubkey,
+    pub payout_destination: Pubkey,
+    pub display_name: String,
+    pub active_subscribers: u64,
+    pub lifetime_revenue_usdc: u64,
+    pub pending_revenue_usdc: u64,
+    pub bump: u8,
+    pub registered_at: i64,
+}
+
+impl Merchant {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // owner
+        + 32 // payout_destination
+        + 4 + MAX_MERCHANT_NAME_LEN // display_name
+        + 8  // active_subscribers
+        + 8  // lifetime_revenue_usdc
+        + 8  // pending_revenue_usdc
+        + 1  // bump
+        + 8; // registered_at
+
+    /// Loads the merchant of `creator`. `merchant_info` has to be the
+    /// creator's merchant PDA even when they never registered, so a caller
+    /// cannot leave a registered merchant's counters behind.
+    pub fn load_for_creator(merchant_info: &AccountInfo, creator: Pubkey) -> Result<Option<Self>> {
+        let (expected, _) =
+            Pubkey::find_program_address(&[MERCHANT_SEED.as_bytes(), creator.as_ref()], &crate::ID);
+        require_keys_eq!(
+            expected,
+            merchant_info.key(),
+            ErrorCode::InvalidMerchantAccount
+        );
+        if merchant_info.data_is_empty() {
+            return Ok(None);
+        }
+        require_keys_eq!(
+            *merchant_info.owner,
+            crate::ID,
+            ErrorCode::InvalidMerchantAccount
+        );
+
+        let data = merchant_info.try_borrow_data()?;
+        let mut data_slice: &[u8] = &data;
+        let merchant = Self::try_deserialize(&mut data_slice)?;
+        require_keys_eq!(merchant.owner, creator, ErrorCode::InvalidMerchantAccount);
+        Ok(Some(merchant))
+    }
+
+    pub fn store(&self, merchant_info: &AccountInfo) -> Result<()> {
+        let mut data = merchant_info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        self.try_serialize(&mut writer)
+    }
+
+    /// The counters cover subscriptions started from the second the
+    /// merchant registered onwards.
+    pub fn tracks(&self, started_at: i64) -> bool {
+        started_at >= self.registered_at
+    }
+
+    /// Pending revenue tracks what the next billing cycle of every active
+    /// subscriber is expected to pay out.
+    pub fn record_subscription_started(
+        &mut self,
+        started_at: i64,
+        monthly_price: u64,
+    ) -> Result<()> {
+        if !self.tracks(started_at) {
+            return Ok(());
+        }
+        self.active_subscribers = self
+            .active_subscribers
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.pending_revenue_usdc = self
+            .pending_revenue_usdc
+            .checked_add(monthly_price)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_subscription_cancelled(
+        &mut self,
+        started_at: i64,
+        monthly_price: u64,
+    ) -> Result<()> {
+        if !self.tracks(started_at) {
+            return Ok(());
+        }
+        if started_at == self.registered_at {
+            // A subscription from earlier in the registration second was
+            // never counted; it must not keep its owner from cancelling.
+            self.active_subscribers = self.active_subscribers.saturating_sub(1);
+            self.pending_revenue_usdc = self.pending_revenue_usdc.saturating_sub(monthly_price);
+            return Ok(());
+        }
+        self.active_subscribers = self
+            .active_subscribers
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.pending_revenue_usdc = self
+            .pending_revenue_usdc
+            .checked_sub(monthly_price)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_payment(&mut self, amount: u64) -> Result<()> {
+        self.lifetime_revenue_usdc = self
+            .lifetime_revenue_usdc
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BillingBucketEntry {
+    pub user: Pubkey,
+    pub subscription_id: u64,
+}
+
+impl BillingBucketEntry {
+    pub const SIZE: usize = 32 // user
+        + 8; // subscription_id
+}
+
+#[account]
+pub struct BillingBucket {
+    pub day: i64,
+    pub bump: u8,
+    pub entries: Vec<BillingBucketEntry>,
+}
+
+impl BillingBucket {
+    pub const INITIAL_ENTRY_CAPACITY: usize = 8;
+    pub const BASE_SIZE: usize = 8 // discriminator
+        + 8  // day
+        + 1  // bump
+        + 4; // entries length prefix
+
+    pub const INITIAL_SIZE: usize =
+        Self::BASE_SIZE + Self::INITIAL_ENTRY_CAPACITY * BillingBucketEntry::SIZE;
+
+    pub fn required_size(entry_count: usize) -> usize {
+        Self::BASE_SIZE + entry_count * BillingBucketEntry::SIZE
+    }
+
+    pub fn day_for(ts: i64) -> i64 {
+        ts.div_euclid(SECONDS_PER_DAY)
+    }
+
+    /// Seed for a day's `chunk`. Chunk 0 adds nothing, so it keeps the
+    /// address buckets had before they were chunked.
+    pub fn chunk_seed(chunk: u16) -> Vec<u8> {
+        if chunk == 0 {
+            Vec::new()
+        } else {
+            chunk.to_le_bytes().to_vec()
+        }
+    }
+
+    pub fn ensure_for_day(&self, bucket_key: Pubkey, day: i64, chunk: u16) -> Result<()> {
+        require!(self.day == day, ErrorCode::InvalidBillingBucket);
+        let expected = Pubkey::create_program_address(
+            &[
+                BILLING_BUCKET_SEED.as_bytes(),
+                &day.to_le_bytes(),
+                &Self::chunk_seed(chunk),
+                &[self.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::InvalidBillingBucket)?;
+        require_keys_eq!(expected, bucket_key, ErrorCode::InvalidBillingBucket);
+        Ok(())
+    }
+
+    pub fn contains(&self, user: Pubkey, subscription_id: u64) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.user == user && entry.subscription_id == subscription_id)
+    }
+
+    /// Grows the bucket so one more entry fits, topping up rent from `payer`.
+    /// Fails once the chunk holds `MAX_BILLING_BUCKET_ENTRIES`.
+    pub fn reserve_entry<'info>(
+        &self,
+        bucket_info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<()> {
+        require!(
+            self.entries.len() < MAX_BILLING_BUCKET_ENTRIES,
+            ErrorCode::BillingBucketFull
+        );
+        let required_space = Self::required_size(self.entries.len() + 1);
+        if bucket_info.data_len() >= required_space {
+            return Ok(());
+        }
+
+        let required_lamports = Rent::get()?.minimum_balance(required_space);
+        let current_lamports = bucket_info.lamports();
+        if required_lamports > current_lamports {
+            let transfer_accounts = SystemTransfer {
+                from: payer.clone(),
+                to: bucket_info.clone(),
+            };
+            system_program::transfer(
+                CpiContext::new(system_program.clone(), transfer_accounts),
+                required_lamports - current_lamports,
+            )?;
+        }
+        bucket_info.resize(required_space)?;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, user: Pubkey, subscription_id: u64) {
+        if !self.contains(user, subscription_id) {
+            self.entries.push(BillingBucketEntry {
+                user,
+                subscription_id,
+            });
+        }
+    }
+
+    /// Subscriptions created before the billing queue existed are not indexed,
+    /// so removing a missing entry is not an error.
+    pub fn remove(&mut self, user: Pubkey, subscription_id: u64) {
+        self.entries
+            .retain(|entry| entry.user != user || entry.subscription_id != subscription_id);
+    }
+
+    /// Points an entry at a new owner in place, so the bucket never grows.
+    pub fn replace_user(&mut self, user: Pubkey, new_user: Pubkey, subscription_id: u64) -> bool {
+        match self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.user == user && entry.subscription_id == subscription_id)
+        {
+            Some(entry) => {
+                entry.user = new_user;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Sponsors that have gifted subscriptions to `beneficiary`, so the
+/// beneficiary can find them without scanning every `UserSubscriptions`.
+/// Sponsors are never removed; views filter out cancelled gifts.
+#[account]
+pub struct BeneficiaryGifts {
+    pub beneficiary: Pubkey,
+    pub bump: u8,
+    pub sponsors: Vec<Pubkey>,
+}
+
+impl BeneficiaryGifts {
+    pub const INITIAL_SPONSOR_CAPACITY: usize = 4;
+    pub const BASE_SIZE: usize = 8 // discriminator
+        + 32 // beneficiary
+        + 1  // bump
+        + 4; // sponsors length prefix
+
+    pub const INITIAL_SIZE: usize = Self::BASE_SIZE + Self::INITIAL_SPONSOR_CAPACITY * 32;
+
+    pub fn required_size(sponsor_count: usize) -> usize {
+        Self::BASE_SIZE + sponsor_count * 32
+    }
+
+    pub fn ensure_for_beneficiary(&self, gifts_key: Pubkey, beneficiary: Pubkey) -> Result<()> {
+        require_keys_eq!(
+            self.beneficiary,
+            beneficiary,
+            ErrorCode::InvalidBeneficiaryGifts
+        );
+        let expected = Pubkey::create_program_address(
+            &[
+                BENEFICIARY_GIFTS_SEED.as_bytes(),
+                beneficiary.as_ref(),
+                &[self.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::InvalidBeneficiaryGifts)?;
+        require_keys_eq!(expected, gifts_key, ErrorCode::InvalidBeneficiaryGifts);
+        Ok(())
+    }
+
+    pub fn contains(&self, sponsor: Pubkey) -> bool {
+        self.sponsors.contains(&sponsor)
+    }
+
+    pub fn replace_sponsor(&mut self, sponsor: Pubkey, new_sponsor: Pubkey) -> bool {
+        let Some(index) = self.sponsors.iter().position(|entry| *entry == sponsor) else {
+            return false;
+        };
+        if self.contains(new_sponsor) {
+            self.sponsors.remove(index);
+        } else {
+            self.sponsors[index] = new_sponsor;
+        }
+        true
+    }
+
+    /// Adds `sponsor` unless already listed, topping up rent from `payer`
+    /// when the account has to grow.
+    pub fn add_sponsor<'info>(
+        &mut self,
+        gifts_info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+        sponsor: Pubkey,
+    ) -> Result<()> {
+        if self.contains(sponsor) {
+            return Ok(());
+        }
+
+        let required_space = Self::required_size(self.sponsors.len() + 1);
+        if gifts_info.data_len() < required_space {
+            let required_lamports = Rent::get()?.minimum_balance(required_space);
+            let current_lamports = gifts_info.lamports();
+            if required_lamports > current_lamports {
+                let transfer_accounts = SystemTransfer {
+                    from: payer.clone(),
+                    to: gifts_info.clone(),
+                };
+                system_program::transfer(
+                    CpiContext::new(system_program.clone(), transfer_accounts),
+                    required_lamports - current_lamports,
+                )?;
+            }
+            gifts_info.resize(required_space)?;
+        }
+
+        self.sponsors.push(sponsor);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupMember {
+    pub member: Pubkey,
+    /// Monthly USDC the member may commit from the pool; zero means no limit
+    /// beyond the pool itself.
+    pub spending_limit_usdc: u64,
+}
+
+impl GroupMember {
+    pub const SIZE: usize = 32 // member
+        + 8; // spending_limit_usdc
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupContribution {
+    pub member: Pubkey,
+    pub tranche_id: u64,
+    pub principal: u64,
+}
+
+impl GroupContribution {
+    pub const SIZE: usize = 32 // member
+        + 8  // tranche_id
+        + 8; // principal
+}
+
+/// A pool of stake tranches whose combined yield funds the subscriptions of
+/// its members. Tranches stay in their owner's `UserStake` and keep accruing
+/// there; a `TranchePledge` keeps them out of the owner's own budget.
+#[account]
+pub struct GroupBudget {
+    pub admin: Pubkey,
+    pub group_id: u64,
+    pub bump: u8,
+    pub members: Vec<GroupMember>,
+    pub contributions: Vec<GroupContribution>,
+}
+
+impl GroupBudget {
+    pub const INITIAL_MEMBER_CAPACITY: usize = 4;
+    pub const BASE_SIZE: usize = 8 // discriminator
+        + 32 // admin
+        + 8  // group_id
+        + 1  // bump
+        + 4  // members length prefix
+        + 4; // contributions length prefix
+
+    pub const INITIAL_SIZE: usize = Self::BASE_SIZE
+        + Self::INITIAL_MEMBER_CAPACITY * (GroupMember::SIZE + GroupContribution::SIZE);
+
+    pub fn required_size(member_count: usize, contribution_count: usize) -> usize {
+        Self::BASE_SIZE
+            + member_count * GroupMember::SIZE
+            + contribution_count * GroupContribution::SIZE
+    }
+
+    pub fn ensure_key(&self, group_key: Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[
+                GROUP_BUDGET_SEED.as_bytes(),
+                self.admin.as_ref(),
+                &self.group_id.to_le_bytes(),
+                &[self.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::InvalidGroupBudget)?;
+        require_keys_eq!(expected, group_key, ErrorCode::InvalidGroupBudget);
+        Ok(())
+    }
+
+    pub fn member(&self, member: Pubkey) -> Result<&GroupMember> {
+        self.members
+            .iter()
+            .find(|entry| entry.member == member)
+            .ok_or(ErrorCode::GroupMemberNotFound.into())
+    }
+
+    pub fn contributed_principal(&self) -> Result<u64> {
+        self.contributions
+            .iter()
+            .try_fold(0u64, |acc, contribution| {
+                acc.checked_add(contribution.principal)
+                    .ok_or_else(|| ErrorCode::MathOverflow.into())
+            })
+    }
+
+    pub fn set_member(&mut self, member: Pubkey, spending_limit_usdc: u64) -> Result<()> {
+        if let Some(entry) = self.members.iter_mut().find(|entry| entry.member == member) {
+            entry.spending_limit_usdc = spending_limit_usdc;
+            return Ok(());
+        }
+        require!(
+            self.members.len() < MAX_GROUP_MEMBERS,
+            ErrorCode::GroupMemberLimitReached
+        );
+        self.members.push(GroupMember {
+            member,
+            spending_limit_usdc,
+        });
+        Ok(())
+    }
+
+    pub fn remove_member(&mut self, member: Pubkey) -> Result<()> {
+        self.member(member)?;
+        require!(
+            !self
+                .contributions
+                .iter()
+                .any(|contribution| contribution.member == member),
+            ErrorCode::GroupMemberInUse
+        );
+        self.members.retain(|entry| entry.member != member);
+        Ok(())
+    }
+
+    pub fn add_contribution(&mut self, member: Pubkey, tranche_id: u64, principal: u64) {
+        self.contributions.push(GroupContribution {
+            member,
+            tranche_id,
+            principal,
+        });
+    }
+
+    pub fn remove_contribution(&mut self, member: Pubkey, tranche_id: u64) -> Result<u64> {
+        let index = self
+            .contributions
+            .iter()
+            .position(|contribution| {
+                contribution.member == member && contribution.tranche_id == tranche_id
+            })
+            .ok_or(ErrorCode::InvalidTranche)?;
+        Ok(self.contributions.remove(index).principal)
+    }
+
+    /// Grows the account to fit the members and contributions held in memory,
+    /// topping up rent from `payer`.
+    pub fn reserve<'info>(
+        &self,
+        group_info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let required_space = Self::required_size(self.members.len(), self.contributions.len());
+        if group_info.data_len() >= required_space {
+            return Ok(());
+        }
+
+        let required_lamports = Rent::get()?.minimum_balance(required_space);
+        let current_lamports = group_info.lamports();
+        if required_lamports > current_lamports {
+            let transfer_accounts = SystemTransfer {
+                from: payer.clone(),
+                to: group_info.clone(),
+            };
+            system_program::transfer(
+                CpiContext::new(system_program.clone(), transfer_accounts),
+                required_lamports - current_lamports,
+            )?;
+        }
+        group_info.resize(required_space)?;
+        Ok(())
+    }
+
+    /// Sums what every member has committed against the pool. `member_accounts`
+    /// must hold each member's `UserSubscriptions` PDA exactly once; members
+    /// who never subscribed pass the uninitialised address. An account
+    /// `init_if_needed` created earlier in the same transaction has no
+    /// discriminator yet and holds no commitments either.
+    pub fn total_committed(
+        &self,
+        group_key: Pubkey,
+        member_accounts: &[AccountInfo],
+        now: i64,
+    ) -> Result<u64> {
+        require!(
+            member_accounts.len() == self.members.len(),
+            ErrorCode::GroupMembersMissing
+        );
+
+        let mut committed: u64 = 0;
+        for member in self.members.iter() {
+            let (expected_pda, _) = Pubkey::find_program_address(
+                &[USER_SUBSCRIPTIONS_SEED.as_bytes(), member.member.as_ref()],
+                &crate::ID,
+            );
+            let account_info = member_accounts
+                .iter()
+                .find(|account_info| *account_info.key == expected_pda)
+                .ok_or(ErrorCode::GroupMembersMissing)?;
+            if account_info.data_is_empty() {
+                continue;
+            }
+            require_keys_eq!(
+                *account_info.owner,
+                crate::ID,
+                ErrorCode::InvalidSubscriptionAccount
+            );
+
+            let data_ref = account_info.try_borrow_data()?;
+            let discriminator_len = UserSubscriptions::DISCRIMINATOR.len();
+            if data_ref.len() >= discriminator_len
+                && data_ref[..discriminator_len].iter().all(|byte| *byte == 0)
+            {
+                continue;
+            }
+            let mut data_slice: &[u8] = &data_ref;
+            let mut member_subscriptions = UserSubscriptions::try_deserialize(&mut data_slice)?;
+            drop(data_ref);
+            // Refresh the copy so lapsed cancellations stop counting.
+            member_subscriptions.refresh(now)?;
+
+            committed = committed
+                .checked_add(member_subscriptions.committed_to_group(Some(group_key))?)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(committed)
+    }
+}
+
+/// Marks a tranche as pledged to `group`. Its existence blocks `unstake` for
+/// the tranche until the contribution is withdrawn.
+#[account]
+pub struct TranchePledge {
+    pub owner: Pubkey,
+    pub tranche_id: u64,
+    pub group: Pubkey,
+    pub principal: u64,
+    pub bump: u8,
+}
+
+impl TranchePledge {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // owner
+        + 8  // tranche_id
+        + 32 // group
+        + 8  // principal
+        + 1; // bump
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SubscriptionStatus {
     Active,
@@ -368,6 +1102,16 @@ impl Default for SubscriptionStatus {
     }
 }
 
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "ACTIVE",
+            Self::PendingCancellation => "PENDING_CANCELLATION",
+            Self::Cancelled => "CANCELLED",
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PayPalRecipientType {
     Email,
@@ -395,6 +1139,287 @@ impl PayPalRecipientType {
             Self::UserHandle => "USER_HANDLE",
         }
     }
+
+    pub fn validate_receiver(&self, receiver: &str) -> Result<()> {
+        match self {
+            Self::Email => require!(is_valid_email(receiver), ErrorCode::InvalidPayPalEmail),
+            Self::Phone => require!(is_valid_e164(receiver), ErrorCode::InvalidPayPalPhone),
+            Self::PayPalId => require!(
+                receiver.bytes().all(|b| b.is_ascii_alphanumeric()),
+                ErrorCode::InvalidPayPalId
+            ),
+            Self::UserHandle => {
+                let handle = receiver.strip_prefix('@').unwrap_or(receiver);
+                require!(
+                    !handle.is_empty()
+                        && handle
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.')),
+                    ErrorCode::InvalidPayPalUserHandle
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_valid_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    if local.is_empty()
+        || local.len() > 64
+        || local.starts_with('.')
+        || local.ends_with('.')
+        || local.contains("..")
+        || !local
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+/=?^_`{|}~.-".contains(&b))
+    {
+        return false;
+    }
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.len() < 2 {
+        return false;
+    }
+    let labels_valid = labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    });
+    let tld = labels[labels.len() - 1];
+    labels_valid && tld.len() >= 2 && tld.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+/// E.164: a leading `+`, no leading zero and at most 15 digits.
+fn is_valid_e164(value: &str) -> bool {
+    let Some(digits) = value.strip_prefix('+') else {
+        return false;
+    };
+    (2..=15).contains(&digits.len())
+        && !digits.starts_with('0')
+        && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Which provider adapter the keeper must use to pay a recipient out.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayoutRail {
+    PayPal { recipient_type: PayPalRecipientType },
+    Venmo,
+    Iban,
+    Ach,
+    Wallet,
+}
+
+impl PayoutRail {
+    /// Upper bound of the serialized size; only `PayPal` carries a payload.
+    pub const MAX_SIZE: usize = 1 + 1;
+
+    pub fn from_parts(rail: &str, recipient_type: &str) -> Result<Self> {
+        match rail.to_ascii_uppercase().as_str() {
+            "PAYPAL" => Ok(Self::PayPal {
+                recipient_type: PayPalRecipientType::from_str(recipient_type)?,
+            }),
+            "VENMO" => Ok(Self::Venmo),
+            "IBAN" => Ok(Self::Iban),
+            "ACH" => Ok(Self::Ach),
+            "WALLET" => Ok(Self::Wallet),
+            _ => Err(ErrorCode::InvalidPayoutRail.into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PayPal { .. } => "PAYPAL",
+            Self::Venmo => "VENMO",
+            Self::Iban => "IBAN",
+            Self::Ach => "ACH",
+            Self::Wallet => "WALLET",
+        }
+    }
+
+    /// PayPal keeps its receiver sub-type; every other rail has one format.
+    pub fn recipient_type(&self) -> &'static str {
+        match self {
+            Self::PayPal { recipient_type } => recipient_type.as_str(),
+            other => other.as_str(),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PayoutRecipient {
+    PayPal {
+        recipient_type: PayPalRecipientType,
+        receiver: String,
+    },
+    Venmo {
+        handle: String,
+    },
+    Iban {
+        iban: String,
+    },
+    Ach {
+        routing_number: String,
+        account_number: String,
+    },
+    Wallet {
+        address: Pubkey,
+    },
+    /// Any of the plaintext variants sealed to the operator key. Only the rail
+    /// and the hash of the canonical receiver are public.
+    Encrypted {
+        rail: PayoutRail,
+        receiver_hash: [u8; 32],
+        receiver: EncryptedReceiver,
+    },
+}
+
+impl PayoutRecipient {
+    pub fn rail(&self) -> PayoutRail {
+        match self {
+            Self::PayPal { recipient_type, .. } => PayoutRail::PayPal {
+                recipient_type: *recipient_type,
+            },
+            Self::Venmo { .. } => PayoutRail::Venmo,
+            Self::Iban { .. } => PayoutRail::Iban,
+            Self::Ach { .. } => PayoutRail::Ach,
+            Self::Wallet { .. } => PayoutRail::Wallet,
+            Self::Encrypted { rail, .. } => *rail,
+        }
+    }
+
+    pub fn space(&self) -> usize {
+        1 + match self {
+            Self::PayPal { receiver, .. } => 1 + 4 + receiver.len(),
+            Self::Venmo { handle } => 4 + handle.len(),
+            Self::Iban { iban } => 4 + iban.len(),
+            Self::Ach {
+                routing_number,
+                account_number,
+            } => 4 + routing_number.len() + 4 + account_number.len(),
+            Self::Wallet { .. } => 32,
+            Self::Encrypted { receiver, .. } => PayoutRail::MAX_SIZE + 32 + receiver.space(),
+        }
+    }
+
+    /// The string the keeper hands to the provider adapter. Encrypted
+    /// recipients seal exactly this string, so it is also what gets hashed.
+    pub fn canonical_receiver(&self) -> Option<String> {
+        match self {
+            Self::PayPal { receiver, .. } => Some(receiver.clone()),
+            Self::Venmo { handle } => Some(handle.clone()),
+            Self::Iban { iban } => Some(iban.clone()),
+            Self::Ach {
+                routing_number,
+                account_number,
+            } => Some(format!("{routing_number}:{account_number}")),
+            Self::Wallet { address } => Some(address.to_string()),
+            Self::Encrypted { .. } => None,
+        }
+    }
+
+    /// Events only carry this digest, salted with the owner so equal receivers
+    /// cannot be linked across wallets.
+    pub fn receiver_hash(&self, owner: &Pubkey) -> [u8; 32] {
+        match self {
+            Self::Encrypted { receiver_hash, .. } => *receiver_hash,
+            plaintext => {
+                let receiver = plaintext.canonical_receiver().unwrap_or_default();
+                hashv(&[owner.as_ref(), receiver.as_bytes()]).to_bytes()
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::PayPal {
+                recipient_type,
+                receiver,
+            } => {
+                require!(
+                    !receiver.is_empty() && receiver.len() <= MAX_PAYOUT_RECEIVER_LEN,
+                    ErrorCode::InvalidPayoutReceiver
+                );
+                recipient_type.validate_receiver(receiver)?;
+            }
+            Self::Venmo { handle } => {
+                let name = handle.strip_prefix('@').unwrap_or(handle);
+                require!(
+                    (5..=30).contains(&name.len())
+                        && name
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-')),
+                    ErrorCode::InvalidVenmoHandle
+                );
+            }
+            Self::Iban { iban } => require!(is_valid_iban(iban), ErrorCode::InvalidIban),
+            Self::Ach {
+                routing_number,
+                account_number,
+            } => require!(
+                is_valid_aba_routing_number(routing_number)
+                    && (4..=17).contains(&account_number.len())
+                    && account_number.bytes().all(|b| b.is_ascii_digit()),
+                ErrorCode::InvalidAchAccount
+            ),
+            Self::Wallet { address } => require!(
+                *address != Pubkey::default() && address.is_on_curve(),
+                ErrorCode::InvalidPayoutWallet
+            ),
+            Self::Encrypted { receiver, .. } => require!(
+                !receiver.ciphertext.is_empty()
+                    && receiver.ciphertext.len() <= MAX_ENCRYPTED_RECEIVER_LEN,
+                ErrorCode::InvalidEncryptedReceiver
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Compact IBAN (no spaces, upper case) with a valid ISO 13616 mod-97 check.
+fn is_valid_iban(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if !(15..=34).contains(&bytes.len())
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+        || !bytes[4..]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+    {
+        return false;
+    }
+
+    let mut remainder: u32 = 0;
+    for &b in bytes[4..].iter().chain(&bytes[..4]) {
+        let value = if b.is_ascii_digit() {
+            u32::from(b - b'0')
+        } else {
+            u32::from(b - b'A') + 10
+        };
+        let scale = if value >= 10 { 100 } else { 10 };
+        remainder = (remainder * scale + value) % 97;
+    }
+    remainder == 1
+}
+
+/// Nine-digit ABA routing number with its 3-7-1 checksum.
+fn is_valid_aba_routing_number(value: &str) -> bool {
+    if value.len() != 9 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let digits: Vec<u32> = value.bytes().map(|b| u32::from(b - b'0')).collect();
+    let weighted: u32 = digits[..8]
+        .iter()
+        .zip([3, 7, 1].iter().cycle())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    (10 - weighted % 10) % 10 == digits[8]
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
@@ -422,7 +1447,11 @@ impl UserSubscription {
         + 1; // initial_payment_recorded
 }
 
-#[account]
+/// Deserializes both the original PayPal-only layout and the current one,
+/// upgrading the former in memory so handlers always see the current one.
+/// Anything that writes it back calls `reserve_tail` first, since the
+/// original layout may be shorter.
+#[derive(AnchorSerialize, Clone)]
 pub struct UserSubscriptions {
     pub owner: Pubkey,
     pub next_subscription_id: u64,
@@ -430,9 +1459,238 @@ pub struct UserSubscriptions {
     pub total_pending_commitment: u64,
     pub bump: u8,
     pub subscriptions: Vec<UserSubscription>,
-    pub paypal_configured: bool,
-    pub paypal_recipient_type: PayPalRecipientType,
-    pub paypal_receiver: String,
+    pub payout_layout_version: u8,
+    pub payout_recipient: Option<PayoutRecipient>,
+    pub payout_overrides: Vec<PayoutOverride>,
+    pub gifts: Vec<Gift>,
+    pub group_charges: Vec<GroupCharge>,
+    pub spending_caps: SpendingCaps,
+    pub billing_slots: Vec<BillingSlot>,
+}
+
+/// The billing bucket chunk holding a subscription's next renewal. The day
+/// follows from `next_billing_ts`. Subscriptions queued before chunks existed
+/// have no slot.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BillingSlot {
+    pub subscription_id: u64,
+    pub chunk: u16,
+}
+
+impl BillingSlot {
+    pub const SIZE: usize = 8 // subscription_id
+        + 2; // chunk
+}
+
+impl Discriminator for UserSubscriptions {
+    // sha256("account:UserSubscriptions")[..8], as `#[account]` would derive.
+    const DISCRIMINATOR: &'static [u8] = &[28, 64, 101, 41, 241, 150, 8, 81];
+}
+
+impl Owner for UserSubscriptions {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+impl AccountSerialize for UserSubscriptions {
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        if writer.write_all(Self::DISCRIMINATOR).is_err() {
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+        }
+        if AnchorSerialize::serialize(self, writer).is_err() {
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+        }
+        Ok(())
+    }
+}
+
+impl AccountDeserialize for UserSubscriptions {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        if buf.len() < Self::DISCRIMINATOR.len() {
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if &buf[..Self::DISCRIMINATOR.len()] != Self::DISCRIMINATOR {
+            return Err(
+                error!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch)
+                    .with_account_name("UserSubscriptions"),
+            );
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data: &[u8] = &buf[Self::DISCRIMINATOR.len()..];
+        AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl AnchorDeserialize for UserSubscriptions {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut account = Self {
+            owner: Pubkey::deserialize_reader(reader)?,
+            next_subscription_id: u64::deserialize_reader(reader)?,
+            total_active_commitment: u64::deserialize_reader(reader)?,
+            total_pending_commitment: u64::deserialize_reader(reader)?,
+            bump: u8::deserialize_reader(reader)?,
+            subscriptions: Vec::<UserSubscription>::deserialize_reader(reader)?,
+            payout_layout_version: PAYOUT_LAYOUT_VERSION,
+            payout_recipient: None,
+            payout_overrides: Vec::new(),
+            gifts: Vec::new(),
+            group_charges: Vec::new(),
+            spending_caps: SpendingCaps::default(),
+            billing_slots: Vec::new(),
+        };
+
+        // PayPal-only accounts stored a `paypal_configured` bool where the
+        // current layout stores its version.
+        match u8::deserialize_reader(reader)? {
+            marker @ (0 | 1) => {
+                account.payout_recipient = read_legacy_paypal_tail(marker == 1, reader)?;
+            }
+            PAYOUT_LAYOUT_VERSION => {
+                account.payout_recipient = AnchorDeserialize::deserialize_reader(reader)?;
+                account.payout_overrides = AnchorDeserialize::deserialize_reader(reader)?;
+                account.gifts = AnchorDeserialize::deserialize_reader(reader)?;
+                account.group_charges = AnchorDeserialize::deserialize_reader(reader)?;
+                account.spending_caps = SpendingCaps::deserialize_reader(reader)?;
+                account.billing_slots = AnchorDeserialize::deserialize_reader(reader)?;
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "unknown UserSubscriptions layout",
+                ))
+            }
+        }
+
+        Ok(account)
+    }
+}
+
+/// The PayPal-only tail: recipient type and plaintext receiver.
+fn read_legacy_paypal_tail<R: std::io::Read>(
+    configured: bool,
+    reader: &mut R,
+) -> std::io::Result<Option<PayoutRecipient>> {
+    let recipient_type = PayPalRecipientType::deserialize_reader(reader)?;
+    let receiver = String::deserialize_reader(reader)?;
+    Ok(configured.then_some(PayoutRecipient::PayPal {
+        recipient_type,
+        receiver,
+    }))
+}
+
+/// Limits a user puts on their own commitments on top of the yield budget,
+/// leaving headroom for rounding or a lower APY. Zero leaves a limit unset.
+/// Group-funded subscriptions are bounded by the group's member limits
+/// instead, apart from the price ceiling.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpendingCaps {
+    /// Share of the monthly yield budget that may be committed.
+    pub max_budget_bps: u16,
+    /// Absolute ceiling on the monthly commitment.
+    pub monthly_cap_usdc: u64,
+    /// Most any single service may cost per month.
+    pub max_service_price_usdc: u64,
+}
+
+impl SpendingCaps {
+    pub const SIZE: usize = 2 // max_budget_bps
+        + 8  // monthly_cap_usdc
+        + 8; // max_service_price_usdc
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_budget_bps as u64 <= BASIS_POINTS_DIVISOR,
+            ErrorCode::InvalidSpendingCaps
+        );
+        Ok(())
+    }
+
+    /// Narrows `monthly_budget` to what the caps allow committing.
+    pub fn cap_budget(&self, monthly_budget: u64) -> Result<u64> {
+        let mut capped = monthly_budget;
+        if self.max_budget_bps > 0 {
+            let share = (monthly_budget as u128)
+                .checked_mul(self.max_budget_bps as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(BASIS_POINTS_DIVISOR as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            capped = u64::try_from(share).map_err(|_| ErrorCode::MathOverflow)?;
+        }
+        if self.monthly_cap_usdc > 0 {
+            capped = capped.min(self.monthly_cap_usdc);
+        }
+        Ok(capped)
+    }
+
+    pub fn allows_price(&self, monthly_price_usdc: u64) -> bool {
+        self.max_service_price_usdc == 0 || monthly_price_usdc <= self.max_service_price_usdc
+    }
+}
+
+/// Charges a subscription to a `GroupBudget` instead of the owner's stake.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupCharge {
+    pub subscription_id: u64,
+    pub group: Pubkey,
+}
+
+impl GroupCharge {
+    pub const SIZE: usize = 8 // subscription_id
+        + 32; // group
+}
+
+/// Sends the payouts of one subscription somewhere other than the account
+/// wide `payout_recipient`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PayoutOverride {
+    pub subscription_id: u64,
+    pub recipient: PayoutRecipient,
+}
+
+impl PayoutOverride {
+    pub fn space(&self) -> usize {
+        8 + self.recipient.space()
+    }
+}
+
+/// Marks a subscription the owner pays for on someone else's behalf. The
+/// owner stays the sponsor: the commitment counts against their budget and
+/// only they can cancel it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gift {
+    pub subscription_id: u64,
+    /// Wallet that can list the gift through `get_gifted_subscriptions`. Gifts
+    /// to a bare payout recipient leave it empty.
+    pub beneficiary: Option<Pubkey>,
+}
+
+impl Gift {
+    pub const SIZE: usize = 8 // subscription_id
+        + 1 + 32; // beneficiary
+}
+
+/// A payout receiver sealed with a NaCl box from an ephemeral X25519 key to
+/// the operator key published in `SublyConfig`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedReceiver {
+    pub operator_key: [u8; 32],
+    pub ephemeral_key: [u8; 32],
+    pub nonce: [u8; 24],
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedReceiver {
+    pub fn space(&self) -> usize {
+        32 // operator_key
+            + 32 // ephemeral_key
+            + 24 // nonce
+            + 4 + self.ciphertext.len() // ciphertext
+    }
 }
 
 impl UserSubscriptions {
@@ -444,13 +1702,37 @@ impl UserSubscriptions {
         + 8  // total_pending_commitment
         + 1  // bump
         + 4  // subscriptions length prefix
-        + 1  // paypal_configured
-        + 1  // paypal_recipient_type enum tag
-        + 4; // paypal_receiver length prefix
+        + 1  // payout_layout_version
+        + 1  // payout_recipient option tag
+        + 4  // payout_overrides length prefix
+        + 4  // gifts length prefix
+        + 4  // group_charges length prefix
+        + SpendingCaps::SIZE // spending_caps
+        + 4; // billing_slots length prefix
 
     pub const INITIAL_SIZE: usize =
         Self::BASE_SIZE + Self::INITIAL_SUBSCRIPTION_CAPACITY * UserSubscription::SIZE;
 
+    /// Loads a `UserSubscriptions` PDA passed as a view remaining account,
+    /// or `None` when the account is not one.
+    pub fn load_view(account_info: &AccountInfo) -> Option<Self> {
+        if account_info.owner != &crate::ID {
+            return None;
+        }
+        let data_ref = account_info.try_borrow_data().ok()?;
+        let mut data_slice: &[u8] = &data_ref;
+        let user_subscriptions = Self::try_deserialize(&mut data_slice).ok()?;
+
+        let (expected_pda, _) = Pubkey::find_program_address(
+            &[
+                USER_SUBSCRIPTIONS_SEED.as_bytes(),
+                user_subscriptions.owner.as_ref(),
+            ],
+            &crate::ID,
+        );
+        (expected_pda == *account_info.key).then_some(user_subscriptions)
+    }
+
     pub fn ensure_owner(&mut self, owner: Pubkey, bump: u8) {
         if self.owner == Pubkey::default() {
             self.owner = owner;
@@ -459,18 +1741,87 @@ impl UserSubscriptions {
             self.total_active_commitment = 0;
             self.total_pending_commitment = 0;
             self.subscriptions = Vec::with_capacity(Self::INITIAL_SUBSCRIPTION_CAPACITY);
-            self.paypal_configured = false;
-            self.paypal_recipient_type = PayPalRecipientType::Email;
-            self.paypal_receiver = String::new();
+            self.payout_layout_version = PAYOUT_LAYOUT_VERSION;
+            self.payout_recipient = None;
+            self.payout_overrides = Vec::new();
+            self.gifts = Vec::new();
+            self.group_charges = Vec::new();
+            self.spending_caps = SpendingCaps::default();
+            self.billing_slots = Vec::new();
+        }
+    }
+
+    pub fn required_size(subscription_count: usize, tail_len: usize) -> usize {
+        Self::BASE_SIZE + subscription_count * UserSubscription::SIZE + tail_len
+    }
+
+    /// Bytes taken by the variable-length data after the subscriptions vector.
+    pub fn tail_len(&self) -> usize {
+        self.payout_recipient
+            .as_ref()
+            .map_or(0, PayoutRecipient::space)
+            + self
+                .payout_overrides
+                .iter()
+                .map(PayoutOverride::space)
+                .sum::<usize>()
+            + self.gifts.len() * Gift::SIZE
+            + self.group_charges.len() * GroupCharge::SIZE
+            + self.billing_slots.len() * BillingSlot::SIZE
+    }
+
+    pub fn billing_slot(&self, subscription_id: u64) -> Option<u16> {
+        self.billing_slots
+            .iter()
+            .find(|slot| slot.subscription_id == subscription_id)
+            .map(|slot| slot.chunk)
+    }
+
+    pub fn set_billing_slot(&mut self, subscription_id: u64, chunk: u16) {
+        self.clear_billing_slot(subscription_id);
+        self.billing_slots.push(BillingSlot {
+            subscription_id,
+            chunk,
+        });
+    }
+
+    pub fn clear_billing_slot(&mut self, subscription_id: u64) {
+        self.billing_slots
+            .retain(|slot| slot.subscription_id != subscription_id);
+    }
+
+    pub fn gift(&self, subscription_id: u64) -> Option<&Gift> {
+        self.gifts
+            .iter()
+            .find(|gift| gift.subscription_id == subscription_id)
+    }
+
+    /// The override for `subscription_id` if there is one, else the account
+    /// wide recipient.
+    /// Gifts never fall back to the account wide recipient: that is the
+    /// sponsor's own payout, not the beneficiary's.
+    pub fn payout_recipient_for(&self, subscription_id: u64) -> Option<&PayoutRecipient> {
+        let payout_override = self.payout_override(subscription_id);
+        if self.gift(subscription_id).is_some() {
+            return payout_override;
         }
+        payout_override.or(self.payout_recipient.as_ref())
+    }
+
+    pub fn payout_override(&self, subscription_id: u64) -> Option<&PayoutRecipient> {
+        self.payout_overrides
+            .iter()
+            .find(|payout_override| payout_override.subscription_id == subscription_id)
+            .map(|payout_override| &payout_override.recipient)
     }
 
-    pub fn required_size(subscription_count: usize, receiver_len: usize) -> usize {
-        Self::BASE_SIZE + subscription_count * UserSubscription::SIZE + receiver_len
+    pub fn payout_configured(&self) -> bool {
+        self.payout_recipient.is_some()
     }
 
-    pub fn receiver_len(&self) -> usize {
-        self.paypal_receiver.len()
+    pub fn receiver_hash_for(&self, subscription_id: u64) -> [u8; 32] {
+        self.payout_recipient_for(subscription_id)
+            .map_or([0u8; 32], |recipient| recipient.receiver_hash(&self.owner))
     }
 
     pub fn refresh(&mut self, now: i64) -> Result<()> {
@@ -488,6 +1839,21 @@ impl UserSubscriptions {
             }
         }
 
+        let subscriptions = &self.subscriptions;
+        let is_live = |subscription_id: u64| {
+            subscriptions.iter().any(|subscription| {
+                subscription.id == subscription_id
+                    && subscription.status != SubscriptionStatus::Cancelled
+            })
+        };
+        self.payout_overrides
+            .retain(|payout_override| is_live(payout_override.subscription_id));
+        self.gifts.retain(|gift| is_live(gift.subscription_id));
+        self.group_charges
+            .retain(|charge| is_live(charge.subscription_id));
+        self.billing_slots
+            .retain(|slot| is_live(slot.subscription_id));
+
         if released > 0 {
             self.total_pending_commitment = self
                 .total_pending_commitment
@@ -504,11 +1870,76 @@ impl UserSubscriptions {
             .ok_or(ErrorCode::MathOverflow.into())
     }
 
+    pub fn group_charge(&self, subscription_id: u64) -> Option<&GroupCharge> {
+        self.group_charges
+            .iter()
+            .find(|charge| charge.subscription_id == subscription_id)
+    }
+
+    /// Live commitments charged to `group`, or to any group when `None`.
+    pub fn committed_to_group(&self, group: Option<Pubkey>) -> Result<u64> {
+        self.subscriptions
+            .iter()
+            .filter(|subscription| {
+                subscription.status != SubscriptionStatus::Cancelled
+                    && self
+                        .group_charge(subscription.id)
+                        .is_some_and(|charge| group.is_none() || group == Some(charge.group))
+            })
+            .try_fold(0u64, |acc, subscription| {
+                acc.checked_add(subscription.monthly_price_usdc)
+                    .ok_or_else(|| ErrorCode::MathOverflow.into())
+            })
+    }
+
+    /// What the owner's own stake has to cover, leaving out group charges.
+    pub fn personal_committed(&self) -> Result<u64> {
+        self.total_committed()?
+            .checked_sub(self.committed_to_group(None)?)
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    pub fn has_active_or_pending(&self) -> bool {
+        self.subscriptions.iter().any(|subscription| {
+            subscription.status == SubscriptionStatus::Active
+                || subscription.status == SubscriptionStatus::PendingCancellation
+        })
+    }
+
+    /// Only looks at the owner's own subscriptions; gifts of the same service
+    /// do not count.
     pub fn has_active_or_pending_for_service(&self, service_id: u64) -> bool {
         self.subscriptions.iter().any(|subscription| {
             subscription.service_id == service_id
                 && (subscription.status == SubscriptionStatus::Active
                     || subscription.status == SubscriptionStatus::PendingCancellation)
+                && self.gift(subscription.id).is_none()
+        })
+    }
+
+    /// Gifts are told apart by beneficiary wallet, or by receiver when they
+    /// only carry a payout recipient.
+    pub fn has_active_or_pending_gift(
+        &self,
+        service_id: u64,
+        beneficiary: Option<Pubkey>,
+        receiver_hash: [u8; 32],
+    ) -> bool {
+        self.subscriptions.iter().any(|subscription| {
+            let Some(gift) = self.gift(subscription.id) else {
+                return false;
+            };
+            let same_beneficiary = match beneficiary {
+                Some(_) => gift.beneficiary == beneficiary,
+                None => {
+                    gift.beneficiary.is_none()
+                        && self.receiver_hash_for(subscription.id) == receiver_hash
+                }
+            };
+            subscription.service_id == service_id
+                && (subscription.status == SubscriptionStatus::Active
+                    || subscription.status == SubscriptionStatus::PendingCancellation)
+                && same_beneficiary
         })
     }
 
@@ -595,10 +2026,89 @@ impl UserSubscriptions {
         ))
     }
 
-    pub fn set_paypal_recipient(&mut self, recipient_type: PayPalRecipientType, receiver: String) {
-        self.paypal_configured = true;
-        self.paypal_recipient_type = recipient_type;
-        self.paypal_receiver = receiver;
+    /// Grows the account to fit the payout data currently held in memory, so
+    /// call it after changing recipients and before the handler returns.
+    pub fn reserve_tail<'info>(
+        &self,
+        subscriptions_info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let required_space = Self::required_size(self.subscriptions.len(), self.tail_len());
+        if subscriptions_info.data_len() >= required_space {
+            return Ok(());
+        }
+
+        let required_lamports = Rent::get()?.minimum_balance(required_space);
+        let current_lamports = subscriptions_info.lamports();
+        if required_lamports > current_lamports {
+            let transfer_accounts = SystemTransfer {
+                from: payer.clone(),
+                to: subscriptions_info.clone(),
+            };
+            system_program::transfer(
+                CpiContext::new(system_program.clone(), transfer_accounts),
+                required_lamports - current_lamports,
+            )?;
+        }
+        subscriptions_info.resize(required_space)?;
+        Ok(())
+    }
+
+    pub fn set_payout_recipient(&mut self, recipient: PayoutRecipient) {
+        self.payout_recipient = Some(recipient);
+    }
+
+    pub fn clear_payout_recipient(&mut self) {
+        self.payout_recipient = None;
+    }
+
+    pub fn record_group_charge(&mut self, subscription_id: u64, group: Pubkey) {
+        self.group_charges
+            .retain(|charge| charge.subscription_id != subscription_id);
+        self.group_charges.push(GroupCharge {
+            subscription_id,
+            group,
+        });
+    }
+
+    pub fn record_gift(&mut self, subscription_id: u64, beneficiary: Option<Pubkey>) {
+        self.gifts
+            .retain(|gift| gift.subscription_id != subscription_id);
+        self.gifts.push(Gift {
+            subscription_id,
+            beneficiary,
+        });
+    }
+
+    pub fn set_payout_override(
+        &mut self,
+        subscription_id: u64,
+        recipient: Option<PayoutRecipient>,
+    ) -> Result<()> {
+        let subscription = self
+            .subscriptions
+            .iter()
+            .find(|subscription| subscription.id == subscription_id)
+            .ok_or(ErrorCode::SubscriptionNotFound)?;
+        require!(
+            subscription.status != SubscriptionStatus::Cancelled,
+            ErrorCode::SubscriptionNotActive
+        );
+        require!(
+            recipient.is_some() || self.gift(subscription_id).is_none(),
+            ErrorCode::GiftPayoutOverrideMissing
+        );
+
+        self.payout_overrides
+            .retain(|payout_override| payout_override.subscription_id != subscription_id);
+        if let Some(recipient) = recipient {
+            self.payout_overrides.push(PayoutOverride {
+                subscription_id,
+                recipient,
+            });
+        }
+        Ok(())
     }
 
     pub fn record_payment(
@@ -698,15 +2208,20 @@ impl UserStake {
         Ok(())
     }
 
-    pub fn sync_against_index(&mut self, acc_index: u128, now: i64) -> Result<u64> {
+    pub fn sync_against_index(&mut self, config: &SublyConfig, now: i64) -> Result<(u64, u64)> {
         let mut accrued: u64 = 0;
+        let mut protocol_fees: u64 = 0;
         for entry in self.entries.iter_mut() {
+            let (entry_accrual, entry_fee) = entry.sync_to_index(config)?;
             accrued = accrued
-                .checked_add(entry.sync_to_index(acc_index)?)
+                .checked_add(entry_accrual)
+                .ok_or(ErrorCode::MathOverflow)?;
+            protocol_fees = protocol_fees
+                .checked_add(entry_fee)
                 .ok_or(ErrorCode::MathOverflow)?;
         }
         self.last_updated_ts = now;
-        Ok(accrued)
+        Ok((accrued, protocol_fees))
     }
 
     pub fn record_stake(
@@ -803,6 +2318,18 @@ impl UserStake {
         Ok(claimed)
     }
 
+    /// Principal of tranches pledged to groups, which `total_principal` leaves
+    /// out while the entries keep it.
+    pub fn pledged_principal(&self) -> Result<u64> {
+        let entries_principal = self.entries.iter().try_fold(0u64, |acc, entry| {
+            acc.checked_add(entry.principal)
+                .ok_or(ErrorCode::MathOverflow)
+        })?;
+        entries_principal
+            .checked_sub(self.total_principal)
+            .ok_or_else(|| ErrorCode::MathOverflow.into())
+    }
+
     pub fn find_entry_mut(&mut self, tranche_id: u64) -> Option<&mut StakeEntry> {
         self.entries
             .iter_mut()
@@ -9,12 +9,16 @@ import {
   mintTo,
 } from "@solana/spl-token";
 import { expect } from "chai";
+import nacl from "tweetnacl";
 
 import { SublySolanaProgram } from "../target/types/subly_solana_program";
+import { encryptReceiver, receiverHash, resolveReceiver } from "../scripts/receiver-crypto";
 
 const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
 
 const toBN = (value: bigint) => new anchor.BN(value.toString());
+const SECONDS_PER_DAY = 86_400;
+const BILLING_PERIOD_SECONDS = 30 * SECONDS_PER_DAY;
 const formatUsdc = (lamports: anchor.BN | number | bigint) => {
   const bn =
     lamports instanceof anchor.BN
@@ -65,6 +69,12 @@ describe("subly-solana-program", () => {
     [Buffer.from("user_subscriptions"), wallet.publicKey.toBuffer()],
     program.programId
   );
+  // Every service in these tests is created by the provider wallet, so this
+  // is the merchant PDA each subscription instruction expects.
+  const [merchantPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("merchant"), wallet.publicKey.toBuffer()],
+    program.programId
+  );
 
   let mint: PublicKey;
   let walletTokenAccount: PublicKey;
@@ -104,6 +114,129 @@ describe("subly-solana-program", () => {
     return events;
   };
 
+  const billingBucketPda = (dueTs: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("billing_bucket"),
+        new anchor.BN(Math.floor(dueTs / SECONDS_PER_DAY)).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const tranchePledgePda = (owner: PublicKey, trancheId: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tranche_pledge"),
+        owner.toBuffer(),
+        new anchor.BN(trancheId).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const ensureBillingBucket = async (dueTs: number) => {
+    const billingBucket = billingBucketPda(dueTs);
+    await program.methods
+      .initBillingBucket(new anchor.BN(Math.floor(dueTs / SECONDS_PER_DAY)), 0)
+      .accounts({
+        payer: wallet.publicKey,
+        billingBucket,
+        previousBucket: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    return billingBucket;
+  };
+
+  // Subscriptions are queued under the day of their first renewal.
+  const nextPeriodBillingBucket = async () => {
+    const slot = await provider.connection.getSlot();
+    const now = await provider.connection.getBlockTime(slot);
+    return ensureBillingBucket(now! + BILLING_PERIOD_SECONDS);
+  };
+
+  const bucketEntries = async (billingBucket: PublicKey) => {
+    const page: any = await program.methods
+      .crankDueBucket(0, { offset: 0, limit: 0 })
+      .accounts({ billingBucket })
+      .view();
+    return page.entries.map(
+      (entry: any) => `${entry.user.toBase58()}:${entry.subscriptionId.toString()}`
+    );
+  };
+
+  const setupStaker = async (stakeAmount: anchor.BN, registerRecipient = true) => {
+    const user = Keypair.generate();
+    const connection = provider.connection;
+    const latestBlockhash = await connection.getLatestBlockhash();
+    const airdropSig = await connection.requestAirdrop(
+      user.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await connection.confirmTransaction({
+      signature: airdropSig,
+      ...latestBlockhash,
+    });
+
+    const tokenAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      mint,
+      user.publicKey
+    );
+    await mintTo(
+      connection,
+      wallet.payer,
+      mint,
+      tokenAccount.address,
+      wallet.payer,
+      stakeAmount.toNumber()
+    );
+
+    const [userStakePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_position"), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const [userSubscriptionsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_subscriptions"), user.publicKey.toBuffer()],
+      program.programId
+    );
+
+    if (registerRecipient) {
+      await program.methods
+        .registerPaypalRecipient({
+          recipientType: "EMAIL",
+          receiver: "subscriber@example.com",
+        })
+        .accounts({
+          user: user.publicKey,
+          userSubscriptions: userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+    }
+    await program.methods
+      .stake(stakeAmount, 0)
+      .accounts({
+        config: configPda,
+        user: user.publicKey,
+        userPosition: userStakePda,
+        userTokenAccount: tokenAccount.address,
+        vault: vaultPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+
+    return {
+      user,
+      tokenAccount: tokenAccount.address,
+      userStakePda,
+      userSubscriptionsPda,
+    };
+  };
+
   before(async () => {
     mint = await createMint(
       provider.connection,
@@ -190,7 +323,7 @@ describe("subly-solana-program", () => {
     }
 
     await program.methods
-      .getSubscriptionServices()
+      .getSubscriptionServices({ offset: 0, limit: 0 })
       .accounts({
         subscriptionRegistry: subscriptionRegistryPda,
       })
@@ -223,6 +356,29 @@ describe("subly-solana-program", () => {
     expect(ultra.name).to.eq("Ultra Elite Concierge");
     expect(ultra.monthlyPriceUsdc.toString()).to.eq("90000000000");
     expect(ultra.provider).to.eq("Ultra Services");
+
+    const firstPage: any = await program.methods
+      .getSubscriptionServices({ offset: 0, limit: 2 })
+      .accounts({
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .view();
+    expect(firstPage.total).to.eq(4);
+    expect(firstPage.nextOffset).to.eq(2);
+    expect(
+      firstPage.services.map((service: any) => service.name)
+    ).to.deep.eq(["Subly Premium", "Stream Vault"]);
+
+    const lastPage: any = await program.methods
+      .getSubscriptionServices({ offset: firstPage.nextOffset, limit: 2 })
+      .accounts({
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .view();
+    expect(lastPage.nextOffset).to.eq(null);
+    expect(
+      lastPage.services.map((service: any) => service.name)
+    ).to.deep.eq(["Music Box", "Ultra Elite Concierge"]);
   });
 
   it("rejects services that exceed the configured metadata limits", async () => {
@@ -250,7 +406,7 @@ describe("subly-solana-program", () => {
     await program.methods
       .registerPaypalRecipient({
         recipientType: "PHONE",
-        receiver: "91-734-234-1234",
+        receiver: "+917342341234",
       })
       .accounts({
         user: wallet.publicKey,
@@ -262,9 +418,9 @@ describe("subly-solana-program", () => {
     const walletSubscriptions: any = await program.account.userSubscriptions.fetch(
       walletSubscriptionsPda
     );
-    expect(walletSubscriptions.paypalConfigured).to.eq(true);
-    expect(walletSubscriptions.paypalRecipientType.phone).to.deep.eq({});
-    expect(walletSubscriptions.paypalReceiver).to.eq("91-734-234-1234");
+    const payPalRecipient = walletSubscriptions.payoutRecipient.payPal;
+    expect(payPalRecipient.recipientType.phone).to.deep.eq({});
+    expect(payPalRecipient.receiver).to.eq("+917342341234");
 
     const fetchSig = await program.methods
       .getPaypalRecipient()
@@ -282,7 +438,266 @@ describe("subly-solana-program", () => {
     expect(fetched!.data.user.toBase58()).to.eq(wallet.publicKey.toBase58());
     expect(fetched!.data.configured).to.eq(true);
     expect(fetched!.data.recipientType).to.eq("PHONE");
-    expect(fetched!.data.receiver).to.eq("91-734-234-1234");
+    expect(fetched!.data.encrypted).to.eq(false);
+    expect(Buffer.from(fetched!.data.receiverHash)).to.deep.eq(
+      receiverHash(wallet.publicKey, "+917342341234")
+    );
+    expect(fetched!.data).to.not.have.property("receiver");
+  });
+
+  it("serves read-only views for any wallet without a signer", async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    const [strangerSubscriptionsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user_subscriptions"), stranger.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initUserSubscriptions()
+      .accounts({
+        user: wallet.publicKey,
+        userSubscriptions: walletSubscriptionsPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const walletSubscriptions: any = await program.account.userSubscriptions.fetch(
+      walletSubscriptionsPda
+    );
+    expect(walletSubscriptions.payoutRecipient.payPal.receiver).to.eq("+917342341234");
+
+    const recipient: any = await program.methods
+      .getPaypalRecipient()
+      .accounts({
+        user: wallet.publicKey,
+        userSubscriptions: walletSubscriptionsPda,
+      })
+      .view();
+    expect(recipient.user.toBase58()).to.eq(wallet.publicKey.toBase58());
+    expect(recipient.configured).to.eq(true);
+    expect(recipient.recipientType).to.eq("PHONE");
+
+    let missingAccountError: unknown;
+    try {
+      await program.methods
+        .getPaypalRecipient()
+        .accounts({
+          user: stranger.publicKey,
+          userSubscriptions: strangerSubscriptionsPda,
+        })
+        .view();
+    } catch (err) {
+      missingAccountError = err;
+    }
+    expect(missingAccountError).to.not.eq(undefined);
+  });
+
+  it("validates PayPal receivers per recipient type and clears them", async () => {
+    const staker = await setupStaker(new anchor.BN(1_000_000));
+    const register = (recipientType: string, receiver: string) =>
+      program.methods
+        .registerPaypalRecipient({ recipientType, receiver })
+        .accounts({
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+
+    await expectAnchorError(register("EMAIL", "subscriber.example.com"), "InvalidPayPalEmail");
+    await expectAnchorError(register("EMAIL", "a..b@example.com"), "InvalidPayPalEmail");
+    await expectAnchorError(register("EMAIL", "subscriber@localhost"), "InvalidPayPalEmail");
+    await expectAnchorError(register("PHONE", "91-734-234-1234"), "InvalidPayPalPhone");
+    await expectAnchorError(register("PHONE", "+0123456789"), "InvalidPayPalPhone");
+    await expectAnchorError(register("PAYPAL_ID", "ABC-123"), "InvalidPayPalId");
+    await expectAnchorError(register("USER_HANDLE", "@bad handle"), "InvalidPayPalUserHandle");
+    await expectAnchorError(register("EMAIL", ""), "InvalidPayoutReceiver");
+    await expectAnchorError(register("PAYPAL_ID", "A".repeat(257)), "InvalidPayoutReceiver");
+
+    await register("PAYPAL_ID", "7XYZ12ABCD345");
+    await register("USER_HANDLE", "@subly.user_01");
+
+    await program.methods
+      .clearPaypalRecipient()
+      .accounts({
+        user: staker.user.publicKey,
+        userSubscriptions: staker.userSubscriptionsPda,
+      })
+      .signers([staker.user])
+      .rpc();
+
+    const cleared: any = await program.account.userSubscriptions.fetch(
+      staker.userSubscriptionsPda
+    );
+    expect(cleared.payoutRecipient).to.eq(null);
+  });
+
+  it("stores encrypted PayPal receivers sealed to the operator key", async () => {
+    const staker = await setupStaker(new anchor.BN(1_000_000));
+    const operator = nacl.box.keyPair();
+    const receiver = "subscriber@example.com";
+    const registerEncrypted = (sealed: ReturnType<typeof encryptReceiver>) =>
+      program.methods
+        .registerEncryptedPaypalRecipient({ recipientType: "EMAIL", ...sealed })
+        .accounts({
+          config: configPda,
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+    const setKey = (key: Uint8Array) =>
+      program.methods
+        .setReceiverEncryptionKey(Array.from(key))
+        .accounts({ config: configPda, authority: wallet.publicKey })
+        .rpc();
+
+    await expectAnchorError(
+      registerEncrypted(encryptReceiver(staker.user.publicKey, receiver, operator.publicKey)),
+      "ReceiverEncryptionKeyNotSet"
+    );
+
+    await setKey(operator.publicKey);
+    try {
+      const stale = nacl.box.keyPair();
+      await expectAnchorError(
+        registerEncrypted(encryptReceiver(staker.user.publicKey, receiver, stale.publicKey)),
+        "ReceiverEncryptionKeyMismatch"
+      );
+
+      const signature = await registerEncrypted(
+        encryptReceiver(staker.user.publicKey, receiver, operator.publicKey)
+      );
+      const registered = (await fetchEventsForSignature(signature)).find(
+        (event) => event.name.toLowerCase() === "payoutrecipientregistered"
+      )?.data;
+      expect(registered.rail).to.eq("PAYPAL");
+      expect(registered.recipientType).to.eq("EMAIL");
+      expect(registered.encrypted).to.eq(true);
+      expect(Buffer.from(registered.receiverHash)).to.deep.eq(
+        receiverHash(staker.user.publicKey, receiver)
+      );
+
+      const stored: any = await program.account.userSubscriptions.fetch(
+        staker.userSubscriptionsPda
+      );
+      const sealed = stored.payoutRecipient.encrypted;
+      expect(sealed.rail.payPal.recipientType.email).to.deep.eq({});
+      expect(Buffer.from(sealed.receiver.ciphertext).includes(receiver)).to.eq(false);
+      expect(
+        resolveReceiver(staker.user.publicKey, stored.payoutRecipient, operator.secretKey)
+      ).to.eq(receiver);
+      expect(
+        resolveReceiver(
+          staker.user.publicKey,
+          stored.payoutRecipient,
+          nacl.box.keyPair().secretKey
+        )
+      ).to.eq(null);
+
+      await program.methods
+        .registerPaypalRecipient({ recipientType: "PHONE", receiver: "+14155550123" })
+        .accounts({
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+      const replaced: any = await program.account.userSubscriptions.fetch(
+        staker.userSubscriptionsPda
+      );
+      expect(replaced.payoutRecipient.encrypted).to.eq(undefined);
+      expect(resolveReceiver(staker.user.publicKey, replaced.payoutRecipient, null)).to.eq(
+        "+14155550123"
+      );
+    } finally {
+      await setKey(new Uint8Array(32));
+    }
+  });
+
+  it("validates and registers non-PayPal payout rails", async () => {
+    const staker = await setupStaker(new anchor.BN(1_000_000));
+    const register = (recipient: any) =>
+      program.methods
+        .registerPayoutRecipient({ recipient })
+        .accounts({
+          config: configPda,
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+
+    await expectAnchorError(register({ venmo: { handle: "@abc" } }), "InvalidVenmoHandle");
+    await expectAnchorError(
+      register({ iban: { iban: "GB82WEST12345698765431" } }),
+      "InvalidIban"
+    );
+    await expectAnchorError(
+      register({ ach: { routingNumber: "021000022", accountNumber: "12345678" } }),
+      "InvalidAchAccount"
+    );
+    await expectAnchorError(
+      register({ wallet: { address: PublicKey.default } }),
+      "InvalidPayoutWallet"
+    );
+    await expectAnchorError(
+      register({
+        payPal: { recipientType: { email: {} }, receiver: "not-an-email" },
+      }),
+      "InvalidPayPalEmail"
+    );
+
+    const expectRail = async (recipient: any, rail: string, receiver: string) => {
+      const signature = await register(recipient);
+      const registered = (await fetchEventsForSignature(signature)).find(
+        (event) => event.name.toLowerCase() === "payoutrecipientregistered"
+      )?.data;
+      expect(registered.rail).to.eq(rail);
+      expect(registered.encrypted).to.eq(false);
+      expect(Buffer.from(registered.receiverHash)).to.deep.eq(
+        receiverHash(staker.user.publicKey, receiver)
+      );
+
+      const info: any = await program.methods
+        .getPaypalRecipient()
+        .accounts({
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+        })
+        .view();
+      expect(info.rail).to.eq(rail);
+      expect(info.receiver).to.eq(receiver);
+    };
+
+    await expectRail({ venmo: { handle: "@subly-user" } }, "VENMO", "@subly-user");
+    await expectRail({ iban: { iban: "DE89370400440532013000" } }, "IBAN", "DE89370400440532013000");
+    await expectRail(
+      { ach: { routingNumber: "021000021", accountNumber: "000123456789" } },
+      "ACH",
+      "021000021:000123456789"
+    );
+    const payoutWallet = Keypair.generate().publicKey;
+    await expectRail({ wallet: { address: payoutWallet } }, "WALLET", payoutWallet.toBase58());
+
+    // Already on the current layout, so migration is a no-op.
+    await program.methods
+      .migrateUserSubscriptions()
+      .accounts({
+        payer: wallet.publicKey,
+        user: staker.user.publicKey,
+        userSubscriptions: staker.userSubscriptionsPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const stored: any = await program.account.userSubscriptions.fetch(
+      staker.userSubscriptionsPda
+    );
+    expect(stored.payoutLayoutVersion).to.eq(2);
+    expect(stored.payoutRecipient.wallet.address.toBase58()).to.eq(payoutWallet.toBase58());
   });
 
   it("stakes, accrues yield, allows operator claim, and enforces user lock", async () => {
@@ -465,6 +880,7 @@ describe("subly-solana-program", () => {
           config: configPda,
           user: user.publicKey,
           userPosition: userStakePda,
+          tranchePledge: tranchePledgePda(user.publicKey, 0),
           vault: vaultPda,
           userTokenAccount: userTokenAccount.address,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -633,13 +1049,12 @@ describe("subly-solana-program", () => {
       .rpc();
 
     const signature = await program.methods
-      .getUserStake()
+      .getUserStake({ offset: 0, limit: 0 })
       .accounts({
         config: configPda,
         user: user.publicKey,
         userPosition: userStakePda,
       })
-      .signers([user])
       .rpc();
 
     const events = await fetchEventsForSignature(signature);
@@ -709,7 +1124,7 @@ describe("subly-solana-program", () => {
     await program.methods
       .registerPaypalRecipient({
         recipientType: "PHONE",
-        receiver: "91-734-234-1234",
+        receiver: "+917342341234",
       })
       .accounts({
         user: subscriptionUser.publicKey,
@@ -733,16 +1148,14 @@ describe("subly-solana-program", () => {
       .rpc();
     const pullAvailableSummary = async () => {
       await program.methods
-        .getUserAvailableServices()
+        .getUserAvailableServices({ offset: 0, limit: 0 })
         .accounts({
           config: configPda,
           user: subscriptionUser.publicKey,
           userPosition: subscriptionUserStakePda,
           userSubscriptions: subscriptionUserSubscriptionsPda,
           subscriptionRegistry: subscriptionRegistryPda,
-          systemProgram: SystemProgram.programId,
         })
-        .signers([subscriptionUser])
         .rpc();
 
       const [configAccount, userStakeAccount, userSubscriptionsAccount, registryAccount] =
@@ -794,13 +1207,22 @@ describe("subly-solana-program", () => {
     );
 
     const firstSubscribeSig = await program.methods
-      .subscribeService({ serviceId: new anchor.BN(streamingServiceId!) })
+      .subscribeService({
+        serviceId: new anchor.BN(streamingServiceId!),
+        payoutOverride: null,
+        gift: null,
+        billingChunk: 0,
+      })
       .accounts({
         config: configPda,
         user: subscriptionUser.publicKey,
         userPosition: subscriptionUserStakePda,
         userSubscriptions: subscriptionUserSubscriptionsPda,
         subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: await nextPeriodBillingBucket(),
+        beneficiaryGifts: null,
+        groupBudget: null,
         systemProgram: SystemProgram.programId,
       })
       .signers([subscriptionUser])
@@ -815,8 +1237,11 @@ describe("subly-solana-program", () => {
     expect(activationEvent.user.toBase58()).to.eq(
       subscriptionUser.publicKey.toBase58()
     );
+    expect(activationEvent.rail).to.eq("PAYPAL");
     expect(activationEvent.recipientType).to.eq("PHONE");
-    expect(activationEvent.receiver).to.eq("91-734-234-1234");
+    expect(Buffer.from(activationEvent.receiverHash)).to.deep.eq(
+      receiverHash(subscriptionUser.publicKey, "+917342341234")
+    );
     expect(activationEvent.monthlyPriceUsdc.toString()).to.eq("30000000");
 
     const subscriptionsAfterFirst: any =
@@ -833,12 +1258,18 @@ describe("subly-solana-program", () => {
       .recordSubscriptionPayment({
         subscriptionId: new anchor.BN(streamingSubscriptionId),
         paymentTs: null,
+        nextBillingChunk: 0,
       })
       .accounts({
         config: configPda,
         operator: wallet.publicKey,
         user: subscriptionUser.publicKey,
         userSubscriptions: subscriptionUserSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: null,
+        nextBillingBucket: null,
+        systemProgram: SystemProgram.programId,
       })
       .rpc();
     const initialPaymentEvents = await fetchEventsForSignature(initialPaymentSig);
@@ -855,7 +1286,12 @@ describe("subly-solana-program", () => {
 
     const initialLookAheadSeconds = new anchor.BN(40 * 24 * 60 * 60);
     const firstDueSig = await program.methods
-      .findDueSubscriptions({ lookAheadSeconds: initialLookAheadSeconds })
+      .findDueSubscriptions({
+        lookAheadSeconds: initialLookAheadSeconds,
+        startUserIndex: 0,
+        startSubscriptionId: new anchor.BN(0),
+        maxEntries: 0,
+      })
       .accounts({
         config: configPda,
         subscriptionRegistry: subscriptionRegistryPda,
@@ -880,18 +1316,30 @@ describe("subly-solana-program", () => {
     );
     expect(firstDueEntry.serviceId.toNumber()).to.eq(streamingServiceId);
     expect(firstDueEntry.monthlyPriceUsdc.toString()).to.eq("30000000");
+    expect(firstDueEntry.rail).to.eq("PAYPAL");
     expect(firstDueEntry.recipientType).to.eq("PHONE");
-    expect(firstDueEntry.receiver).to.eq("91-734-234-1234");
+    expect(Buffer.from(firstDueEntry.receiverHash)).to.deep.eq(
+      receiverHash(subscriptionUser.publicKey, "+917342341234")
+    );
     expect(firstDueEntry.subscriptionId.toNumber()).to.eq(streamingSubscriptionId);
 
     await program.methods
-      .subscribeService({ serviceId: new anchor.BN(musicServiceId!) })
+      .subscribeService({
+        serviceId: new anchor.BN(musicServiceId!),
+        payoutOverride: null,
+        gift: null,
+        billingChunk: 0,
+      })
       .accounts({
         config: configPda,
         user: subscriptionUser.publicKey,
         userPosition: subscriptionUserStakePda,
         userSubscriptions: subscriptionUserSubscriptionsPda,
         subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: await nextPeriodBillingBucket(),
+        beneficiaryGifts: null,
+        groupBudget: null,
         systemProgram: SystemProgram.programId,
       })
       .signers([subscriptionUser])
@@ -900,7 +1348,12 @@ describe("subly-solana-program", () => {
     expect(summaryAfterActive.availableBudget.toString()).to.eq("0");
     expect(summaryAfterActive.availableServiceIds).to.deep.eq([]);
     const secondDueSig = await program.methods
-      .findDueSubscriptions({ lookAheadSeconds: initialLookAheadSeconds })
+      .findDueSubscriptions({
+        lookAheadSeconds: initialLookAheadSeconds,
+        startUserIndex: 0,
+        startSubscriptionId: new anchor.BN(0),
+        maxEntries: 0,
+      })
       .accounts({
         config: configPda,
         subscriptionRegistry: subscriptionRegistryPda,
@@ -925,18 +1378,136 @@ describe("subly-solana-program", () => {
     expect(secondDueServiceIds).to.deep.eq(
       [streamingServiceId!, musicServiceId!].sort((a, b) => a - b)
     );
+
+    const pagedDueAccounts = [
+      { pubkey: configPda, isSigner: false, isWritable: false },
+      {
+        pubkey: subscriptionUserSubscriptionsPda,
+        isSigner: false,
+        isWritable: false,
+      },
+    ];
+    const firstPageSig = await program.methods
+      .findDueSubscriptions({
+        lookAheadSeconds: initialLookAheadSeconds,
+        startUserIndex: 0,
+        startSubscriptionId: new anchor.BN(0),
+        maxEntries: 1,
+      })
+      .accounts({
+        config: configPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .remainingAccounts(pagedDueAccounts)
+      .rpc();
+    const firstPage = (await fetchEventsForSignature(firstPageSig)).find(
+      (event) => event.name.toLowerCase() === "subscriptionsdue"
+    )?.data;
+    expect(firstPage, "Paged SubscriptionsDue event missing").to.not.eq(undefined);
+    expect(firstPage.entries.length).to.eq(1);
+    expect(firstPage.skipped.length).to.eq(1);
+    expect(firstPage.skipped[0].account.toBase58()).to.eq(configPda.toBase58());
+    expect(firstPage.skipped[0].reason).to.eq("INVALID_ACCOUNT");
+    expect(firstPage.nextCursor).to.not.eq(null);
+    expect(firstPage.nextCursor.userIndex).to.eq(1);
+
+    // The cursor is also returned, so keepers need not decode logs.
+    const firstScan: any = await program.methods
+      .findDueSubscriptions({
+        lookAheadSeconds: initialLookAheadSeconds,
+        startUserIndex: 0,
+        startSubscriptionId: new anchor.BN(0),
+        maxEntries: 1,
+      })
+      .accounts({
+        config: configPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .remainingAccounts(pagedDueAccounts)
+      .view();
+    expect(firstScan.entryCount).to.eq(1);
+    expect(firstScan.skippedCount).to.eq(1);
+    expect(firstScan.nextCursor.userIndex).to.eq(firstPage.nextCursor.userIndex);
+    expect(firstScan.nextCursor.subscriptionId.toString()).to.eq(
+      firstPage.nextCursor.subscriptionId.toString()
+    );
+
+    const nextPageSig = await program.methods
+      .findDueSubscriptions({
+        lookAheadSeconds: initialLookAheadSeconds,
+        startUserIndex: firstPage.nextCursor.userIndex,
+        startSubscriptionId: firstPage.nextCursor.subscriptionId,
+        maxEntries: 1,
+      })
+      .accounts({
+        config: configPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .remainingAccounts(pagedDueAccounts)
+      .rpc();
+    const nextPage = (await fetchEventsForSignature(nextPageSig)).find(
+      (event) => event.name.toLowerCase() === "subscriptionsdue"
+    )?.data;
+    expect(nextPage, "Second paged SubscriptionsDue event missing").to.not.eq(undefined);
+    expect(nextPage.entries.length).to.eq(1);
+    expect(nextPage.skipped.length).to.eq(0);
+    expect(nextPage.nextCursor).to.eq(null);
+    expect(
+      [firstPage.entries[0], nextPage.entries[0]]
+        .map((entry: any) => entry.serviceId.toNumber())
+        .sort((a: number, b: number) => a - b)
+    ).to.deep.eq(secondDueServiceIds);
+
+    // Skips are capped like entries, and the cursor resumes at the first
+    // account that did not fit.
+    const manyInvalidAccounts = [
+      ...Array.from({ length: 17 }, () => pagedDueAccounts[0]),
+      pagedDueAccounts[1],
+    ];
+    const cappedScan: any = await program.methods
+      .findDueSubscriptions({
+        lookAheadSeconds: initialLookAheadSeconds,
+        startUserIndex: 0,
+        startSubscriptionId: new anchor.BN(0),
+        maxEntries: 0,
+      })
+      .accounts({
+        config: configPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .remainingAccounts(manyInvalidAccounts)
+      .view();
+    expect(cappedScan.entryCount).to.eq(0);
+    expect(cappedScan.skippedCount).to.eq(16);
+    expect(cappedScan.nextCursor.userIndex).to.eq(16);
+    const streamingQueueKey = `${subscriptionUser.publicKey.toBase58()}:${streamingSubscriptionId}`;
+    const streamingDueTs = streamingEntry.nextBillingTs.toNumber();
+    const streamingBucket = billingBucketPda(streamingDueTs);
+    expect(await bucketEntries(streamingBucket)).to.include(streamingQueueKey);
+    const streamingNextBucket = await ensureBillingBucket(
+      streamingDueTs + BILLING_PERIOD_SECONDS
+    );
+
     const paymentSig = await program.methods
       .recordSubscriptionPayment({
         subscriptionId: new anchor.BN(streamingSubscriptionId),
         paymentTs: null,
+        nextBillingChunk: 0,
       })
       .accounts({
         config: configPda,
         operator: wallet.publicKey,
         user: subscriptionUser.publicKey,
         userSubscriptions: subscriptionUserSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: streamingBucket,
+        nextBillingBucket: streamingNextBucket,
+        systemProgram: SystemProgram.programId,
       })
       .rpc();
+    expect(await bucketEntries(streamingBucket)).to.not.include(streamingQueueKey);
+    expect(await bucketEntries(streamingNextBucket)).to.include(streamingQueueKey);
     const paymentEvents = await fetchEventsForSignature(paymentSig);
     const paymentEvent = paymentEvents.find(
       (event) => event.name.toLowerCase() === "subscriptionpaymentrecorded"
@@ -949,7 +1520,12 @@ describe("subly-solana-program", () => {
 
     const shortLookAheadSeconds = new anchor.BN(10 * 24 * 60 * 60);
     const postPaymentDueSig = await program.methods
-      .findDueSubscriptions({ lookAheadSeconds: shortLookAheadSeconds })
+      .findDueSubscriptions({
+        lookAheadSeconds: shortLookAheadSeconds,
+        startUserIndex: 0,
+        startSubscriptionId: new anchor.BN(0),
+        maxEntries: 0,
+      })
       .accounts({
         config: configPda,
         subscriptionRegistry: subscriptionRegistryPda,
@@ -984,13 +1560,12 @@ describe("subly-solana-program", () => {
     expect(subscriptionsAfterActivate.subscriptions.length).to.eq(2);
 
     const listSig = await program.methods
-      .getUserSubscriptions()
+      .getUserSubscriptions({ offset: 0, limit: 0 })
       .accounts({
         user: subscriptionUser.publicKey,
         userSubscriptions: subscriptionUserSubscriptionsPda,
         subscriptionRegistry: subscriptionRegistryPda,
       })
-      .signers([subscriptionUser])
       .rpc();
     const listEvents = await fetchEventsForSignature(listSig);
     const listed = listEvents.find(
@@ -1020,13 +1595,22 @@ describe("subly-solana-program", () => {
 
     await expectAnchorError(
       program.methods
-        .subscribeService({ serviceId: new anchor.BN(premiumServiceId!) })
+        .subscribeService({
+          serviceId: new anchor.BN(premiumServiceId!),
+          payoutOverride: null,
+          gift: null,
+          billingChunk: 0,
+        })
         .accounts({
           config: configPda,
           user: subscriptionUser.publicKey,
           userPosition: subscriptionUserStakePda,
           userSubscriptions: subscriptionUserSubscriptionsPda,
           subscriptionRegistry: subscriptionRegistryPda,
+          merchant: merchantPda,
+          billingBucket: await nextPeriodBillingBucket(),
+          beneficiaryGifts: null,
+          groupBudget: null,
           systemProgram: SystemProgram.programId,
         })
         .signers([subscriptionUser])
@@ -1043,9 +1627,13 @@ describe("subly-solana-program", () => {
       .accounts({
         user: subscriptionUser.publicKey,
         userSubscriptions: subscriptionUserSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: streamingNextBucket,
       })
       .signers([subscriptionUser])
       .rpc();
+    expect(await bucketEntries(streamingNextBucket)).to.not.include(streamingQueueKey);
     const subscriptionsAfterUnsubscribe: any =
       await program.account.userSubscriptions.fetch(
         subscriptionUserSubscriptionsPda
@@ -1072,13 +1660,12 @@ describe("subly-solana-program", () => {
     expect(summaryWhilePending.availableServiceIds).to.deep.eq([]);
 
     const listAfterUnsubscribeSig = await program.methods
-      .getUserSubscriptions()
+      .getUserSubscriptions({ offset: 0, limit: 0 })
       .accounts({
         user: subscriptionUser.publicKey,
         userSubscriptions: subscriptionUserSubscriptionsPda,
         subscriptionRegistry: subscriptionRegistryPda,
       })
-      .signers([subscriptionUser])
       .rpc();
     const listAfterEvents = await fetchEventsForSignature(listAfterUnsubscribeSig);
     const afterListed = listAfterEvents.find(
@@ -1099,13 +1686,22 @@ describe("subly-solana-program", () => {
 
     await expectAnchorError(
       program.methods
-        .subscribeService({ serviceId: new anchor.BN(premiumServiceId!) })
+        .subscribeService({
+          serviceId: new anchor.BN(premiumServiceId!),
+          payoutOverride: null,
+          gift: null,
+          billingChunk: 0,
+        })
         .accounts({
           config: configPda,
           user: subscriptionUser.publicKey,
           userPosition: subscriptionUserStakePda,
           userSubscriptions: subscriptionUserSubscriptionsPda,
           subscriptionRegistry: subscriptionRegistryPda,
+          merchant: merchantPda,
+          billingBucket: await nextPeriodBillingBucket(),
+          beneficiaryGifts: null,
+          groupBudget: null,
           systemProgram: SystemProgram.programId,
         })
         .signers([subscriptionUser])
@@ -1113,4 +1709,1239 @@ describe("subly-solana-program", () => {
       "SubscriptionBudgetExceeded"
     );
   });
+
+  it("opens billing bucket chunks only behind a full one", async () => {
+    // A day no other test queues renewals under.
+    const day = new anchor.BN(1_000);
+    const chunkPda = (chunk: number) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("billing_bucket"),
+          day.toArrayLike(Buffer, "le", 8),
+          chunk > 0 ? new anchor.BN(chunk).toArrayLike(Buffer, "le", 2) : Buffer.alloc(0),
+        ],
+        program.programId
+      )[0];
+    const initChunk = (chunk: number, previousBucket: PublicKey | null) =>
+      program.methods
+        .initBillingBucket(day, chunk)
+        .accounts({
+          payer: wallet.publicKey,
+          billingBucket: chunkPda(chunk),
+          previousBucket,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    await initChunk(0, null);
+    // Keepers stop at the first missing chunk, so chunk 1 must wait for
+    // chunk 0 to fill.
+    await expectAnchorError(initChunk(1, null), "BillingBucketChunkOutOfOrder");
+    await expectAnchorError(initChunk(1, chunkPda(0)), "BillingBucketChunkOutOfOrder");
+    expect(await provider.connection.getAccountInfo(chunkPda(1))).to.eq(null);
+  });
+
+  it("tracks merchant subscribers and revenue", async () => {
+    await program.methods
+      .registerMerchant({
+        displayName: "Subly Labs",
+        payoutDestination: walletTokenAccount,
+      })
+      .accounts({
+        owner: wallet.publicKey,
+        merchant: merchantPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const staker = await setupStaker(new anchor.BN(3_600_000_000)); // 30 USDC monthly budget
+
+    await program.methods
+      .subscribeService({
+        serviceId: new anchor.BN(premiumServiceId!),
+        payoutOverride: null,
+        gift: null,
+        billingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: await nextPeriodBillingBucket(),
+        beneficiaryGifts: null,
+        groupBudget: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([staker.user])
+      .rpc();
+
+    let merchant: any = await program.account.merchant.fetch(merchantPda);
+    expect(merchant.displayName).to.eq("Subly Labs");
+    expect(merchant.activeSubscribers.toNumber()).to.eq(1);
+    expect(merchant.pendingRevenueUsdc.toNumber()).to.eq(15_000_000);
+    expect(merchant.lifetimeRevenueUsdc.toNumber()).to.eq(0);
+
+    await program.methods
+      .recordSubscriptionPayment({
+        subscriptionId: new anchor.BN(0),
+        paymentTs: null,
+        nextBillingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        operator: wallet.publicKey,
+        user: staker.user.publicKey,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: null,
+        nextBillingBucket: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    merchant = await program.account.merchant.fetch(merchantPda);
+    expect(merchant.lifetimeRevenueUsdc.toNumber()).to.eq(15_000_000);
+    expect(merchant.pendingRevenueUsdc.toNumber()).to.eq(15_000_000);
+
+    const subscribersPage: any = await program.methods
+      .getMerchantSubscribers({ offset: 0, limit: 0 })
+      .accounts({
+        merchant: merchantPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .remainingAccounts([
+        {
+          pubkey: staker.userSubscriptionsPda,
+          isSigner: false,
+          isWritable: false,
+        },
+        // Not a UserSubscriptions PDA; reported instead of failing the view.
+        { pubkey: staker.userStakePda, isSigner: false, isWritable: false },
+      ])
+      .view();
+    expect(subscribersPage.merchant.toBase58()).to.eq(merchant.owner.toBase58());
+    expect(subscribersPage.lifetimeRevenueUsdc.toNumber()).to.eq(15_000_000);
+    expect(subscribersPage.total).to.eq(1);
+    expect(subscribersPage.nextOffset).to.eq(null);
+    expect(subscribersPage.subscribers.length).to.eq(1);
+    expect(subscribersPage.subscribers[0].user.toBase58()).to.eq(
+      staker.user.publicKey.toBase58()
+    );
+    expect(subscribersPage.subscribers[0].status).to.eq("ACTIVE");
+    expect(subscribersPage.skipped.length).to.eq(1);
+    expect(subscribersPage.skipped[0].account.toBase58()).to.eq(
+      staker.userStakePda.toBase58()
+    );
+    expect(subscribersPage.skipped[0].reason).to.eq("INVALID_ACCOUNT");
+
+    // The merchant cannot be left out once the creator has registered one.
+    await expectAnchorError(
+      program.methods
+        .unsubscribeService({ subscriptionId: new anchor.BN(0) })
+        .accounts({
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+          merchant: staker.userStakePda,
+          billingBucket: null,
+        })
+        .signers([staker.user])
+        .rpc(),
+      "InvalidMerchantAccount"
+    );
+
+    await program.methods
+      .unsubscribeService({ subscriptionId: new anchor.BN(0) })
+      .accounts({
+        user: staker.user.publicKey,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: null,
+      })
+      .signers([staker.user])
+      .rpc();
+
+    merchant = await program.account.merchant.fetch(merchantPda);
+    expect(merchant.activeSubscribers.toNumber()).to.eq(0);
+    expect(merchant.pendingRevenueUsdc.toNumber()).to.eq(0);
+    expect(merchant.lifetimeRevenueUsdc.toNumber()).to.eq(15_000_000);
+
+    await expectAnchorError(
+      program.methods
+        .clearPaypalRecipient()
+        .accounts({
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+        })
+        .signers([staker.user])
+        .rpc(),
+      "PayPalRecipientInUse"
+    );
+  });
+
+  it("pays a subscription out to its own recipient override", async () => {
+    const staker = await setupStaker(new anchor.BN(3_600_000_000)); // 30 USDC monthly budget
+    const override = { venmo: { handle: "@subly-gift" } };
+
+    await expectAnchorError(
+      program.methods
+        .subscribeService({
+          serviceId: new anchor.BN(premiumServiceId!),
+          payoutOverride: { venmo: { handle: "@a" } },
+          gift: null,
+          billingChunk: 0,
+        })
+        .accounts({
+          config: configPda,
+          user: staker.user.publicKey,
+          userPosition: staker.userStakePda,
+          userSubscriptions: staker.userSubscriptionsPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+          merchant: merchantPda,
+          billingBucket: await nextPeriodBillingBucket(),
+          beneficiaryGifts: null,
+          groupBudget: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc(),
+      "InvalidVenmoHandle"
+    );
+
+    const subscribeSig = await program.methods
+      .subscribeService({
+        serviceId: new anchor.BN(premiumServiceId!),
+        payoutOverride: override,
+        gift: null,
+        billingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: await nextPeriodBillingBucket(),
+        beneficiaryGifts: null,
+        groupBudget: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([staker.user])
+      .rpc();
+    const activation = (await fetchEventsForSignature(subscribeSig)).find(
+      (event) => event.name.toLowerCase() === "subscriptionactivated"
+    )?.data;
+    expect(activation.rail).to.eq("VENMO");
+    expect(Buffer.from(activation.receiverHash)).to.deep.eq(
+      receiverHash(staker.user.publicKey, "@subly-gift")
+    );
+
+    const findDue = async () => {
+      const signature = await program.methods
+        .findDueSubscriptions({
+          lookAheadSeconds: new anchor.BN(0),
+          startUserIndex: 0,
+          startSubscriptionId: new anchor.BN(0),
+          maxEntries: 0,
+        })
+        .accounts({
+          config: configPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+        })
+        .remainingAccounts([
+          {
+            pubkey: staker.userSubscriptionsPda,
+            isSigner: false,
+            isWritable: false,
+          },
+        ])
+        .rpc();
+      return (await fetchEventsForSignature(signature)).find(
+        (event) => event.name.toLowerCase() === "subscriptionsdue"
+      )?.data.entries[0];
+    };
+
+    let due = await findDue();
+    expect(due.subscriptionId.toNumber()).to.eq(0);
+    expect(due.rail).to.eq("VENMO");
+
+    const page: any = await program.methods
+      .getUserSubscriptions({ offset: 0, limit: 0 })
+      .accounts({
+        user: staker.user.publicKey,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .view();
+    expect(page.subscriptions[0].payoutOverride.venmo.handle).to.eq("@subly-gift");
+
+    const setOverride = (subscriptionId: number, recipient: any) =>
+      program.methods
+        .setSubscriptionPayoutOverride({
+          subscriptionId: new anchor.BN(subscriptionId),
+          recipient,
+        })
+        .accounts({
+          config: configPda,
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+
+    await expectAnchorError(setOverride(7, override), "SubscriptionNotFound");
+
+    const clearSig = await setOverride(0, null);
+    const cleared = (await fetchEventsForSignature(clearSig)).find(
+      (event) => event.name.toLowerCase() === "subscriptionpayoutoverrideupdated"
+    )?.data;
+    expect(cleared.overridden).to.eq(false);
+    expect(cleared.rail).to.eq("PAYPAL");
+    expect(Buffer.from(cleared.receiverHash)).to.deep.eq(
+      receiverHash(staker.user.publicKey, "subscriber@example.com")
+    );
+
+    due = await findDue();
+    expect(due.rail).to.eq("PAYPAL");
+
+    const payoutWallet = Keypair.generate().publicKey;
+    await setOverride(0, { wallet: { address: payoutWallet } });
+    due = await findDue();
+    expect(due.rail).to.eq("WALLET");
+    expect(Buffer.from(due.receiverHash)).to.deep.eq(
+      receiverHash(staker.user.publicKey, payoutWallet.toBase58())
+    );
+
+    const stored: any = await program.account.userSubscriptions.fetch(
+      staker.userSubscriptionsPda
+    );
+    expect(stored.payoutOverrides.length).to.eq(1);
+    expect(stored.payoutOverrides[0].recipient.wallet.address.toBase58()).to.eq(
+      payoutWallet.toBase58()
+    );
+  });
+
+  it("lets a sponsor gift subscriptions from their own budget", async () => {
+    const sponsor = await setupStaker(new anchor.BN(3_600_000_000)); // 30 USDC monthly budget
+    const beneficiary = Keypair.generate();
+    const [beneficiaryGiftsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("beneficiary_gifts"), beneficiary.publicKey.toBuffer()],
+      program.programId
+    );
+    const subscribe = async (gift: any, payoutOverride: any, beneficiaryGifts: any) =>
+      program.methods
+        .subscribeService({
+          serviceId: new anchor.BN(premiumServiceId!),
+          payoutOverride,
+          gift,
+          billingChunk: 0,
+        })
+        .accounts({
+          config: configPda,
+          user: sponsor.user.publicKey,
+          userPosition: sponsor.userStakePda,
+          userSubscriptions: sponsor.userSubscriptionsPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+          merchant: merchantPda,
+          billingBucket: await nextPeriodBillingBucket(),
+          beneficiaryGifts,
+          groupBudget: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([sponsor.user])
+        .rpc();
+
+    const familyRecipient = {
+      payPal: { recipientType: { email: {} }, receiver: "family@example.com" },
+    };
+
+    await subscribe(null, null, null);
+    await expectAnchorError(
+      subscribe({ beneficiary: null }, null, null),
+      "GiftPayoutOverrideMissing"
+    );
+    // A wallet gift would otherwise pay the sponsor's own recipient.
+    await expectAnchorError(
+      subscribe({ beneficiary: beneficiary.publicKey }, null, beneficiaryGiftsPda),
+      "GiftPayoutOverrideMissing"
+    );
+    await expectAnchorError(
+      subscribe({ beneficiary: sponsor.user.publicKey }, familyRecipient, null),
+      "InvalidGiftBeneficiary"
+    );
+    await expectAnchorError(
+      subscribe({ beneficiary: beneficiary.publicKey }, familyRecipient, null),
+      "InvalidBeneficiaryGifts"
+    );
+
+    await program.methods
+      .initBeneficiaryGifts(beneficiary.publicKey)
+      .accounts({
+        payer: wallet.publicKey,
+        beneficiaryGifts: beneficiaryGiftsPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const giftSig = await subscribe(
+      { beneficiary: beneficiary.publicKey },
+      familyRecipient,
+      beneficiaryGiftsPda
+    );
+    const gifted = (await fetchEventsForSignature(giftSig)).find(
+      (event) => event.name.toLowerCase() === "subscriptiongifted"
+    )?.data;
+    expect(gifted.sponsor.toBase58()).to.eq(sponsor.user.publicKey.toBase58());
+    expect(gifted.beneficiary.toBase58()).to.eq(beneficiary.publicKey.toBase58());
+    expect(gifted.subscriptionId.toNumber()).to.eq(1);
+
+    // The beneficiary's recipient cannot be dropped in favour of the sponsor's.
+    await expectAnchorError(
+      program.methods
+        .setSubscriptionPayoutOverride({ subscriptionId: new anchor.BN(1), recipient: null })
+        .accounts({
+          config: configPda,
+          user: sponsor.user.publicKey,
+          userSubscriptions: sponsor.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([sponsor.user])
+        .rpc(),
+      "GiftPayoutOverrideMissing"
+    );
+
+    // Both premium subscriptions now use up the sponsor's 30 USDC budget.
+    await expectAnchorError(
+      subscribe({ beneficiary: null }, { venmo: { handle: "@grandma-subly" } }, null),
+      "SubscriptionBudgetExceeded"
+    );
+
+    const listGifts = async () =>
+      (await program.methods
+        .getGiftedSubscriptions({ offset: 0, limit: 0 })
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          beneficiaryGifts: beneficiaryGiftsPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+        })
+        .remainingAccounts([
+          {
+            pubkey: sponsor.userSubscriptionsPda,
+            isSigner: false,
+            isWritable: false,
+          },
+          // Not a UserSubscriptions PDA; reported instead of failing the view.
+          { pubkey: sponsor.userStakePda, isSigner: false, isWritable: false },
+        ])
+        .view()) as any;
+
+    let page = await listGifts();
+    expect(page.total).to.eq(1);
+    expect(page.skipped.length).to.eq(1);
+    expect(page.skipped[0].reason).to.eq("INVALID_ACCOUNT");
+    expect(page.subscriptions[0].sponsor.toBase58()).to.eq(
+      sponsor.user.publicKey.toBase58()
+    );
+    expect(page.subscriptions[0].serviceId.toNumber()).to.eq(premiumServiceId);
+    expect(page.subscriptions[0].status).to.eq("ACTIVE");
+
+    const sponsorView: any = await program.methods
+      .getUserSubscriptions({ offset: 0, limit: 0 })
+      .accounts({
+        user: sponsor.user.publicKey,
+        userSubscriptions: sponsor.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .view();
+    expect(sponsorView.subscriptions[0].gift).to.eq(null);
+    expect(sponsorView.subscriptions[1].gift.beneficiary.toBase58()).to.eq(
+      beneficiary.publicKey.toBase58()
+    );
+
+    // Only the sponsor controls cancellation.
+    await program.methods
+      .unsubscribeService({ subscriptionId: new anchor.BN(1) })
+      .accounts({
+        user: sponsor.user.publicKey,
+        userSubscriptions: sponsor.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: null,
+      })
+      .signers([sponsor.user])
+      .rpc();
+
+    page = await listGifts();
+    expect(page.subscriptions[0].status).to.eq("PENDING_CANCELLATION");
+  });
+
+  it("pools tranches into a group budget with per-member limits", async () => {
+    const admin = await setupStaker(new anchor.BN(3_600_000_000)); // 30 USDC monthly budget
+    const member = await setupStaker(new anchor.BN(1_000_000));
+    const groupId = new anchor.BN(1);
+    const [groupBudgetPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("group_budget"),
+        admin.user.publicKey.toBuffer(),
+        groupId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const memberAccounts = [admin, member].map((staker) => ({
+      pubkey: staker.userSubscriptionsPda,
+      isSigner: false,
+      isWritable: false,
+    }));
+
+    await program.methods
+      .createGroupBudget(groupId)
+      .accounts({
+        admin: admin.user.publicKey,
+        groupBudget: groupBudgetPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin.user])
+      .rpc();
+    await program.methods
+      .setGroupMember({
+        member: member.user.publicKey,
+        spendingLimitUsdc: new anchor.BN(15_000_000),
+      })
+      .accounts({
+        admin: admin.user.publicKey,
+        groupBudget: groupBudgetPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin.user])
+      .rpc();
+    await program.methods
+      .contributeToGroup(new anchor.BN(0))
+      .accounts({
+        config: configPda,
+        member: admin.user.publicKey,
+        groupBudget: groupBudgetPda,
+        userPosition: admin.userStakePda,
+        userSubscriptions: admin.userSubscriptionsPda,
+        tranchePledge: tranchePledgePda(admin.user.publicKey, 0),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin.user])
+      .rpc();
+
+    const pledged: any = await program.account.userStake.fetch(admin.userStakePda);
+    expect(pledged.totalPrincipal.toNumber()).to.eq(0);
+
+    const subscribe = async (
+      staker: Awaited<ReturnType<typeof setupStaker>>,
+      serviceId: number,
+      remainingAccounts = memberAccounts
+    ) =>
+      program.methods
+        .subscribeService({
+          serviceId: new anchor.BN(serviceId),
+          payoutOverride: null,
+          gift: null,
+          billingChunk: 0,
+        })
+        .accounts({
+          config: configPda,
+          user: staker.user.publicKey,
+          userPosition: staker.userStakePda,
+          userSubscriptions: staker.userSubscriptionsPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+          merchant: merchantPda,
+          billingBucket: await nextPeriodBillingBucket(),
+          beneficiaryGifts: null,
+          groupBudget: groupBudgetPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([staker.user])
+        .rpc();
+
+    await expectAnchorError(
+      subscribe(member, premiumServiceId!, memberAccounts.slice(1)),
+      "GroupMembersMissing"
+    );
+    const activationSig = await subscribe(member, premiumServiceId!);
+    const activated = (await fetchEventsForSignature(activationSig)).find(
+      (event) => event.name.toLowerCase() === "subscriptionactivated"
+    )?.data;
+    expect(activated.group.toBase58()).to.eq(groupBudgetPda.toBase58());
+
+    // The member's 15 USDC limit is used up, and the admin's 30 USDC
+    // subscription would overdraw the pool.
+    await expectAnchorError(
+      subscribe(member, streamingServiceId!),
+      "GroupSpendingLimitExceeded"
+    );
+    await expectAnchorError(
+      subscribe(admin, streamingServiceId!),
+      "SubscriptionBudgetExceeded"
+    );
+
+    const memberView: any = await program.methods
+      .getUserSubscriptions({ offset: 0, limit: 0 })
+      .accounts({
+        user: member.user.publicKey,
+        userSubscriptions: member.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .view();
+    expect(memberView.subscriptions[0].group.toBase58()).to.eq(
+      groupBudgetPda.toBase58()
+    );
+
+    await expectAnchorError(
+      program.methods
+        .unstake(new anchor.BN(0))
+        .accounts({
+          config: configPda,
+          user: admin.user.publicKey,
+          userPosition: admin.userStakePda,
+          tranchePledge: tranchePledgePda(admin.user.publicKey, 0),
+          vault: vaultPda,
+          userTokenAccount: admin.tokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([admin.user])
+        .rpc(),
+      "TranchePledged"
+    );
+    await expectAnchorError(
+      program.methods
+        .withdrawGroupContribution(new anchor.BN(0))
+        .accounts({
+          config: configPda,
+          member: admin.user.publicKey,
+          groupBudget: groupBudgetPda,
+          userPosition: admin.userStakePda,
+          tranchePledge: tranchePledgePda(admin.user.publicKey, 0),
+        })
+        .remainingAccounts(memberAccounts)
+        .signers([admin.user])
+        .rpc(),
+      "SubscriptionBudgetExceeded"
+    );
+    await expectAnchorError(
+      program.methods
+        .removeGroupMember(member.user.publicKey)
+        .accounts({
+          admin: admin.user.publicKey,
+          groupBudget: groupBudgetPda,
+          memberSubscriptions: member.userSubscriptionsPda,
+        })
+        .signers([admin.user])
+        .rpc(),
+      "GroupMemberInUse"
+    );
+  });
+
+  it("lets a group member's first subscription create their account", async () => {
+    const admin = await setupStaker(new anchor.BN(3_600_000_000)); // 30 USDC monthly budget
+    // No recipient registered, so the member has no subscriptions account
+    // until `subscribe_service` creates it.
+    const member = await setupStaker(new anchor.BN(1_000_000), false);
+    const groupId = new anchor.BN(1);
+    const [groupBudgetPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("group_budget"),
+        admin.user.publicKey.toBuffer(),
+        groupId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .createGroupBudget(groupId)
+      .accounts({
+        admin: admin.user.publicKey,
+        groupBudget: groupBudgetPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin.user])
+      .rpc();
+    await program.methods
+      .setGroupMember({
+        member: member.user.publicKey,
+        spendingLimitUsdc: new anchor.BN(0),
+      })
+      .accounts({
+        admin: admin.user.publicKey,
+        groupBudget: groupBudgetPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin.user])
+      .rpc();
+    await program.methods
+      .contributeToGroup(new anchor.BN(0))
+      .accounts({
+        config: configPda,
+        member: admin.user.publicKey,
+        groupBudget: groupBudgetPda,
+        userPosition: admin.userStakePda,
+        userSubscriptions: admin.userSubscriptionsPda,
+        tranchePledge: tranchePledgePda(admin.user.publicKey, 0),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin.user])
+      .rpc();
+
+    await program.methods
+      .subscribeService({
+        serviceId: new anchor.BN(premiumServiceId!),
+        payoutOverride: { venmo: { handle: "@group-member" } },
+        gift: null,
+        billingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        user: member.user.publicKey,
+        userPosition: member.userStakePda,
+        userSubscriptions: member.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket: await nextPeriodBillingBucket(),
+        beneficiaryGifts: null,
+        groupBudget: groupBudgetPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        [admin, member].map((staker) => ({
+          pubkey: staker.userSubscriptionsPda,
+          isSigner: false,
+          isWritable: false,
+        }))
+      )
+      .signers([member.user])
+      .rpc();
+
+    const stored: any = await program.account.userSubscriptions.fetch(
+      member.userSubscriptionsPda
+    );
+    expect(stored.subscriptions.length).to.eq(1);
+    expect(stored.groupCharges[0].group.toBase58()).to.eq(groupBudgetPda.toBase58());
+  });
+
+  it("enforces user-defined spending caps below the yield budget", async () => {
+    const staker = await setupStaker(new anchor.BN(3_600_000_000)); // 30 USDC monthly budget
+    const setCaps = (maxBudgetBps: number, monthlyCapUsdc: number, maxServicePriceUsdc: number) =>
+      program.methods
+        .setSpendingCaps({
+          maxBudgetBps,
+          monthlyCapUsdc: new anchor.BN(monthlyCapUsdc),
+          maxServicePriceUsdc: new anchor.BN(maxServicePriceUsdc),
+        })
+        .accounts({
+          user: staker.user.publicKey,
+          userSubscriptions: staker.userSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+    const subscribe = async (serviceId: number) =>
+      program.methods
+        .subscribeService({
+          serviceId: new anchor.BN(serviceId),
+          payoutOverride: null,
+          gift: null,
+          billingChunk: 0,
+        })
+        .accounts({
+          config: configPda,
+          user: staker.user.publicKey,
+          userPosition: staker.userStakePda,
+          userSubscriptions: staker.userSubscriptionsPda,
+          subscriptionRegistry: subscriptionRegistryPda,
+          merchant: merchantPda,
+          billingBucket: await nextPeriodBillingBucket(),
+          beneficiaryGifts: null,
+          groupBudget: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staker.user])
+        .rpc();
+
+    await expectAnchorError(setCaps(10_001, 0, 0), "InvalidSpendingCaps");
+
+    // Half of the 30 USDC budget leaves room for premium but not streaming.
+    await setCaps(5_000, 0, 0);
+    await expectAnchorError(subscribe(streamingServiceId!), "SpendingCapExceeded");
+    await setCaps(0, 10_000_000, 0);
+    await expectAnchorError(subscribe(premiumServiceId!), "SpendingCapExceeded");
+
+    await setCaps(5_000, 0, 20_000_000);
+    const available: any = await program.methods
+      .getUserAvailableServices({ offset: 0, limit: 0 })
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+      })
+      .view();
+    expect(available.availableBudgetUsdc.toNumber()).to.eq(15_000_000);
+    const availableIds = available.services.map((service: any) => service.id.toNumber());
+    expect(availableIds).to.include(premiumServiceId);
+    expect(availableIds).to.not.include(streamingServiceId);
+
+    await expectAnchorError(subscribe(streamingServiceId!), "ServicePriceAboveCap");
+    await subscribe(premiumServiceId!);
+
+    const stored: any = await program.account.userSubscriptions.fetch(
+      staker.userSubscriptionsPda
+    );
+    expect(stored.spendingCaps.maxBudgetBps).to.eq(5_000);
+    expect(stored.spendingCaps.maxServicePriceUsdc.toNumber()).to.eq(20_000_000);
+  });
+
+  it("quotes the stake needed to afford a set of services", async () => {
+    const quote = async (
+      user: PublicKey,
+      serviceIds: number[],
+      lockOption: number,
+      staker?: Awaited<ReturnType<typeof setupStaker>>
+    ) =>
+      (await program.methods
+        .quoteStakeForServices({
+          serviceIds: serviceIds.map((id) => new anchor.BN(id)),
+          lockOption,
+        })
+        .accounts({
+          config: configPda,
+          user,
+          userPosition: staker?.userStakePda ?? null,
+          userSubscriptions: staker?.userSubscriptionsPda ?? null,
+          subscriptionRegistry: subscriptionRegistryPda,
+        })
+        .view()) as any;
+    const principalFor = (monthlyUsdc: number, apyBps: number) =>
+      Math.ceil((monthlyUsdc * 12 * 10_000) / apyBps);
+
+    // Duplicates are priced once.
+    const newcomer = Keypair.generate().publicKey;
+    const fresh = await quote(
+      newcomer,
+      [premiumServiceId!, streamingServiceId!, premiumServiceId!],
+      3
+    );
+    expect(fresh.lockDuration.toNumber()).to.eq(365 * SECONDS_PER_DAY);
+    expect(fresh.monthlyPriceUsdc.toNumber()).to.eq(45_000_000);
+    expect(fresh.currentPrincipal.toNumber()).to.eq(0);
+    expect(fresh.requiredPrincipal.toNumber()).to.eq(principalFor(45_000_000, fresh.apyBps));
+    expect(fresh.shortfall.toNumber()).to.eq(fresh.requiredPrincipal.toNumber());
+    expect(fresh.monthlyBudgetUsdc.toNumber()).to.be.gte(45_000_000);
+    expect(fresh.withinSpendingCaps).to.eq(true);
+
+    const staker = await setupStaker(new anchor.BN(3_600_000_000));
+    const covered = await quote(staker.user.publicKey, [premiumServiceId!], 0, staker);
+    expect(covered.currentPrincipal.toNumber()).to.eq(3_600_000_000);
+    expect(covered.requiredPrincipal.toNumber()).to.eq(principalFor(15_000_000, covered.apyBps));
+    expect(covered.shortfall.toNumber()).to.eq(0);
+
+    await expectAnchorError(
+      quote(staker.user.publicKey, [premiumServiceId!], 9, staker),
+      "InvalidLockOption"
+    );
+  });
+
+  it("transfers a position and its subscriptions to a new wallet", async () => {
+    const staker = await setupStaker(new anchor.BN(3_600_000_000));
+    const billingBucket = await nextPeriodBillingBucket();
+    await program.methods
+      .subscribeService({
+        serviceId: new anchor.BN(premiumServiceId!),
+        payoutOverride: null,
+        gift: null,
+        billingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket,
+        beneficiaryGifts: null,
+        groupBudget: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([staker.user])
+      .rpc();
+
+    const newOwner = Keypair.generate();
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    const airdropSig = await provider.connection.requestAirdrop(
+      newOwner.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction({ signature: airdropSig, ...latestBlockhash });
+    const [newPositionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_position"), newOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const [newSubscriptionsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_subscriptions"), newOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const before: any = await program.account.userStake.fetch(staker.userStakePda);
+    const transfer = (remainingAccounts: anchor.web3.AccountMeta[]) =>
+      program.methods
+        .transferPosition({ payoutRecipient: null })
+        .accounts({
+          config: configPda,
+          oldOwner: staker.user.publicKey,
+          newOwner: newOwner.publicKey,
+          oldPosition: staker.userStakePda,
+          newPosition: newPositionPda,
+          oldSubscriptions: staker.userSubscriptionsPda,
+          newSubscriptions: newSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([staker.user, newOwner])
+        .rpc();
+
+    // The queued renewal would otherwise stay keyed to the closed PDA.
+    await expectAnchorError(transfer([]), "BillingBucketMissing");
+    await transfer([{ pubkey: billingBucket, isSigner: false, isWritable: true }]);
+
+    expect(await provider.connection.getAccountInfo(staker.userStakePda)).to.eq(null);
+    expect(await provider.connection.getAccountInfo(staker.userSubscriptionsPda)).to.eq(null);
+
+    const moved: any = await program.account.userStake.fetch(newPositionPda);
+    expect(moved.owner.toBase58()).to.eq(newOwner.publicKey.toBase58());
+    expect(moved.totalPrincipal.toString()).to.eq(before.totalPrincipal.toString());
+    expect(moved.entries[0].lockEndTs.toString()).to.eq(before.entries[0].lockEndTs.toString());
+
+    const movedSubscriptions: any = await program.account.userSubscriptions.fetch(
+      newSubscriptionsPda
+    );
+    expect(movedSubscriptions.owner.toBase58()).to.eq(newOwner.publicKey.toBase58());
+    expect(movedSubscriptions.subscriptions).to.have.length(1);
+    expect(movedSubscriptions.payoutRecipient.payPal.receiver).to.eq("subscriber@example.com");
+    expect(movedSubscriptions.billingSlots).to.have.length(1);
+
+    const entries = await bucketEntries(billingBucket);
+    expect(entries).to.include(`${newOwner.publicKey.toBase58()}:0`);
+    expect(entries).to.not.include(`${staker.user.publicKey.toBase58()}:0`);
+  });
+
+  it("refuses to transfer a position without its subscriptions", async () => {
+    const staker = await setupStaker(new anchor.BN(3_600_000_000));
+    const billingBucket = await nextPeriodBillingBucket();
+    await program.methods
+      .subscribeService({
+        serviceId: new anchor.BN(premiumServiceId!),
+        payoutOverride: null,
+        gift: null,
+        billingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+        userSubscriptions: staker.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket,
+        beneficiaryGifts: null,
+        groupBudget: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([staker.user])
+      .rpc();
+
+    const newOwner = Keypair.generate();
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    const airdropSig = await provider.connection.requestAirdrop(
+      newOwner.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction({ signature: airdropSig, ...latestBlockhash });
+    const [newPositionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_position"), newOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const [newSubscriptionsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_subscriptions"), newOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const transfer = (oldSubscriptions: PublicKey, newSubscriptions: PublicKey | null) =>
+      program.methods
+        .transferPosition({ payoutRecipient: null })
+        .accounts({
+          config: configPda,
+          oldOwner: staker.user.publicKey,
+          newOwner: newOwner.publicKey,
+          oldPosition: staker.userStakePda,
+          newPosition: newPositionPda,
+          oldSubscriptions,
+          newSubscriptions,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([{ pubkey: billingBucket, isSigner: false, isWritable: true }])
+        .signers([staker.user, newOwner])
+        .rpc();
+
+    // The active subscription would outlive the stake that backs it.
+    await expectAnchorError(
+      transfer(staker.userSubscriptionsPda, null),
+      "InvalidSubscriptionAccount"
+    );
+    // Another address cannot stand in for the old owner's subscriptions.
+    await expectAnchorError(transfer(newSubscriptionsPda, null), "ConstraintSeeds");
+
+    const position: any = await program.account.userStake.fetch(staker.userStakePda);
+    expect(position.owner.toBase58()).to.eq(staker.user.publicKey.toBase58());
+    const subscriptions: any = await program.account.userSubscriptions.fetch(
+      staker.userSubscriptionsPda
+    );
+    expect(subscriptions.subscriptions).to.have.length(1);
+    expect(await provider.connection.getAccountInfo(newPositionPda)).to.eq(null);
+  });
+
+  it("refuses to transfer a position to the beneficiary of its gifts", async () => {
+    const sponsor = await setupStaker(new anchor.BN(3_600_000_000));
+    const beneficiary = Keypair.generate();
+    const [beneficiaryGiftsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("beneficiary_gifts"), beneficiary.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initBeneficiaryGifts(beneficiary.publicKey)
+      .accounts({
+        payer: wallet.publicKey,
+        beneficiaryGifts: beneficiaryGiftsPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const billingBucket = await nextPeriodBillingBucket();
+    await program.methods
+      .subscribeService({
+        serviceId: new anchor.BN(premiumServiceId!),
+        payoutOverride: {
+          payPal: { recipientType: { email: {} }, receiver: "family@example.com" },
+        },
+        gift: { beneficiary: beneficiary.publicKey },
+        billingChunk: 0,
+      })
+      .accounts({
+        config: configPda,
+        user: sponsor.user.publicKey,
+        userPosition: sponsor.userStakePda,
+        userSubscriptions: sponsor.userSubscriptionsPda,
+        subscriptionRegistry: subscriptionRegistryPda,
+        merchant: merchantPda,
+        billingBucket,
+        beneficiaryGifts: beneficiaryGiftsPda,
+        groupBudget: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([sponsor.user])
+      .rpc();
+
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    const airdropSig = await provider.connection.requestAirdrop(
+      beneficiary.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction({ signature: airdropSig, ...latestBlockhash });
+    const [newPositionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_position"), beneficiary.publicKey.toBuffer()],
+      program.programId
+    );
+    const [newSubscriptionsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_subscriptions"), beneficiary.publicKey.toBuffer()],
+      program.programId
+    );
+
+    // The beneficiary would end up sponsoring its own gift.
+    await expectAnchorError(
+      program.methods
+        .transferPosition({ payoutRecipient: null })
+        .accounts({
+          config: configPda,
+          oldOwner: sponsor.user.publicKey,
+          newOwner: beneficiary.publicKey,
+          oldPosition: sponsor.userStakePda,
+          newPosition: newPositionPda,
+          oldSubscriptions: sponsor.userSubscriptionsPda,
+          newSubscriptions: newSubscriptionsPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: billingBucket, isSigner: false, isWritable: true },
+          { pubkey: beneficiaryGiftsPda, isSigner: false, isWritable: true },
+        ])
+        .signers([sponsor.user, beneficiary])
+        .rpc(),
+      "InvalidGiftBeneficiary"
+    );
+
+    const position: any = await program.account.userStake.fetch(sponsor.userStakePda);
+    expect(position.owner.toBase58()).to.eq(sponsor.user.publicKey.toBase58());
+    expect(await provider.connection.getAccountInfo(newPositionPda)).to.eq(null);
+  });
+
+  it("sets aside a capped protocol fee on accrued yield", async () => {
+    await expectAnchorError(
+      program.methods
+        .setProtocolFee(2_001)
+        .accounts({
+          config: configPda,
+          authority: wallet.publicKey,
+        })
+        .rpc(),
+      "ProtocolFeeTooHigh"
+    );
+
+    await program.methods
+      .setProtocolFee(1_000)
+      .accounts({
+        config: configPda,
+        authority: wallet.publicKey,
+      })
+      .rpc();
+
+    const staker = await setupStaker(new anchor.BN(1_000_000_000_000));
+    const configBefore: any = await program.account.sublyConfig.fetch(configPda);
+    await sleep(1500);
+
+    const syncSig = await program.methods
+      .syncYield()
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+      })
+      .signers([staker.user])
+      .rpc();
+    const syncEvents = await fetchEventsForSignature(syncSig);
+    const feeEvent = syncEvents.find(
+      (event) => event.name.toLowerCase() === "protocolfeesaccrued"
+    );
+    expect(feeEvent, "ProtocolFeesAccrued event missing").to.not.eq(undefined);
+    expect(feeEvent!.data.feeBps).to.eq(1_000);
+
+    const configAfter: any = await program.account.sublyConfig.fetch(configPda);
+    const accruedFees = (configAfter.protocolFees as anchor.BN).sub(
+      configBefore.protocolFees as anchor.BN
+    );
+    expect(accruedFees.gt(new anchor.BN(0))).to.eq(true);
+
+    const position: any = await program.account.userStake.fetch(
+      staker.userStakePda
+    );
+    const unrealized = new anchor.BN(position.entries[0].unrealizedYield);
+    // The staker keeps 90% of the accrual, so the fee is roughly a ninth of it.
+    expect(accruedFees.lte(unrealized.divn(9).addn(1))).to.eq(true);
+
+    const rewardBefore = configAfter.rewardPool as anchor.BN;
+    const withdrawSig = await program.methods
+      .withdrawProtocolFees(new anchor.BN(0))
+      .accounts({
+        config: configPda,
+        authority: wallet.publicKey,
+        vault: vaultPda,
+        authorityTokenAccount: walletTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const withdrawEvents = await fetchEventsForSignature(withdrawSig);
+    const withdrawEvent = withdrawEvents.find(
+      (event) => event.name.toLowerCase() === "protocolfeeswithdrawn"
+    );
+    expect(withdrawEvent, "ProtocolFeesWithdrawn event missing").to.not.eq(undefined);
+    expect(withdrawEvent!.data.amount.toString()).to.eq(
+      (configAfter.protocolFees as anchor.BN).toString()
+    );
+    expect(withdrawEvent!.data.remainingProtocolFees.toString()).to.eq("0");
+
+    const configWithdrawn: any = await program.account.sublyConfig.fetch(configPda);
+    expect(configWithdrawn.protocolFees.toString()).to.eq("0");
+    expect(
+      rewardBefore.sub(configWithdrawn.rewardPool as anchor.BN).toString()
+    ).to.eq((configAfter.protocolFees as anchor.BN).toString());
+
+    await program.methods
+      .setProtocolFee(0)
+      .accounts({
+        config: configPda,
+        authority: wallet.publicKey,
+      })
+      .rpc();
+
+    // The config is already on the current layout, so migration is a no-op.
+    const configInfoBefore = await provider.connection.getAccountInfo(configPda);
+    await program.methods
+      .migrateConfig()
+      .accounts({
+        authority: wallet.publicKey,
+        config: configPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const configInfoAfter = await provider.connection.getAccountInfo(configPda);
+    expect(configInfoAfter!.data.length).to.eq(configInfoBefore!.data.length);
+    expect(configInfoAfter!.data.equals(configInfoBefore!.data)).to.eq(true);
+  });
+
+  it("charges yield accrued before a fee change at the earlier rate", async () => {
+    const staker = await setupStaker(new anchor.BN(1_000_000_000_000));
+    // Accrue fee-free yield without syncing the position.
+    await sleep(3000);
+
+    await program.methods
+      .setProtocolFee(2_000)
+      .accounts({
+        config: configPda,
+        authority: wallet.publicKey,
+      })
+      .rpc();
+    const configBefore: any = await program.account.sublyConfig.fetch(configPda);
+    expect(configBefore.feeCheckpointCount).to.be.greaterThan(0);
+
+    await program.methods
+      .syncYield()
+      .accounts({
+        config: configPda,
+        user: staker.user.publicKey,
+        userPosition: staker.userStakePda,
+      })
+      .signers([staker.user])
+      .rpc();
+
+    const configAfter: any = await program.account.sublyConfig.fetch(configPda);
+    const accruedFees = (configAfter.protocolFees as anchor.BN).sub(
+      configBefore.protocolFees as anchor.BN
+    );
+    const position: any = await program.account.userStake.fetch(
+      staker.userStakePda
+    );
+    const unrealized = new anchor.BN(position.entries[0].unrealizedYield);
+    expect(unrealized.gt(new anchor.BN(0))).to.eq(true);
+    // Most of the accrual predates the 20% rate, so the fee stays under
+    // an eighth of what the staker kept.
+    expect(accruedFees.muln(8).lt(unrealized)).to.eq(true);
+
+    await program.methods
+      .setProtocolFee(0)
+      .accounts({
+        config: configPda,
+        authority: wallet.publicKey,
+      })
+      .rpc();
+  });
 });
@@ -9,6 +9,18 @@ mod circuits {
     const BASIS_POINTS_DIVISOR: u64 = 10_000;
     const SECONDS_PER_YEAR: u64 = 31_536_000;
     const MAX_STAKE_ENTRIES: usize = 16;
+    const TRANCHE_IDS_PER_PAGE: u64 = 4_294_967_296;
+    const SERVICE_TREE_DEPTH: usize = 8;
+    const MAX_SERVICES: u64 = 256;
+    // Mersenne prime 2^61 - 1; products of two reduced values fit in a u128.
+    const SERVICE_FIELD_MODULUS: u128 = 2_305_843_009_213_693_951;
+    const SERVICE_HASH_ROUNDS: usize = 34;
+    const SERVICE_HASH_ROUND_STEP: u128 = 2_177_342_782_468_422_681;
+    // Right child of every leaf, so a leaf never hashes like an inner node.
+    const SERVICE_LEAF_TAG: u128 = 1;
+    const MAX_SUBSCRIPTIONS: usize = 8;
+    /// Unrealized yield a tranche may still carry when it is unstaked.
+    const UNSTAKE_YIELD_DUST: u64 = 10_000;
     const LOCK_OPTIONS: [i64; 4] = [30 * 86_400, 90 * 86_400, 180 * 86_400, 365 * 86_400];
 
     #[derive(Clone, Copy)]
@@ -21,6 +33,15 @@ mod circuits {
         pub paused: bool,
     }
 
+    /// Changes a user's computations make to the aggregate config. They are
+    /// kept next to the user's stake so computations of different users can
+    /// run concurrently, and folded into `ConfigSecrets` by `settle_config_subly`.
+    #[derive(Clone, Copy)]
+    pub struct ConfigDelta {
+        pub principal_added: u64,
+        pub principal_removed: u64,
+    }
+
     #[derive(Clone, Copy)]
     pub struct RegistrySecrets {
         pub next_service_id: u64,
@@ -66,6 +87,22 @@ mod circuits {
         pub entries: [StakeEntrySecrets; MAX_STAKE_ENTRIES],
     }
 
+    #[derive(Clone, Copy)]
+    pub struct SubscriptionSlot {
+        pub service_id: u64,
+        pub monthly_price_usdc: u64,
+        pub active: bool,
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct UserSubscriptionsSecrets {
+        pub committed_usdc: u64,
+        // Principal across all of the user's stake pages. Every stake and
+        // unstake keeps it, and it is what backs the subscriptions.
+        pub backing_principal: u64,
+        pub slots: [SubscriptionSlot; MAX_SUBSCRIPTIONS],
+    }
+
     pub struct InitializeInput {
         pub clock_unix_ts: u64,
     }
@@ -74,11 +111,81 @@ mod circuits {
         pub amount: u64,
         pub lock_option: u8,
         pub now_ts: u64,
+        pub delta_fresh: bool,
+        pub stake_fresh: bool,
+        pub page_index: u16,
+        pub subscriptions_fresh: bool,
     }
 
     pub struct UnstakeInput {
         pub tranche_id: u64,
         pub now_ts: u64,
+        pub delta_fresh: bool,
+        pub subscriptions_fresh: bool,
+    }
+
+    pub struct SettleInput {
+        pub now_ts: u64,
+        pub delta_fresh: bool,
+    }
+
+    pub struct ClaimInput {
+        pub amount: u64,
+        pub now_ts: u64,
+    }
+
+    pub struct FundRewardsInput {
+        pub amount: u64,
+    }
+
+    pub struct SetApyInput {
+        pub apy_bps: u16,
+        pub now_ts: u64,
+    }
+
+    pub struct SetPausedInput {
+        pub paused: bool,
+    }
+
+    pub struct ViewPositionInput {
+        pub now_ts: u64,
+    }
+
+    /// Node of the service Merkle tree, two elements of the service field.
+    #[derive(Clone, Copy)]
+    pub struct ServiceNode {
+        pub low: u128,
+        pub high: u128,
+    }
+
+    /// A service, its price, and the sibling nodes from its leaf up to
+    /// `RegistrySecrets`' services root.
+    pub struct ServiceProof {
+        pub service_id: u64,
+        pub monthly_price_usdc: u64,
+        pub siblings_low: [u64; SERVICE_TREE_DEPTH],
+        pub siblings_high: [u64; SERVICE_TREE_DEPTH],
+    }
+
+    pub struct SubscriptionRequest {
+        pub service_id: u64,
+    }
+
+    /// `fresh` is set until a computation has written the subscriptions
+    /// state, whose blank ciphertexts do not decrypt to anything.
+    pub struct SubscriptionsInput {
+        pub fresh: bool,
+    }
+
+    /// What a user gets to decrypt about one page of their position. The full
+    /// entry array is too large to return through a callback.
+    pub struct PositionSummary {
+        pub total_principal: u64,
+        pub unrealized_yield: u64,
+        pub claimable_yield: u64,
+        pub claimed_user: u64,
+        pub next_unlock_ts: i64,
+        pub entry_count: u8,
     }
 
     #[instruction]
@@ -113,17 +220,33 @@ mod circuits {
     pub fn stake_subly(
         config_ctxt: Enc<Mxe, ConfigSecrets>,
         stake_ctxt: Enc<Mxe, UserStakeSecrets>,
+        delta_ctxt: Enc<Mxe, ConfigDelta>,
+        subscriptions_ctxt: Enc<Mxe, UserSubscriptionsSecrets>,
         input: StakeInput,
-    ) -> (Enc<Mxe, ConfigSecrets>, Enc<Mxe, UserStakeSecrets>, u8, u64) {
+    ) -> (
+        Enc<Mxe, UserStakeSecrets>,
+        Enc<Mxe, ConfigDelta>,
+        Enc<Mxe, UserSubscriptionsSecrets>,
+        bool,
+        u8,
+        u64,
+    ) {
         let mut config = config_ctxt.to_arcis();
-        let mut stake_state = stake_ctxt.to_arcis();
+        let mut stake_state = load_stake(&stake_ctxt, input.stake_fresh, input.page_index);
+        let delta = load_delta(&delta_ctxt, input.delta_fresh);
+        let subscriptions = load_subscriptions(
+            &subscriptions_ctxt,
+            input.subscriptions_fresh,
+            stake_state.total_principal,
+        );
 
         let now = input.now_ts as i64;
         accrue_config(&mut config, now);
         sync_entries(&mut stake_state, config.acc_index, now);
 
-        let mut updated_config = config;
+        let mut updated_delta = delta;
         let mut updated_stake = stake_state;
+        let mut updated_subscriptions = subscriptions;
         let mut placed = false;
 
         let lock_option_index = input.lock_option as usize;
@@ -146,14 +269,20 @@ mod circuits {
                     updated_stake.last_updated_ts = now;
                     updated_stake.entry_count = (current_count as u8) + 1;
                     updated_stake.next_tranche_id = next_id;
-                    updated_config.total_principal = updated_config.total_principal + input.amount;
+                    updated_delta.principal_added += input.amount;
+                    updated_subscriptions.backing_principal += input.amount;
                     placed = true;
                 }
             }
         }
 
         let final_stake = if placed { updated_stake } else { stake_state };
-        let final_config = if placed { updated_config } else { config };
+        let final_delta = if placed { updated_delta } else { delta };
+        let final_subscriptions = if placed {
+            updated_subscriptions
+        } else {
+            subscriptions
+        };
         let final_entry_count = final_stake.entry_count;
         let final_next_tranche_id = final_stake.next_tranche_id;
 
@@ -161,8 +290,10 @@ mod circuits {
         let public_next_tranche_id = final_next_tranche_id.reveal();
 
         (
-            config_ctxt.owner.from_arcis(final_config),
             stake_ctxt.owner.from_arcis(final_stake),
+            Mxe::get().from_arcis(final_delta),
+            Mxe::get().from_arcis(final_subscriptions),
+            placed.reveal(),
             public_entry_count,
             public_next_tranche_id,
         )
@@ -172,20 +303,40 @@ mod circuits {
     pub fn unstake_subly(
         config_ctxt: Enc<Mxe, ConfigSecrets>,
         stake_ctxt: Enc<Mxe, UserStakeSecrets>,
+        delta_ctxt: Enc<Mxe, ConfigDelta>,
+        subscriptions_ctxt: Enc<Mxe, UserSubscriptionsSecrets>,
         input: UnstakeInput,
-    ) -> (Enc<Mxe, ConfigSecrets>, Enc<Mxe, UserStakeSecrets>, u64, u8) {
+    ) -> (
+        Enc<Mxe, UserStakeSecrets>,
+        Enc<Mxe, ConfigDelta>,
+        Enc<Mxe, UserSubscriptionsSecrets>,
+        u64,
+        u64,
+        u8,
+    ) {
         let mut config = config_ctxt.to_arcis();
         let mut stake_state = stake_ctxt.to_arcis();
+        let delta = load_delta(&delta_ctxt, input.delta_fresh);
+        let subscriptions = load_subscriptions(
+            &subscriptions_ctxt,
+            input.subscriptions_fresh,
+            stake_state.total_principal,
+        );
 
         let now = input.now_ts as i64;
         accrue_config(&mut config, now);
         sync_entries(&mut stake_state, config.acc_index, now);
 
-        let mut updated_config = config;
+        let mut updated_delta = delta;
         let mut updated_stake = stake_state;
+        let mut updated_subscriptions = subscriptions;
         let mut withdrawn_principal: u64 = 0;
+        let mut forfeited_yield: u64 = 0;
         let mut removed_index: usize = MAX_STAKE_ENTRIES;
 
+        // A tranche holding more than dust of yield the pool could pay stays
+        // staked until the user claims it. Whatever is left once the pool is
+        // empty is forfeited with the tranche and stays in the pool.
         let active_count = updated_stake.entry_count as usize;
         for idx in 0..MAX_STAKE_ENTRIES {
             let within_active = idx < active_count;
@@ -195,15 +346,25 @@ mod circuits {
                 let matches_tranche = entry.tranche_id == input.tranche_id;
                 let has_principal = entry.principal > 0;
                 let lock_finished = now >= entry.lock_end_ts;
-                let yield_cleared = entry.unrealized_yield == 0;
-                if matches_tranche && has_principal && lock_finished && yield_cleared {
+                let yield_settled =
+                    entry.unrealized_yield <= UNSTAKE_YIELD_DUST || config.reward_pool == 0;
+                if matches_tranche && has_principal && lock_finished && yield_settled {
                     withdrawn_principal = entry.principal;
+                    forfeited_yield = entry.unrealized_yield;
                     removed_index = idx;
                 }
             }
         }
 
-        if removed_index < active_count {
+        // The budget left after the withdrawal must still cover every active
+        // subscription, or the tranche stays staked.
+        let remaining_budget = monthly_budget(
+            subscriptions.backing_principal - withdrawn_principal,
+            config.apy_bps,
+        );
+        let commitments_covered = subscriptions.committed_usdc <= remaining_budget;
+
+        if removed_index < active_count && commitments_covered {
             let last_index = active_count - 1;
             let replacement = updated_stake.entries[last_index];
 
@@ -214,9 +375,11 @@ mod circuits {
             updated_stake.entry_count = last_index as u8;
             updated_stake.total_principal = updated_stake.total_principal - withdrawn_principal;
             updated_stake.last_updated_ts = now;
-            updated_config.total_principal = updated_config.total_principal - withdrawn_principal;
+            updated_delta.principal_removed += withdrawn_principal;
+            updated_subscriptions.backing_principal -= withdrawn_principal;
         } else {
             withdrawn_principal = 0;
+            forfeited_yield = 0;
         }
 
         let final_stake = if withdrawn_principal > 0 {
@@ -225,23 +388,307 @@ mod circuits {
             stake_state
         };
 
-        let final_config = if withdrawn_principal > 0 {
-            updated_config
+        let final_delta = if withdrawn_principal > 0 {
+            updated_delta
         } else {
-            config
+            delta
+        };
+
+        let final_subscriptions = if withdrawn_principal > 0 {
+            updated_subscriptions
+        } else {
+            subscriptions
         };
 
         let public_principal = withdrawn_principal.reveal();
+        let public_forfeited = forfeited_yield.reveal();
         let public_entry_count = final_stake.entry_count.reveal();
 
         (
-            config_ctxt.owner.from_arcis(final_config),
             stake_ctxt.owner.from_arcis(final_stake),
+            Mxe::get().from_arcis(final_delta),
+            Mxe::get().from_arcis(final_subscriptions),
             public_principal,
+            public_forfeited,
             public_entry_count,
         )
     }
 
+    #[instruction]
+    pub fn claim_user_subly(
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        stake_ctxt: Enc<Mxe, UserStakeSecrets>,
+        input: ClaimInput,
+    ) -> (Enc<Mxe, ConfigSecrets>, Enc<Mxe, UserStakeSecrets>, u64) {
+        let mut config = config_ctxt.to_arcis();
+        let mut stake_state = stake_ctxt.to_arcis();
+
+        let now = input.now_ts as i64;
+        accrue_config(&mut config, now);
+        sync_entries(&mut stake_state, config.acc_index, now);
+
+        let payout = claim_yield(&mut config, &mut stake_state, input.amount, now, false);
+
+        (
+            config_ctxt.owner.from_arcis(config),
+            stake_ctxt.owner.from_arcis(stake_state),
+            payout.reveal(),
+        )
+    }
+
+    #[instruction]
+    pub fn claim_operator_subly(
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        stake_ctxt: Enc<Mxe, UserStakeSecrets>,
+        input: ClaimInput,
+    ) -> (Enc<Mxe, ConfigSecrets>, Enc<Mxe, UserStakeSecrets>, u64) {
+        let mut config = config_ctxt.to_arcis();
+        let mut stake_state = stake_ctxt.to_arcis();
+
+        let now = input.now_ts as i64;
+        accrue_config(&mut config, now);
+        sync_entries(&mut stake_state, config.acc_index, now);
+
+        let payout = claim_yield(&mut config, &mut stake_state, input.amount, now, true);
+
+        (
+            config_ctxt.owner.from_arcis(config),
+            stake_ctxt.owner.from_arcis(stake_state),
+            payout.reveal(),
+        )
+    }
+
+    #[instruction]
+    pub fn fund_rewards_subly(
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        input: FundRewardsInput,
+    ) -> Enc<Mxe, ConfigSecrets> {
+        let mut config = config_ctxt.to_arcis();
+        config.reward_pool += input.amount;
+        config_ctxt.owner.from_arcis(config)
+    }
+
+    #[instruction]
+    pub fn set_apy_subly(
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        input: SetApyInput,
+    ) -> Enc<Mxe, ConfigSecrets> {
+        let mut config = config_ctxt.to_arcis();
+        // Time elapsed so far still earns at the previous rate.
+        accrue_config(&mut config, input.now_ts as i64);
+        config.apy_bps = input.apy_bps;
+        config_ctxt.owner.from_arcis(config)
+    }
+
+    #[instruction]
+    pub fn set_paused_subly(
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        input: SetPausedInput,
+    ) -> (Enc<Mxe, ConfigSecrets>, bool) {
+        let mut config = config_ctxt.to_arcis();
+        config.paused = input.paused;
+        let paused = config.paused;
+        (config_ctxt.owner.from_arcis(config), paused.reveal())
+    }
+
+    #[instruction]
+    pub fn view_position_subly(
+        receiver: Shared,
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        stake_ctxt: Enc<Mxe, UserStakeSecrets>,
+        input: ViewPositionInput,
+    ) -> Enc<Shared, PositionSummary> {
+        let mut config = config_ctxt.to_arcis();
+        let mut stake_state = stake_ctxt.to_arcis();
+
+        let now = input.now_ts as i64;
+        accrue_config(&mut config, now);
+        sync_entries(&mut stake_state, config.acc_index, now);
+
+        let mut summary = PositionSummary {
+            total_principal: stake_state.total_principal,
+            unrealized_yield: 0,
+            claimable_yield: 0,
+            claimed_user: 0,
+            next_unlock_ts: 0,
+            entry_count: stake_state.entry_count,
+        };
+
+        for idx in 0..MAX_STAKE_ENTRIES {
+            let entry = stake_state.entries[idx];
+            summary.unrealized_yield += entry.unrealized_yield;
+            summary.claimed_user += entry.claimed_user;
+            if now >= entry.lock_end_ts {
+                summary.claimable_yield += entry.unrealized_yield;
+            }
+            let still_locked = entry.principal > 0 && entry.lock_end_ts > now;
+            let earlier = summary.next_unlock_ts == 0 || entry.lock_end_ts < summary.next_unlock_ts;
+            if still_locked && earlier {
+                summary.next_unlock_ts = entry.lock_end_ts;
+            }
+        }
+
+        receiver.from_arcis(summary)
+    }
+
+    #[instruction]
+    pub fn subscribe_subly(
+        request_ctxt: Enc<Shared, ServiceProof>,
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        stake_ctxt: Enc<Mxe, UserStakeSecrets>,
+        subscriptions_ctxt: Enc<Mxe, UserSubscriptionsSecrets>,
+        registry_ctxt: Enc<Mxe, RegistrySecrets>,
+        input: SubscriptionsInput,
+    ) -> (Enc<Mxe, UserSubscriptionsSecrets>, bool) {
+        let request = request_ctxt.to_arcis();
+        let config = config_ctxt.to_arcis();
+        let stake_state = stake_ctxt.to_arcis();
+        let subscriptions =
+            load_subscriptions(&subscriptions_ctxt, input.fresh, stake_state.total_principal);
+        let registry = registry_ctxt.to_arcis();
+
+        let price = request.monthly_price_usdc;
+        let root = service_root(
+            service_leaf(request.service_id, price),
+            request.service_id,
+            request.siblings_low,
+            request.siblings_high,
+        );
+        // An unused slot hashes to the empty node, so a zero price can
+        // never be proven.
+        let listed = request.service_id < MAX_SERVICES
+            && price > 0
+            && (price as u128) < SERVICE_FIELD_MODULUS
+            && root.low == registry.services_root_low
+            && root.high == registry.services_root_high;
+
+        let mut already_subscribed = false;
+        let mut free_index: usize = MAX_SUBSCRIPTIONS;
+        for idx in 0..MAX_SUBSCRIPTIONS {
+            let slot = subscriptions.slots[idx];
+            if slot.active && slot.service_id == request.service_id {
+                already_subscribed = true;
+            }
+            if !slot.active && free_index == MAX_SUBSCRIPTIONS {
+                free_index = idx;
+            }
+        }
+
+        let budget = monthly_budget(subscriptions.backing_principal, config.apy_bps);
+        let committed = subscriptions.committed_usdc + price;
+        let accepted = listed
+            && !already_subscribed
+            && free_index < MAX_SUBSCRIPTIONS
+            && committed <= budget;
+
+        let mut updated = subscriptions;
+        for idx in 0..MAX_SUBSCRIPTIONS {
+            if accepted && idx == free_index {
+                updated.slots[idx].service_id = request.service_id;
+                updated.slots[idx].monthly_price_usdc = price;
+                updated.slots[idx].active = true;
+            }
+        }
+        if accepted {
+            updated.committed_usdc = committed;
+        }
+
+        (
+            Mxe::get().from_arcis(updated),
+            accepted.reveal(),
+        )
+    }
+
+    #[instruction]
+    pub fn register_service_subly(
+        registry_ctxt: Enc<Mxe, RegistrySecrets>,
+        input: ServiceProof,
+    ) -> (Enc<Mxe, RegistrySecrets>, bool) {
+        let mut registry = registry_ctxt.to_arcis();
+
+        let empty = ServiceNode { low: 0, high: 0 };
+        let current_root = service_root(
+            empty,
+            input.service_id,
+            input.siblings_low,
+            input.siblings_high,
+        );
+        let new_root = service_root(
+            service_leaf(input.service_id, input.monthly_price_usdc),
+            input.service_id,
+            input.siblings_low,
+            input.siblings_high,
+        );
+
+        // The siblings must show the next slot is still empty under the
+        // current root, which also makes them valid for the new leaf.
+        let accepted = input.service_id == registry.next_service_id
+            && input.service_id < MAX_SERVICES
+            && input.monthly_price_usdc > 0
+            && (input.monthly_price_usdc as u128) < SERVICE_FIELD_MODULUS
+            && current_root.low == registry.services_root_low
+            && current_root.high == registry.services_root_high;
+
+        if accepted {
+            registry.services_root_low = new_root.low;
+            registry.services_root_high = new_root.high;
+            registry.next_service_id += 1;
+        }
+
+        (registry_ctxt.owner.from_arcis(registry), accepted.reveal())
+    }
+
+    #[instruction]
+    pub fn unsubscribe_subly(
+        request_ctxt: Enc<Shared, SubscriptionRequest>,
+        subscriptions_ctxt: Enc<Mxe, UserSubscriptionsSecrets>,
+        input: SubscriptionsInput,
+    ) -> (Enc<Mxe, UserSubscriptionsSecrets>, bool) {
+        let request = request_ctxt.to_arcis();
+        let mut subscriptions = load_subscriptions(&subscriptions_ctxt, input.fresh, 0);
+
+        let mut removed = false;
+        for idx in 0..MAX_SUBSCRIPTIONS {
+            let slot = subscriptions.slots[idx];
+            if slot.active && slot.service_id == request.service_id && !removed {
+                subscriptions.committed_usdc -= slot.monthly_price_usdc;
+                subscriptions.slots[idx].service_id = 0;
+                subscriptions.slots[idx].monthly_price_usdc = 0;
+                subscriptions.slots[idx].active = false;
+                removed = true;
+            }
+        }
+
+        (
+            Mxe::get().from_arcis(subscriptions),
+            removed.reveal(),
+        )
+    }
+
+    #[instruction]
+    pub fn settle_config_subly(
+        config_ctxt: Enc<Mxe, ConfigSecrets>,
+        delta_ctxt: Enc<Mxe, ConfigDelta>,
+        input: SettleInput,
+    ) -> (Enc<Mxe, ConfigSecrets>, Enc<Mxe, ConfigDelta>) {
+        let mut config = config_ctxt.to_arcis();
+        let delta = load_delta(&delta_ctxt, input.delta_fresh);
+
+        accrue_config(&mut config, input.now_ts as i64);
+        config.total_principal =
+            config.total_principal + delta.principal_added - delta.principal_removed;
+
+        let cleared = ConfigDelta {
+            principal_added: 0,
+            principal_removed: 0,
+        };
+
+        (
+            config_ctxt.owner.from_arcis(config),
+            Mxe::get().from_arcis(cleared),
+        )
+    }
+
     fn populate_entry(
         entry: &mut StakeEntrySecrets,
         tranche_id: u64,
@@ -262,10 +709,12 @@ mod circuits {
         entry.unrealized_yield = 0;
     }
 
+    // The index only depends on time and APY, so computations that accrue a
+    // private copy of the config agree with the one that is later settled.
     fn accrue_config(config: &mut ConfigSecrets, now: i64) {
         if now > config.last_update_ts {
             let elapsed = now - config.last_update_ts;
-            if elapsed > 0 && config.total_principal > 0 {
+            if elapsed > 0 {
                 let elapsed_u64 = elapsed as u64;
                 if elapsed_u64 > 0 {
                     let numerator = (config.apy_bps as u128) * (elapsed_u64 as u128) * INDEX_SCALE;
@@ -300,4 +749,219 @@ mod circuits {
         }
         stake_data.last_updated_ts = now;
     }
+
+    /// Moves up to `amount` (everything when zero) of unrealized yield out of
+    /// the entries, capped by the reward pool. Users may only take yield from
+    /// tranches whose lock has ended; the operator may take it at any time.
+    fn claim_yield(
+        config: &mut ConfigSecrets,
+        stake_data: &mut UserStakeSecrets,
+        amount: u64,
+        now: i64,
+        operator: bool,
+    ) -> u64 {
+        let mut available: u64 = 0;
+        for idx in 0..MAX_STAKE_ENTRIES {
+            let entry = stake_data.entries[idx];
+            if operator || now >= entry.lock_end_ts {
+                available += entry.unrealized_yield;
+            }
+        }
+
+        let mut payout = if amount == 0 || amount > available {
+            available
+        } else {
+            amount
+        };
+        if payout > config.reward_pool {
+            payout = config.reward_pool;
+        }
+
+        let mut remaining = payout;
+        for idx in 0..MAX_STAKE_ENTRIES {
+            let entry = &mut stake_data.entries[idx];
+            if operator || now >= entry.lock_end_ts {
+                let take = if remaining < entry.unrealized_yield {
+                    remaining
+                } else {
+                    entry.unrealized_yield
+                };
+                entry.unrealized_yield -= take;
+                if operator {
+                    entry.claimed_operator += take;
+                } else {
+                    entry.claimed_user += take;
+                }
+                remaining -= take;
+            }
+        }
+
+        config.reward_pool -= payout;
+        payout
+    }
+
+    // Each page starts its tranche ids at its own offset so ids stay unique
+    // across all of a user's pages.
+    fn load_stake(
+        stake_ctxt: &Enc<Mxe, UserStakeSecrets>,
+        fresh: bool,
+        page_index: u16,
+    ) -> UserStakeSecrets {
+        let stored = stake_ctxt.to_arcis();
+        let empty = UserStakeSecrets {
+            total_principal: 0,
+            last_updated_ts: 0,
+            next_tranche_id: (page_index as u64) * TRANCHE_IDS_PER_PAGE,
+            entry_count: 0,
+            entries: [StakeEntrySecrets {
+                tranche_id: 0,
+                principal: 0,
+                deposited_at: 0,
+                lock_end_ts: 0,
+                lock_duration: 0,
+                start_acc_index: 0,
+                last_acc_index: 0,
+                claimed_operator: 0,
+                claimed_user: 0,
+                unrealized_yield: 0,
+            }; MAX_STAKE_ENTRIES],
+        };
+        if fresh {
+            empty
+        } else {
+            stored
+        }
+    }
+
+    fn load_delta(delta_ctxt: &Enc<Mxe, ConfigDelta>, fresh: bool) -> ConfigDelta {
+        let stored = delta_ctxt.to_arcis();
+        let empty = ConfigDelta {
+            principal_added: 0,
+            principal_removed: 0,
+        };
+        if fresh {
+            empty
+        } else {
+            stored
+        }
+    }
+
+    // Subscriptions state is first written by a computation on the first
+    // stake page, so a fresh state starts from that page's principal.
+    fn load_subscriptions(
+        subscriptions_ctxt: &Enc<Mxe, UserSubscriptionsSecrets>,
+        fresh: bool,
+        first_page_principal: u64,
+    ) -> UserSubscriptionsSecrets {
+        let stored = subscriptions_ctxt.to_arcis();
+        let empty = UserSubscriptionsSecrets {
+            committed_usdc: 0,
+            backing_principal: first_page_principal,
+            slots: [SubscriptionSlot {
+                service_id: 0,
+                monthly_price_usdc: 0,
+                active: false,
+            }; MAX_SUBSCRIPTIONS],
+        };
+        if fresh {
+            empty
+        } else {
+            stored
+        }
+    }
+
+    fn service_leaf(service_id: u64, monthly_price_usdc: u64) -> ServiceNode {
+        compress_nodes(
+            ServiceNode {
+                low: service_id as u128,
+                high: monthly_price_usdc as u128,
+            },
+            ServiceNode {
+                low: SERVICE_LEAF_TAG,
+                high: 0,
+            },
+        )
+    }
+
+    fn service_root(
+        leaf: ServiceNode,
+        service_id: u64,
+        siblings_low: [u64; SERVICE_TREE_DEPTH],
+        siblings_high: [u64; SERVICE_TREE_DEPTH],
+    ) -> ServiceNode {
+        let mut node = leaf;
+        let mut position = service_id;
+        for level in 0..SERVICE_TREE_DEPTH {
+            let sibling = ServiceNode {
+                low: siblings_low[level] as u128,
+                high: siblings_high[level] as u128,
+            };
+            let is_right = position % 2 == 1;
+            let left = if is_right { sibling } else { node };
+            let right = if is_right { node } else { sibling };
+            node = compress_nodes(left, right);
+            position /= 2;
+        }
+        node
+    }
+
+    // Arcis has no hash primitive, so tree nodes use MiMC's Feistel network
+    // over GF(p), p = 2^61 - 1, widened to the four elements of both children
+    // and closed with a feed-forward. The round function is x^17, the
+    // smallest odd power that permutes the field (3, 5, 7, 11 and 13 all
+    // divide p - 1). Each round multiplies the degree by 17; the rounds are
+    // twice the 15 needed to reach degree p, plus 4 so every input element
+    // passes through the round function.
+    //
+    // A root is two elements, about 122 bits. Subscribing at a forged price
+    // needs a second preimage of the root the authority built, about 2^122
+    // work. Collisions cost about 2^61, but only whoever picks the leaves can
+    // use one, and only the authority registers services.
+    //
+    // Children are read modulo p. Two empty children compress to the empty
+    // node, which keeps the unused part of the tree, and the initial root, at
+    // zero.
+    fn compress_nodes(left: ServiceNode, right: ServiceNode) -> ServiceNode {
+        let left_low = left.low % SERVICE_FIELD_MODULUS;
+        let left_high = left.high % SERVICE_FIELD_MODULUS;
+        let right_low = right.low % SERVICE_FIELD_MODULUS;
+        let right_high = right.high % SERVICE_FIELD_MODULUS;
+        let mut a = left_low;
+        let mut b = left_high;
+        let mut c = right_low;
+        let mut d = right_high;
+        for round in 0..SERVICE_HASH_ROUNDS {
+            let round_constant =
+                ((round as u128) + 1) * SERVICE_HASH_ROUND_STEP % SERVICE_FIELD_MODULUS;
+            let shifted = (a + round_constant) % SERVICE_FIELD_MODULUS;
+            let pow2 = shifted * shifted % SERVICE_FIELD_MODULUS;
+            let pow4 = pow2 * pow2 % SERVICE_FIELD_MODULUS;
+            let pow8 = pow4 * pow4 % SERVICE_FIELD_MODULUS;
+            let pow16 = pow8 * pow8 % SERVICE_FIELD_MODULUS;
+            let pow17 = pow16 * shifted % SERVICE_FIELD_MODULUS;
+            let next = (b + pow17) % SERVICE_FIELD_MODULUS;
+            b = c;
+            c = d;
+            d = a;
+            a = next;
+        }
+
+        let hashed = ServiceNode {
+            low: (a + left_low + right_high) % SERVICE_FIELD_MODULUS,
+            high: (b + left_high + right_low) % SERVICE_FIELD_MODULUS,
+        };
+        let empty = ServiceNode { low: 0, high: 0 };
+        let both_empty = left_low == 0 && left_high == 0 && right_low == 0 && right_high == 0;
+        if both_empty {
+            empty
+        } else {
+            hashed
+        }
+    }
+
+    fn monthly_budget(total_principal: u64, apy_bps: u16) -> u64 {
+        let annual_yield =
+            (total_principal as u128) * (apy_bps as u128) / (BASIS_POINTS_DIVISOR as u128);
+        (annual_yield / 12) as u64
+    }
 }
@@ -1,24 +1,78 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::accounts::ComputationAccount;
 
 pub mod subly;
 
 pub use subly::error::ErrorCode;
+use subly::instructions::cancel_pending_computation::handler as cancel_pending_computation_handler;
+pub use subly::instructions::claim_operator::ClaimOperatorArgs;
+use subly::instructions::claim_operator::{
+    callback as claim_operator_callback_handler, handler as claim_operator_handler,
+};
+pub use subly::instructions::claim_user::ClaimUserArgs;
+use subly::instructions::claim_user::{
+    callback as claim_user_callback_handler, handler as claim_user_handler,
+};
+pub use subly::instructions::fund_rewards::FundRewardsArgs;
+use subly::instructions::fund_rewards::{
+    callback as fund_rewards_callback_handler, handler as fund_rewards_handler,
+};
 pub use subly::instructions::initialize::InitializeArgs;
 use subly::instructions::initialize::{
     callback as initialize_callback_handler, handler as initialize_handler,
 };
+use subly::instructions::migrate_config::handler as migrate_config_handler;
+use subly::instructions::migrate_user_stake::handler as migrate_user_stake_handler;
+pub use subly::instructions::register_subscription_service::RegisterSubscriptionServiceArgs;
+use subly::instructions::register_subscription_service::{
+    callback as register_subscription_service_callback_handler,
+    handler as register_subscription_service_handler,
+};
+pub use subly::instructions::set_apy::SetApyArgs;
+use subly::instructions::set_apy::{
+    callback as set_apy_callback_handler, handler as set_apy_handler,
+};
+pub use subly::instructions::set_paused::SetPausedArgs;
+use subly::instructions::set_paused::{
+    callback as set_paused_callback_handler, handler as set_paused_handler,
+};
+use subly::instructions::settle_config::{
+    callback as settle_config_callback_handler, handler as settle_config_handler,
+};
 pub use subly::instructions::stake::StakeArgs;
 use subly::instructions::stake::{callback as stake_callback_handler, handler as stake_handler};
+pub use subly::instructions::subscribe::SubscribeArgs;
+use subly::instructions::subscribe::{
+    callback as subscribe_callback_handler, handler as subscribe_handler,
+};
 pub use subly::instructions::unstake::UnstakeArgs;
 use subly::instructions::unstake::{
     callback as unstake_callback_handler, handler as unstake_handler,
 };
+pub use subly::instructions::unsubscribe::UnsubscribeArgs;
+use subly::instructions::unsubscribe::{
+    callback as unsubscribe_callback_handler, handler as unsubscribe_handler,
+};
+pub use subly::instructions::view_position::ViewPositionArgs;
+use subly::instructions::view_position::{
+    callback as view_position_callback_handler, handler as view_position_handler,
+};
 
 pub const COMP_DEF_OFFSET_INITIALIZE_SUBLY: u32 = comp_def_offset("initialize_subly");
 pub const COMP_DEF_OFFSET_STAKE_SUBLY: u32 = comp_def_offset("stake_subly");
 pub const COMP_DEF_OFFSET_UNSTAKE_SUBLY: u32 = comp_def_offset("unstake_subly");
+pub const COMP_DEF_OFFSET_CLAIM_USER_SUBLY: u32 = comp_def_offset("claim_user_subly");
+pub const COMP_DEF_OFFSET_CLAIM_OPERATOR_SUBLY: u32 = comp_def_offset("claim_operator_subly");
+pub const COMP_DEF_OFFSET_FUND_REWARDS_SUBLY: u32 = comp_def_offset("fund_rewards_subly");
+pub const COMP_DEF_OFFSET_VIEW_POSITION_SUBLY: u32 = comp_def_offset("view_position_subly");
+pub const COMP_DEF_OFFSET_SUBSCRIBE_SUBLY: u32 = comp_def_offset("subscribe_subly");
+pub const COMP_DEF_OFFSET_UNSUBSCRIBE_SUBLY: u32 = comp_def_offset("unsubscribe_subly");
+pub const COMP_DEF_OFFSET_SETTLE_CONFIG_SUBLY: u32 = comp_def_offset("settle_config_subly");
+pub const COMP_DEF_OFFSET_SET_APY_SUBLY: u32 = comp_def_offset("set_apy_subly");
+pub const COMP_DEF_OFFSET_SET_PAUSED_SUBLY: u32 = comp_def_offset("set_paused_subly");
+pub const COMP_DEF_OFFSET_REGISTER_SERVICE_SUBLY: u32 = comp_def_offset("register_service_subly");
 
 declare_id!("EmWktRzQQNanVhAjpHod1UGVkKqJhpJVKxy5V1sc8gFw");
 
@@ -50,11 +104,7 @@ pub mod subly_arcium {
         init_comp_def(ctx.accounts, true, 0, None, None)
     }
 
-    pub fn stake(
-        ctx: Context<Stake>,
-        computation_offset: u64,
-        args: StakeArgs,
-    ) -> Result<()> {
+    pub fn stake(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) -> Result<()> {
         stake_handler(ctx, computation_offset, args)
     }
 
@@ -85,6 +135,217 @@ pub mod subly_arcium {
     ) -> Result<()> {
         unstake_callback_handler(ctx, output)
     }
+
+    pub fn init_claim_user_subly_comp_def(ctx: Context<InitClaimUserCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn claim_user(
+        ctx: Context<ClaimUser>,
+        computation_offset: u64,
+        args: ClaimUserArgs,
+    ) -> Result<()> {
+        claim_user_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "claim_user_subly")]
+    pub fn claim_user_subly_callback(
+        ctx: Context<ClaimUserSublyCallback>,
+        output: ComputationOutputs<crate::ClaimUserSublyOutput>,
+    ) -> Result<()> {
+        claim_user_callback_handler(ctx, output)
+    }
+
+    pub fn init_claim_operator_subly_comp_def(
+        ctx: Context<InitClaimOperatorCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn claim_operator(
+        ctx: Context<ClaimOperator>,
+        computation_offset: u64,
+        args: ClaimOperatorArgs,
+    ) -> Result<()> {
+        claim_operator_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "claim_operator_subly")]
+    pub fn claim_operator_subly_callback(
+        ctx: Context<ClaimOperatorSublyCallback>,
+        output: ComputationOutputs<crate::ClaimOperatorSublyOutput>,
+    ) -> Result<()> {
+        claim_operator_callback_handler(ctx, output)
+    }
+
+    pub fn init_fund_rewards_subly_comp_def(ctx: Context<InitFundRewardsCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn fund_rewards(
+        ctx: Context<FundRewards>,
+        computation_offset: u64,
+        args: FundRewardsArgs,
+    ) -> Result<()> {
+        fund_rewards_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "fund_rewards_subly")]
+    pub fn fund_rewards_subly_callback(
+        ctx: Context<FundRewardsSublyCallback>,
+        output: ComputationOutputs<crate::FundRewardsSublyOutput>,
+    ) -> Result<()> {
+        fund_rewards_callback_handler(ctx, output)
+    }
+
+    pub fn init_view_position_subly_comp_def(ctx: Context<InitViewPositionCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn view_position(
+        ctx: Context<ViewPosition>,
+        computation_offset: u64,
+        args: ViewPositionArgs,
+    ) -> Result<()> {
+        view_position_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "view_position_subly")]
+    pub fn view_position_subly_callback(
+        ctx: Context<ViewPositionSublyCallback>,
+        output: ComputationOutputs<crate::ViewPositionSublyOutput>,
+    ) -> Result<()> {
+        view_position_callback_handler(ctx, output)
+    }
+
+    pub fn init_register_service_subly_comp_def(
+        ctx: Context<InitRegisterServiceCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn register_subscription_service(
+        ctx: Context<RegisterSubscriptionService>,
+        computation_offset: u64,
+        args: RegisterSubscriptionServiceArgs,
+    ) -> Result<()> {
+        register_subscription_service_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "register_service_subly")]
+    pub fn register_service_subly_callback(
+        ctx: Context<RegisterServiceSublyCallback>,
+        output: ComputationOutputs<crate::RegisterServiceSublyOutput>,
+    ) -> Result<()> {
+        register_subscription_service_callback_handler(ctx, output)
+    }
+
+    pub fn init_subscribe_subly_comp_def(ctx: Context<InitSubscribeCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn subscribe(
+        ctx: Context<Subscribe>,
+        computation_offset: u64,
+        args: SubscribeArgs,
+    ) -> Result<()> {
+        subscribe_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "subscribe_subly")]
+    pub fn subscribe_subly_callback(
+        ctx: Context<SubscribeSublyCallback>,
+        output: ComputationOutputs<crate::SubscribeSublyOutput>,
+    ) -> Result<()> {
+        subscribe_callback_handler(ctx, output)
+    }
+
+    pub fn init_unsubscribe_subly_comp_def(ctx: Context<InitUnsubscribeCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn unsubscribe(
+        ctx: Context<Unsubscribe>,
+        computation_offset: u64,
+        args: UnsubscribeArgs,
+    ) -> Result<()> {
+        unsubscribe_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "unsubscribe_subly")]
+    pub fn unsubscribe_subly_callback(
+        ctx: Context<UnsubscribeSublyCallback>,
+        output: ComputationOutputs<crate::UnsubscribeSublyOutput>,
+    ) -> Result<()> {
+        unsubscribe_callback_handler(ctx, output)
+    }
+
+    pub fn cancel_pending_computation(
+        ctx: Context<CancelPendingComputation>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        cancel_pending_computation_handler(ctx, computation_offset)
+    }
+
+    pub fn init_settle_config_subly_comp_def(ctx: Context<InitSettleConfigCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn settle_config(ctx: Context<SettleConfig>, computation_offset: u64) -> Result<()> {
+        settle_config_handler(ctx, computation_offset)
+    }
+
+    #[arcium_callback(encrypted_ix = "settle_config_subly")]
+    pub fn settle_config_subly_callback(
+        ctx: Context<SettleConfigSublyCallback>,
+        output: ComputationOutputs<crate::SettleConfigSublyOutput>,
+    ) -> Result<()> {
+        settle_config_callback_handler(ctx, output)
+    }
+
+    pub fn init_set_apy_subly_comp_def(ctx: Context<InitSetApyCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn set_apy(ctx: Context<SetApy>, computation_offset: u64, args: SetApyArgs) -> Result<()> {
+        set_apy_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "set_apy_subly")]
+    pub fn set_apy_subly_callback(
+        ctx: Context<SetApySublyCallback>,
+        output: ComputationOutputs<crate::SetApySublyOutput>,
+    ) -> Result<()> {
+        set_apy_callback_handler(ctx, output)
+    }
+
+    pub fn init_set_paused_subly_comp_def(ctx: Context<InitSetPausedCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)
+    }
+
+    pub fn set_paused(
+        ctx: Context<SetPaused>,
+        computation_offset: u64,
+        args: SetPausedArgs,
+    ) -> Result<()> {
+        set_paused_handler(ctx, computation_offset, args)
+    }
+
+    #[arcium_callback(encrypted_ix = "set_paused_subly")]
+    pub fn set_paused_subly_callback(
+        ctx: Context<SetPausedSublyCallback>,
+        output: ComputationOutputs<crate::SetPausedSublyOutput>,
+    ) -> Result<()> {
+        set_paused_callback_handler(ctx, output)
+    }
+
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        migrate_config_handler(ctx)
+    }
+
+    pub fn migrate_user_stake(ctx: Context<MigrateUserStake>) -> Result<()> {
+        migrate_user_stake_handler(ctx)
+    }
 }
 
 #[queue_computation_accounts("initialize_subly", payer)]
@@ -197,7 +458,7 @@ pub struct InitInitializeCompDef<'info> {
 
 #[queue_computation_accounts("stake_subly", payer)]
 #[derive(Accounts)]
-#[instruction(computation_offset: u64)]
+#[instruction(computation_offset: u64, args: StakeArgs)]
 pub struct Stake<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -213,10 +474,29 @@ pub struct Stake<'info> {
         init_if_needed,
         payer = user,
         space = subly::state::UserStakeAccount::LEN,
-        seeds = [subly::constants::USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user.key().as_ref(),
+            &subly::state::UserStakeAccount::page_seed(args.page_index),
+        ],
         bump,
     )]
     pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    /// Page 0 of the position; required when opening a later page.
+    #[account(
+        mut,
+        seeds = [subly::constants::USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
+        bump = first_page.bump,
+    )]
+    pub first_page: Option<Account<'info, subly::state::UserStakeAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = subly::state::UserSubscriptionsAccount::LEN,
+        seeds = [subly::constants::USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
     #[account(
         mut,
         constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
@@ -280,6 +560,22 @@ pub struct StakeSublyCallback<'info> {
     pub config: Account<'info, subly::state::SublyConfig>,
     #[account(mut)]
     pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(mut)]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user_stake.owner @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
 }
 
 impl<'info> Discriminator for StakeSublyCallback<'info> {
@@ -316,11 +612,23 @@ pub struct Unstake<'info> {
     pub user: Signer<'info>,
     #[account(
         mut,
-        seeds = [subly::constants::USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user.key().as_ref(),
+            &subly::state::UserStakeAccount::page_seed(user_stake.page_index),
+        ],
         bump = user_stake.bump,
         constraint = user_stake.owner == user.key() @ ErrorCode::InvalidPositionOwner,
     )]
     pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = subly::state::UserSubscriptionsAccount::LEN,
+        seeds = [subly::constants::USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
     #[account(
         mut,
         seeds = [subly::constants::VAULT_SEED.as_bytes()],
@@ -384,6 +692,8 @@ pub struct UnstakeSublyCallback<'info> {
     pub config: Account<'info, subly::state::SublyConfig>,
     #[account(mut)]
     pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(mut)]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
     #[account(
         mut,
         seeds = [subly::constants::VAULT_SEED.as_bytes()],
@@ -417,3 +727,1042 @@ pub struct InitUnstakeCompDef<'info> {
     pub arcium_program: Program<'info, Arcium>,
     pub system_program: Program<'info, System>,
 }
+
+#[queue_computation_accounts("claim_user_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ClaimUser<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user.key().as_ref(),
+            &subly::state::UserStakeAccount::page_seed(user_stake.page_index),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_USER_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("claim_user_subly")]
+#[derive(Accounts)]
+pub struct ClaimUserSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_USER_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(mut)]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user_stake.owner @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Discriminator for ClaimUserSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBCLMUS";
+}
+
+#[init_computation_definition_accounts("claim_user_subly", payer)]
+#[derive(Accounts)]
+pub struct InitClaimUserCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("claim_operator_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ClaimOperator<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &subly::state::UserStakeAccount::page_seed(user_stake.page_index),
+        ],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = authority_token_account.owner == authority.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = authority_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_OPERATOR_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("claim_operator_subly")]
+#[derive(Accounts)]
+pub struct ClaimOperatorSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_OPERATOR_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(mut)]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = authority_token_account.owner == config.authority @ ErrorCode::InvalidTokenOwner,
+        constraint = authority_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Discriminator for ClaimOperatorSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBCLMOP";
+}
+
+#[init_computation_definition_accounts("claim_operator_subly", payer)]
+#[derive(Accounts)]
+pub struct InitClaimOperatorCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("fund_rewards_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct FundRewards<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    pub funder: Signer<'info>,
+    #[account(
+        mut,
+        constraint = funder_token_account.owner == funder.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = funder_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_FUND_REWARDS_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("fund_rewards_subly")]
+#[derive(Accounts)]
+pub struct FundRewardsSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_FUND_REWARDS_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = funder_token_account.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Discriminator for FundRewardsSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBFUNDR";
+}
+
+#[init_computation_definition_accounts("fund_rewards_subly", payer)]
+#[derive(Accounts)]
+pub struct InitFundRewardsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("view_position_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ViewPosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user.key().as_ref(),
+            &subly::state::UserStakeAccount::page_seed(user_stake.page_index),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_VIEW_POSITION_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("view_position_subly")]
+#[derive(Accounts)]
+pub struct ViewPositionSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_VIEW_POSITION_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(mut)]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+}
+
+impl<'info> Discriminator for ViewPositionSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBVIEWP";
+}
+
+#[init_computation_definition_accounts("view_position_subly", payer)]
+#[derive(Accounts)]
+pub struct InitViewPositionCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("register_service_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RegisterSubscriptionService<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, subly::state::SubscriptionRegistry>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REGISTER_SERVICE_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("register_service_subly")]
+#[derive(Accounts)]
+pub struct RegisterServiceSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REGISTER_SERVICE_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, subly::state::SubscriptionRegistry>,
+}
+
+impl<'info> Discriminator for RegisterServiceSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBREGSV";
+}
+
+#[init_computation_definition_accounts("register_service_subly", payer)]
+#[derive(Accounts)]
+pub struct InitRegisterServiceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("subscribe_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct Subscribe<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user.key().as_ref(),
+            &subly::state::UserStakeAccount::page_seed(user_stake.page_index),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = subly::state::UserSubscriptionsAccount::LEN,
+        seeds = [subly::constants::USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
+    #[account(
+        mut,
+        seeds = [subly::constants::SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, subly::state::SubscriptionRegistry>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBSCRIBE_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("subscribe_subly")]
+#[derive(Accounts)]
+pub struct SubscribeSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBSCRIBE_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(
+        mut,
+        seeds = [subly::constants::SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Account<'info, subly::state::SubscriptionRegistry>,
+    #[account(mut)]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(mut)]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
+}
+
+impl<'info> Discriminator for SubscribeSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBSUBCB";
+}
+
+#[init_computation_definition_accounts("subscribe_subly", payer)]
+#[derive(Accounts)]
+pub struct InitSubscribeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("unsubscribe_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct Unsubscribe<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::USER_SUBSCRIPTIONS_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_subscriptions.bump,
+        constraint = user_subscriptions.owner == user.key() @ ErrorCode::InvalidSubscriptionAccount,
+    )]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_UNSUBSCRIBE_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("unsubscribe_subly")]
+#[derive(Accounts)]
+pub struct UnsubscribeSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_UNSUBSCRIBE_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub user_subscriptions: Account<'info, subly::state::UserSubscriptionsAccount>,
+}
+
+impl<'info> Discriminator for UnsubscribeSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBUNSCB";
+}
+
+#[init_computation_definition_accounts("unsubscribe_subly", payer)]
+#[derive(Accounts)]
+pub struct InitUnsubscribeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CancelPendingComputation<'info> {
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(address = derive_comp_pda!(computation_offset))]
+    pub computation_account: Box<Account<'info, ComputationAccount>>,
+    #[account(mut)]
+    pub user_stake: Option<Account<'info, subly::state::UserStakeAccount>>,
+    #[account(mut)]
+    pub user_subscriptions: Option<Account<'info, subly::state::UserSubscriptionsAccount>>,
+    #[account(
+        mut,
+        seeds = [subly::constants::SUBSCRIPTION_REGISTRY_SEED.as_bytes()],
+        bump = subscription_registry.bump,
+    )]
+    pub subscription_registry: Option<Account<'info, subly::state::SubscriptionRegistry>>,
+    #[account(
+        mut,
+        seeds = [subly::constants::VAULT_SEED.as_bytes()],
+        bump = config.vault_bump,
+        constraint = vault.mint == config.usdc_mint @ ErrorCode::InvalidMint,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    /// Receives the deposit of a cancelled stake or `fund_rewards`.
+    #[account(mut)]
+    pub refund_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[queue_computation_accounts("settle_config_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SettleConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(
+        mut,
+        seeds = [
+            subly::constants::USER_POSITION_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &subly::state::UserStakeAccount::page_seed(user_stake.page_index),
+        ],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SETTLE_CONFIG_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("settle_config_subly")]
+#[derive(Accounts)]
+pub struct SettleConfigSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SETTLE_CONFIG_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    #[account(mut)]
+    pub user_stake: Account<'info, subly::state::UserStakeAccount>,
+}
+
+impl<'info> Discriminator for SettleConfigSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBSETCB";
+}
+
+#[init_computation_definition_accounts("settle_config_subly", payer)]
+#[derive(Accounts)]
+pub struct InitSettleConfigCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("set_apy_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SetApy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_APY_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("set_apy_subly")]
+#[derive(Accounts)]
+pub struct SetApySublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_APY_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+}
+
+impl<'info> Discriminator for SetApySublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBSETAP";
+}
+
+#[init_computation_definition_accounts("set_apy_subly", payer)]
+#[derive(Accounts)]
+pub struct InitSetApyCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("set_paused_subly", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SetPaused<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: Verified by the Arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: Verified by the Arcium program
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_PAUSED_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("set_paused_subly")]
+#[derive(Accounts)]
+pub struct SetPausedSublyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_PAUSED_SUBLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instruction sysvar verified by address
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, subly::state::SublyConfig>,
+}
+
+impl<'info> Discriminator for SetPausedSublyCallback<'info> {
+    const DISCRIMINATOR: &'static [u8] = b"SUBSETPS";
+}
+
+#[init_computation_definition_accounts("set_paused_subly", payer)]
+#[derive(Accounts)]
+pub struct InitSetPausedCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: Initialized by the Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Legacy configs do not deserialize as `SublyConfig`; the handler
+    /// checks the owner program, discriminator and authority.
+    #[account(
+        mut,
+        seeds = [subly::constants::CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub config: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserStake<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Only used to derive the position PDA.
+    pub user: UncheckedAccount<'info>,
+    /// CHECK: Legacy positions do not deserialize as `UserStakeAccount`; the
+    /// handler checks the owner program, discriminator and owner. Positions
+    /// predating stake pages all live at the page 0 address.
+    #[account(
+        mut,
+        seeds = [subly::constants::USER_POSITION_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stake: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
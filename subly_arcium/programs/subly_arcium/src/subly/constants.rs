@@ -20,6 +20,16 @@ pub const SECONDS_PER_DAY: i64 = 86_400;
 
 pub const MAX_STAKE_ENTRIES: usize = 16;
 
+pub const SERVICE_TREE_DEPTH: usize = 8;
+
+pub const MAX_SERVICES: usize = 1 << SERVICE_TREE_DEPTH;
+
+/// Service prices are leaves of the service tree and must be elements of
+/// its field, GF(2^61 - 1).
+pub const SERVICE_FIELD_MODULUS: u64 = (1 << 61) - 1;
+
+pub const MAX_SUBSCRIPTIONS: usize = 8;
+
 pub const LOCK_OPTIONS: [i64; 4] = [
     30 * SECONDS_PER_DAY,
     90 * SECONDS_PER_DAY,
@@ -44,3 +54,13 @@ pub const MAX_SERVICE_PROVIDER_LEN: usize = 128;
 pub const BILLING_PERIOD_SECONDS: i64 = 30 * SECONDS_PER_DAY;
 
 pub const MAX_PAYPAL_RECEIVER_LEN: usize = 256;
+
+/// Slots a queued computation may wait before it can be cancelled. The
+/// `localnet` build lets tests cancel in the slot the computation was queued.
+#[cfg(not(feature = "localnet"))]
+pub const COMPUTATION_TIMEOUT_SLOTS: u64 = 9_000;
+#[cfg(feature = "localnet")]
+pub const COMPUTATION_TIMEOUT_SLOTS: u64 = 0;
+
+/// Slots a waiting config writer may hold new readers back.
+pub const WRITER_WAIT_SLOTS: u64 = 750;
@@ -62,4 +62,20 @@ pub enum ErrorCode {
     ComputationValidationFailed,
     #[msg("Cluster not set")]
     ClusterNotSet,
+    #[msg("The service catalogue is full")]
+    ServiceCatalogFull,
+    #[msg("The computation is still queued with Arcium and has not expired")]
+    ComputationStillQueued,
+    #[msg("Stake pages must be opened in order")]
+    InvalidStakePage,
+    #[msg("APY must not exceed 100%")]
+    InvalidApy,
+    #[msg("The service proof does not match the services root")]
+    InvalidServiceProof,
+    #[msg("A config writer is waiting for queued readers to finish")]
+    ConfigWriterWaiting,
+    #[msg("Account data does not match a known layout")]
+    UnrecognizedAccountLayout,
+    #[msg("A service registration is waiting for queued subscriptions to finish")]
+    RegistryWriterWaiting,
 }
@@ -0,0 +1,214 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use arcium_client::idl::arcium::types::ComputationStatus;
+
+use crate::subly::constants::{
+    COMPUTATION_TIMEOUT_SLOTS, CONFIG_SEED, USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED,
+};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::UserStakeAccount;
+use crate::{
+    CancelPendingComputation, COMP_DEF_OFFSET_FUND_REWARDS_SUBLY, COMP_DEF_OFFSET_STAKE_SUBLY,
+    COMP_DEF_OFFSET_SUBSCRIBE_SUBLY, COMP_DEF_OFFSET_UNSTAKE_SUBLY,
+    COMP_DEF_OFFSET_VIEW_POSITION_SUBLY, ID,
+};
+
+/// Computations that hold a reader slot on the config instead of its lock.
+const CONFIG_READER_COMP_DEFS: [u32; 4] = [
+    COMP_DEF_OFFSET_STAKE_SUBLY,
+    COMP_DEF_OFFSET_UNSTAKE_SUBLY,
+    COMP_DEF_OFFSET_VIEW_POSITION_SUBLY,
+    COMP_DEF_OFFSET_SUBSCRIBE_SUBLY,
+];
+
+#[event]
+pub struct PendingComputationCancelled {
+    pub computation_offset: u64,
+    pub refunded: u64,
+}
+
+/// Releases the locks held by a computation whose callback never landed. The
+/// computation must either have left the queue without its callback clearing
+/// the offsets, or have stayed queued for `COMPUTATION_TIMEOUT_SLOTS`. A late
+/// callback for this offset is rejected once the locks are released.
+pub fn handler(ctx: Context<CancelPendingComputation>, computation_offset: u64) -> Result<()> {
+    let computation = &ctx.accounts.computation_account;
+    let current_slot = Clock::get()?.slot;
+    let queued = matches!(computation.status, ComputationStatus::Queued);
+    let expired = computation
+        .slot
+        .checked_add(COMPUTATION_TIMEOUT_SLOTS)
+        .is_some_and(|deadline| deadline <= current_slot);
+    require!(!queued || expired, ErrorCode::ComputationStillQueued);
+
+    let mut released = false;
+    let mut stake_released = false;
+    let mut config_released = false;
+
+    let config = &mut ctx.accounts.config;
+    if config.pending_config_offset == Some(computation_offset) {
+        config.pending_config_offset = None;
+        released = true;
+        config_released = true;
+    }
+
+    if let Some(user_stake) = ctx.accounts.user_stake.as_mut() {
+        let expected_user_pda = Pubkey::create_program_address(
+            &[
+                USER_POSITION_SEED.as_bytes(),
+                user_stake.owner.as_ref(),
+                &UserStakeAccount::page_seed(user_stake.page_index),
+                &[user_stake.bump],
+            ],
+            &ID,
+        )
+        .map_err(|_| ErrorCode::InvalidPositionOwner)?;
+        require_keys_eq!(
+            expected_user_pda,
+            user_stake.key(),
+            ErrorCode::InvalidPositionOwner
+        );
+        if user_stake.pending_computation_offset == Some(computation_offset) {
+            user_stake.pending_computation_offset = None;
+            released = true;
+            stake_released = true;
+        }
+    }
+
+    if let Some(user_subscriptions) = ctx.accounts.user_subscriptions.as_mut() {
+        let expected_subscriptions_pda = Pubkey::create_program_address(
+            &[
+                USER_SUBSCRIPTIONS_SEED.as_bytes(),
+                user_subscriptions.owner.as_ref(),
+                &[user_subscriptions.bump],
+            ],
+            &ID,
+        )
+        .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+        require_keys_eq!(
+            expected_subscriptions_pda,
+            user_subscriptions.key(),
+            ErrorCode::InvalidSubscriptionAccount
+        );
+        if user_subscriptions.pending_computation_offset == Some(computation_offset) {
+            user_subscriptions.pending_computation_offset = None;
+            released = true;
+        }
+    }
+
+    if let Some(registry) = ctx.accounts.subscription_registry.as_mut() {
+        if registry.pending_registry_offset == Some(computation_offset) {
+            registry.pending_registry_offset = None;
+            registry.pending_service_price = 0;
+            released = true;
+        }
+    }
+
+    require!(released, ErrorCode::PendingComputationMismatch);
+
+    // Readers are tracked by count only, so the released position lock is
+    // what identifies this computation as one of them.
+    if stake_released
+        && CONFIG_READER_COMP_DEFS.contains(&computation.computation_definition_offset)
+    {
+        config.pending_reader_count = config
+            .pending_reader_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::PendingComputationMismatch)?;
+    }
+    if stake_released
+        && computation.computation_definition_offset == COMP_DEF_OFFSET_SUBSCRIBE_SUBLY
+    {
+        let registry = ctx
+            .accounts
+            .subscription_registry
+            .as_mut()
+            .ok_or(ErrorCode::PendingComputationMismatch)?;
+        registry.pending_reader_count = registry
+            .pending_reader_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::PendingComputationMismatch)?;
+    }
+
+    // Stakes and `fund_rewards` move the deposit into the vault before
+    // queuing; without the callback it was never recorded, so it goes back.
+    // The refund is tied to releasing the lock so it is paid only once.
+    let refund = match computation.computation_definition_offset {
+        COMP_DEF_OFFSET_STAKE_SUBLY => {
+            require!(stake_released, ErrorCode::PendingComputationMismatch);
+            let user_stake = ctx
+                .accounts
+                .user_stake
+                .as_mut()
+                .ok_or(ErrorCode::InvalidPositionOwner)?;
+            let refund_token_account = ctx
+                .accounts
+                .refund_token_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidTokenOwner)?;
+            require_keys_eq!(
+                refund_token_account.owner,
+                user_stake.owner,
+                ErrorCode::InvalidTokenOwner
+            );
+            Some(std::mem::take(&mut user_stake.pending_stake_amount))
+        }
+        COMP_DEF_OFFSET_FUND_REWARDS_SUBLY => {
+            require!(config_released, ErrorCode::PendingComputationMismatch);
+            let refund_token_account = ctx
+                .accounts
+                .refund_token_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidTokenOwner)?;
+            require_keys_eq!(
+                refund_token_account.key(),
+                std::mem::take(&mut config.pending_reward_refund),
+                ErrorCode::InvalidTokenOwner
+            );
+            Some(std::mem::take(&mut config.pending_reward_deposit))
+        }
+        _ => None,
+    };
+
+    let mut refunded = 0;
+    if let Some(amount) = refund {
+        let refund_token_account = ctx
+            .accounts
+            .refund_token_account
+            .as_ref()
+            .ok_or(ErrorCode::InvalidTokenOwner)?;
+        require_keys_eq!(
+            refund_token_account.mint,
+            config.usdc_mint,
+            ErrorCode::InvalidMint
+        );
+
+        let config_seed = CONFIG_SEED.as_bytes();
+        let bump_bytes = [config.bump];
+        let signer_seeds_slice: &[&[u8]] = &[config_seed, &bump_bytes];
+        let signer_seeds = &[signer_seeds_slice];
+
+        let transfer_accounts = Transfer {
+            from: ctx.accounts.vault.to_account_info(),
+            to: refund_token_account.to_account_info(),
+            authority: config.to_account_info(),
+        };
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            amount,
+        )?;
+        refunded = amount;
+    }
+
+    emit!(PendingComputationCancelled {
+        computation_offset,
+        refunded,
+    });
+
+    Ok(())
+}
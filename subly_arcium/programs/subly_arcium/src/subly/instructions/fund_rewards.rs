@@ -0,0 +1,162 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::CONFIG_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig,
+};
+use crate::{FundRewards, FundRewardsSublyCallback, FundRewardsSublyOutput};
+
+const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
+const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FundRewardsArgs {
+    pub amount: u64,
+}
+
+pub fn handler(
+    ctx: Context<FundRewards>,
+    computation_offset: u64,
+    args: FundRewardsArgs,
+) -> Result<()> {
+    require!(args.amount > 0, ErrorCode::AmountTooSmall);
+
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+    require!(
+        ctx.accounts.config.pending_config_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+
+    if !ctx.accounts.config.admit_writer(false, Clock::get()?.slot) {
+        return Ok(());
+    }
+
+    let config_nonce = ctx.accounts.config.encrypted_state.nonce;
+    let config_key = ctx.accounts.config.key();
+    let vault_key = ctx.accounts.vault.key();
+    let funder_token_key = ctx.accounts.funder_token_account.key();
+    let token_program_key = ctx.accounts.token_program.key();
+
+    let transfer_accounts = Transfer {
+        from: ctx.accounts.funder_token_account.to_account_info(),
+        to: ctx.accounts.vault.to_account_info(),
+        authority: ctx.accounts.funder.to_account_info(),
+    };
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+        ),
+        args.amount,
+    )?;
+
+    let arguments = vec![
+        Argument::PlaintextU128(config_nonce),
+        Argument::Account(config_key, CONFIG_CIPHERTEXT_OFFSET, CONFIG_CIPHERTEXT_LEN),
+        Argument::PlaintextU64(args.amount),
+    ];
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    let callback_accounts = [
+        CallbackAccount {
+            pubkey: config_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: vault_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: funder_token_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: token_program_key,
+            is_writable: false,
+        },
+    ];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![FundRewardsSublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    let config = &mut ctx.accounts.config;
+    config.pending_config_offset = Some(computation_offset);
+    config.pending_reward_deposit = args.amount;
+    config.pending_reward_refund = funder_token_key;
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<FundRewardsSublyCallback>,
+    output: ComputationOutputs<FundRewardsSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let config = &mut ctx.accounts.config;
+
+    release_pending(&mut config.pending_config_offset, computation_offset)?;
+    let deposit = std::mem::take(&mut config.pending_reward_deposit);
+    let refund_account = std::mem::take(&mut config.pending_reward_refund);
+
+    let previous_config_cipher = config.encrypted_state.clone();
+
+    // A failed computation never added the deposit to the pool, so it goes
+    // back to the funder.
+    let FundRewardsSublyOutput {
+        field_0: config_cipher,
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => {
+            require_keys_eq!(
+                ctx.accounts.funder_token_account.key(),
+                refund_account,
+                ErrorCode::InvalidTokenOwner
+            );
+
+            let config_seed = CONFIG_SEED.as_bytes();
+            let bump_bytes = [config.bump];
+            let signer_seeds_slice: &[&[u8]] = &[config_seed, &bump_bytes];
+            let signer_seeds = &[signer_seeds_slice];
+
+            let transfer_accounts = Transfer {
+                from: ctx.accounts.vault.to_account_info(),
+                to: ctx.accounts.funder_token_account.to_account_info(),
+                authority: config.to_account_info(),
+            };
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    transfer_accounts,
+                    signer_seeds,
+                ),
+                deposit,
+            )?;
+
+            return Ok(());
+        }
+    };
+
+    if previous_config_cipher.nonce == config_cipher.nonce
+        && previous_config_cipher.ciphertexts == config_cipher.ciphertexts
+    {
+        return Err(ErrorCode::ComputationValidationFailed.into());
+    }
+
+    config.encrypted_state = EncryptedState::from(config_cipher);
+
+    Ok(())
+}
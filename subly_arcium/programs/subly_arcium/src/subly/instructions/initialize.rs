@@ -4,11 +4,13 @@ use arcium_client::idl::arcium::types::CallbackAccount;
 
 use crate::subly::{
     error::ErrorCode,
-    state::{EncryptedState, SublyConfig, SubscriptionRegistry},
+    state::{
+        callback_computation_offset, release_pending, EncryptedState, SublyConfig,
+        SubscriptionRegistry,
+    },
 };
 use crate::{
-    Initialize, InitializeSublyCallback, InitializeSublyOutput,
-    InitializeSublyOutputStruct0,
+    Initialize, InitializeSublyCallback, InitializeSublyOutput, InitializeSublyOutputStruct0,
 };
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -37,10 +39,21 @@ pub fn handler(
     config.paused = false;
     config.bump = ctx.bumps.config;
     config.vault_bump = ctx.bumps.vault;
+    config.pending_reader_count = 0;
+    config.pending_reward_deposit = 0;
+    config.pending_reward_refund = Pubkey::default();
+    config.writer_waiting = false;
+    config.writer_waiting_slot = 0;
 
     let registry = &mut ctx.accounts.subscription_registry;
     registry.encrypted_registry = SubscriptionRegistry::blank_state();
     registry.bump = ctx.bumps.subscription_registry;
+    registry.service_count = 0;
+    registry.pending_registry_offset = None;
+    registry.pending_service_price = 0;
+    registry.pending_reader_count = 0;
+    registry.writer_waiting = false;
+    registry.writer_waiting_slot = 0;
 
     ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
@@ -69,6 +82,7 @@ pub fn callback(
     ctx: Context<InitializeSublyCallback>,
     output: ComputationOutputs<InitializeSublyOutput>,
 ) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
     let InitializeSublyOutput {
         field_0:
             InitializeSublyOutputStruct0 {
@@ -81,11 +95,11 @@ pub fn callback(
     };
 
     let subly_config = &mut ctx.accounts.config;
-    if subly_config.pending_initialize_offset.take().is_none() {
-        return Err(ErrorCode::PendingComputationMismatch.into());
-    }
+    release_pending(
+        &mut subly_config.pending_initialize_offset,
+        computation_offset,
+    )?;
     subly_config.encrypted_state = EncryptedState::from(config_cipher);
-    subly_config.paused = false;
     subly_config.pending_config_offset = None;
 
     let subscription_registry = &mut ctx.accounts.subscription_registry;
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_lang::Discriminator;
+
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{EncryptedState, SublyConfig, CONFIG_CT_LEN};
+use crate::MigrateConfig;
+
+/// Size of the config before `writer_waiting_slot` was appended.
+const WITHOUT_WRITER_SLOT_LEN: usize = SublyConfig::LEN - 8;
+/// Size of the config before `writer_waiting` was appended.
+const WITHOUT_WRITER_FLAG_LEN: usize = WITHOUT_WRITER_SLOT_LEN - 1;
+/// Size of the config before the `fund_rewards` refund fields were appended.
+const WITHOUT_REWARD_REFUND_LEN: usize = WITHOUT_WRITER_FLAG_LEN - 8 - 32;
+/// Size of the config before `pending_reader_count` was appended.
+const WITHOUT_READER_COUNT_LEN: usize = WITHOUT_REWARD_REFUND_LEN - 4;
+
+#[event]
+pub struct ConfigMigrated {
+    pub authority: Pubkey,
+    pub previous_len: u32,
+    pub len: u32,
+}
+
+/// Rewrites a config created by an earlier layout as `SublyConfig`. Every
+/// earlier layout is a prefix of the current one, but Borsh packs the
+/// options, so the fields are read one by one instead of zero-extending.
+/// Fields the old layout lacked start at their defaults.
+pub fn handler(ctx: Context<MigrateConfig>) -> Result<()> {
+    let config_info = ctx.accounts.config.to_account_info();
+    require_keys_eq!(
+        *config_info.owner,
+        crate::ID,
+        ErrorCode::UnrecognizedAccountLayout
+    );
+
+    let previous_len = config_info.data_len();
+    if previous_len >= SublyConfig::LEN {
+        // Already migrated; deployment scripts may run this blindly.
+        return Ok(());
+    }
+    require!(
+        previous_len >= WITHOUT_READER_COUNT_LEN,
+        ErrorCode::UnrecognizedAccountLayout
+    );
+
+    let migrated = {
+        let data = config_info.try_borrow_data()?;
+        require!(
+            data.starts_with(SublyConfig::DISCRIMINATOR),
+            ErrorCode::UnrecognizedAccountLayout
+        );
+        let mut reader: &[u8] = &data[SublyConfig::DISCRIMINATOR.len()..];
+        read_legacy_config(&mut reader, previous_len)
+            .map_err(|_| ErrorCode::UnrecognizedAccountLayout)?
+    };
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        migrated.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+
+    let required_lamports = Rent::get()?.minimum_balance(SublyConfig::LEN);
+    let current_lamports = config_info.lamports();
+    if required_lamports > current_lamports {
+        let transfer_accounts = SystemTransfer {
+            from: ctx.accounts.authority.to_account_info(),
+            to: config_info.clone(),
+        };
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                transfer_accounts,
+            ),
+            required_lamports - current_lamports,
+        )?;
+    }
+    config_info.resize(SublyConfig::LEN)?;
+
+    let mut data = config_info.try_borrow_mut_data()?;
+    data.fill(0);
+    let mut writer: &mut [u8] = &mut data;
+    migrated.try_serialize(&mut writer)?;
+
+    emit!(ConfigMigrated {
+        authority: migrated.authority,
+        previous_len: previous_len as u32,
+        len: SublyConfig::LEN as u32,
+    });
+
+    Ok(())
+}
+
+fn read_legacy_config(reader: &mut &[u8], previous_len: usize) -> std::io::Result<SublyConfig> {
+    let authority = Pubkey::deserialize(reader)?;
+    let usdc_mint = Pubkey::deserialize(reader)?;
+    let vault = Pubkey::deserialize(reader)?;
+    let encrypted_state = EncryptedState::<CONFIG_CT_LEN>::deserialize(reader)?;
+    let pending_initialize_offset = Option::<u64>::deserialize(reader)?;
+    let pending_config_offset = Option::<u64>::deserialize(reader)?;
+    let paused = bool::deserialize(reader)?;
+    let bump = u8::deserialize(reader)?;
+    let vault_bump = u8::deserialize(reader)?;
+    let pending_reader_count = if previous_len >= WITHOUT_REWARD_REFUND_LEN {
+        u32::deserialize(reader)?
+    } else {
+        0
+    };
+    let (pending_reward_deposit, pending_reward_refund) = if previous_len >= WITHOUT_WRITER_FLAG_LEN
+    {
+        (u64::deserialize(reader)?, Pubkey::deserialize(reader)?)
+    } else {
+        (0, Pubkey::default())
+    };
+
+    Ok(SublyConfig {
+        authority,
+        usdc_mint,
+        vault,
+        encrypted_state,
+        pending_initialize_offset,
+        pending_config_offset,
+        paused,
+        bump,
+        vault_bump,
+        pending_reader_count,
+        pending_reward_deposit,
+        pending_reward_refund,
+        // A wait recorded before its slot was kept is of unknown age, so it
+        // is dropped rather than left to hold readers back.
+        writer_waiting: false,
+        writer_waiting_slot: 0,
+    })
+}
@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_lang::Discriminator;
+
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{EncryptedState, UserStakeAccount, USER_STAKE_CT_LEN};
+use crate::MigrateUserStake;
+
+/// Size of a position before `page_index` and `page_count` were appended.
+const WITHOUT_PAGES_LEN: usize = UserStakeAccount::LEN - 2 - 2;
+/// Size of a position before `pending_stake_amount` was appended.
+const WITHOUT_STAKE_DEPOSIT_LEN: usize = WITHOUT_PAGES_LEN - 8;
+/// Size of a position before `encrypted_delta` was inserted after its state.
+const WITHOUT_DELTA_LEN: usize = WITHOUT_STAKE_DEPOSIT_LEN - UserStakeAccount::ENCRYPTED_DELTA_LEN;
+
+#[event]
+pub struct UserStakeMigrated {
+    pub user: Pubkey,
+    pub previous_len: u32,
+}
+
+/// Rewrites a position created by an earlier layout as `UserStakeAccount`.
+/// Positions predating stake pages are always page 0 of a single page. A
+/// position without a delta starts with a blank one, which the programs
+/// already treat as "nothing to settle".
+pub fn handler(ctx: Context<MigrateUserStake>) -> Result<()> {
+    let stake_info = ctx.accounts.user_stake.to_account_info();
+    require_keys_eq!(
+        *stake_info.owner,
+        crate::ID,
+        ErrorCode::UnrecognizedAccountLayout
+    );
+
+    let previous_len = stake_info.data_len();
+    if previous_len >= UserStakeAccount::LEN {
+        // Already migrated; keepers may sweep every position blindly.
+        return Ok(());
+    }
+    require!(
+        previous_len >= WITHOUT_DELTA_LEN,
+        ErrorCode::UnrecognizedAccountLayout
+    );
+
+    let migrated = {
+        let data = stake_info.try_borrow_data()?;
+        require!(
+            data.starts_with(UserStakeAccount::DISCRIMINATOR),
+            ErrorCode::UnrecognizedAccountLayout
+        );
+        let mut reader: &[u8] = &data[UserStakeAccount::DISCRIMINATOR.len()..];
+        read_legacy_user_stake(&mut reader, previous_len)
+            .map_err(|_| ErrorCode::UnrecognizedAccountLayout)?
+    };
+    require_keys_eq!(
+        migrated.owner,
+        ctx.accounts.user.key(),
+        ErrorCode::InvalidPositionOwner
+    );
+
+    let required_lamports = Rent::get()?.minimum_balance(UserStakeAccount::LEN);
+    let current_lamports = stake_info.lamports();
+    if required_lamports > current_lamports {
+        let transfer_accounts = SystemTransfer {
+            from: ctx.accounts.payer.to_account_info(),
+            to: stake_info.clone(),
+        };
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                transfer_accounts,
+            ),
+            required_lamports - current_lamports,
+        )?;
+    }
+    stake_info.resize(UserStakeAccount::LEN)?;
+
+    let mut data = stake_info.try_borrow_mut_data()?;
+    data.fill(0);
+    let mut writer: &mut [u8] = &mut data;
+    migrated.try_serialize(&mut writer)?;
+
+    emit!(UserStakeMigrated {
+        user: migrated.owner,
+        previous_len: previous_len as u32,
+    });
+
+    Ok(())
+}
+
+fn read_legacy_user_stake(
+    reader: &mut &[u8],
+    previous_len: usize,
+) -> std::io::Result<UserStakeAccount> {
+    let owner = Pubkey::deserialize(reader)?;
+    let entry_count = u8::deserialize(reader)?;
+    let encrypted_state = EncryptedState::<USER_STAKE_CT_LEN>::deserialize(reader)?;
+    let encrypted_delta = if previous_len >= WITHOUT_STAKE_DEPOSIT_LEN {
+        EncryptedState::deserialize(reader)?
+    } else {
+        EncryptedState::blank()
+    };
+    let pending_computation_offset = Option::<u64>::deserialize(reader)?;
+    let bump = u8::deserialize(reader)?;
+    let pending_stake_amount = if previous_len >= WITHOUT_PAGES_LEN {
+        u64::deserialize(reader)?
+    } else {
+        0
+    };
+
+    Ok(UserStakeAccount {
+        owner,
+        entry_count,
+        encrypted_state,
+        encrypted_delta,
+        pending_computation_offset,
+        bump,
+        pending_stake_amount,
+        page_index: 0,
+        page_count: 1,
+    })
+}
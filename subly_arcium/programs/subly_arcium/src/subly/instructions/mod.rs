@@ -1,3 +1,16 @@
+pub mod cancel_pending_computation;
+pub mod claim_operator;
+pub mod claim_user;
+pub mod fund_rewards;
 pub mod initialize;
+pub mod migrate_config;
+pub mod migrate_user_stake;
+pub mod register_subscription_service;
+pub mod set_apy;
+pub mod set_paused;
+pub mod settle_config;
 pub mod stake;
+pub mod subscribe;
 pub mod unstake;
+pub mod unsubscribe;
+pub mod view_position;
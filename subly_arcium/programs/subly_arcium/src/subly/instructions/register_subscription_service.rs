@@ -0,0 +1,154 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::{MAX_SERVICES, SERVICE_FIELD_MODULUS, SERVICE_TREE_DEPTH};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SubscriptionRegistry,
+};
+use crate::{
+    RegisterServiceSublyCallback, RegisterServiceSublyOutput, RegisterServiceSublyOutputStruct0,
+    RegisterSubscriptionService,
+};
+
+const REGISTRY_CIPHERTEXT_OFFSET: u32 = SubscriptionRegistry::ENCRYPTED_STATE_OFFSET as u32;
+const REGISTRY_CIPHERTEXT_LEN: u32 = SubscriptionRegistry::ENCRYPTED_STATE_LEN as u32;
+
+/// The siblings are the nodes on the path from the next free leaf to the
+/// current services root. The catalogue is public through
+/// `SubscriptionServiceRegistered`, so anyone can rebuild the tree.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RegisterSubscriptionServiceArgs {
+    pub monthly_price_usdc: u64,
+    pub siblings_low: [u64; SERVICE_TREE_DEPTH],
+    pub siblings_high: [u64; SERVICE_TREE_DEPTH],
+}
+
+#[event]
+pub struct SubscriptionServiceRegistered {
+    pub service_id: u64,
+    pub monthly_price_usdc: u64,
+}
+
+pub fn handler(
+    ctx: Context<RegisterSubscriptionService>,
+    computation_offset: u64,
+    args: RegisterSubscriptionServiceArgs,
+) -> Result<()> {
+    require!(args.monthly_price_usdc > 0, ErrorCode::AmountTooSmall);
+    require!(
+        args.monthly_price_usdc < SERVICE_FIELD_MODULUS,
+        ErrorCode::MathOverflow
+    );
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        ctx.accounts.config.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+
+    let registry = &ctx.accounts.subscription_registry;
+    require!(
+        (registry.service_count as usize) < MAX_SERVICES,
+        ErrorCode::ServiceCatalogFull
+    );
+    require!(
+        registry.pending_registry_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+
+    if !ctx
+        .accounts
+        .subscription_registry
+        .admit_writer(Clock::get()?.slot)
+    {
+        return Ok(());
+    }
+
+    let registry = &ctx.accounts.subscription_registry;
+    let registry_nonce = registry.encrypted_registry.nonce;
+    let registry_key = registry.key();
+    let service_id = u64::from(registry.service_count);
+
+    let mut arguments = vec![
+        Argument::PlaintextU128(registry_nonce),
+        Argument::Account(
+            registry_key,
+            REGISTRY_CIPHERTEXT_OFFSET,
+            REGISTRY_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU64(service_id),
+        Argument::PlaintextU64(args.monthly_price_usdc),
+    ];
+    arguments.extend(
+        args.siblings_low
+            .iter()
+            .chain(args.siblings_high.iter())
+            .map(|sibling| Argument::PlaintextU64(*sibling)),
+    );
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    let callback_accounts = [CallbackAccount {
+        pubkey: registry_key,
+        is_writable: true,
+    }];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![RegisterServiceSublyCallback::callback_ix(
+            &callback_accounts,
+        )],
+    )?;
+
+    let registry = &mut ctx.accounts.subscription_registry;
+    registry.pending_registry_offset = Some(computation_offset);
+    registry.pending_service_price = args.monthly_price_usdc;
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<RegisterServiceSublyCallback>,
+    output: ComputationOutputs<RegisterServiceSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let registry = &mut ctx.accounts.subscription_registry;
+
+    release_pending(&mut registry.pending_registry_offset, computation_offset)?;
+    let monthly_price_usdc = std::mem::take(&mut registry.pending_service_price);
+
+    let RegisterServiceSublyOutput {
+        field_0:
+            RegisterServiceSublyOutputStruct0 {
+                field_0: registry_cipher,
+                field_1: accepted,
+            },
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    // A stale proof leaves the registry as it was; the price is not listed.
+    if !accepted {
+        return Ok(());
+    }
+
+    let service_id = u64::from(registry.service_count);
+    registry.encrypted_registry = EncryptedState::from(registry_cipher);
+    registry.service_count += 1;
+
+    emit!(SubscriptionServiceRegistered {
+        service_id,
+        monthly_price_usdc,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::BASIS_POINTS_DIVISOR;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig,
+};
+use crate::{SetApy, SetApySublyCallback, SetApySublyOutput};
+
+const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
+const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SetApyArgs {
+    pub apy_bps: u16,
+}
+
+pub fn handler(ctx: Context<SetApy>, computation_offset: u64, args: SetApyArgs) -> Result<()> {
+    require!(
+        u64::from(args.apy_bps) <= BASIS_POINTS_DIVISOR,
+        ErrorCode::InvalidApy
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= 0, ErrorCode::ComputationValidationFailed);
+    let now_u64: u64 = now.try_into().map_err(|_| ErrorCode::ClockOverflow)?;
+
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        ctx.accounts.config.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+    require!(
+        ctx.accounts.config.pending_config_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+
+    if !ctx.accounts.config.admit_writer(true, Clock::get()?.slot) {
+        return Ok(());
+    }
+
+    let config_nonce = ctx.accounts.config.encrypted_state.nonce;
+    let config_key = ctx.accounts.config.key();
+
+    let arguments = vec![
+        Argument::PlaintextU128(config_nonce),
+        Argument::Account(config_key, CONFIG_CIPHERTEXT_OFFSET, CONFIG_CIPHERTEXT_LEN),
+        Argument::PlaintextU16(args.apy_bps),
+        Argument::PlaintextU64(now_u64),
+    ];
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    let callback_accounts = [CallbackAccount {
+        pubkey: config_key,
+        is_writable: true,
+    }];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![SetApySublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    ctx.accounts.config.pending_config_offset = Some(computation_offset);
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<SetApySublyCallback>,
+    output: ComputationOutputs<SetApySublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let config = &mut ctx.accounts.config;
+
+    release_pending(&mut config.pending_config_offset, computation_offset)?;
+
+    let previous_config_cipher = config.encrypted_state.clone();
+
+    let SetApySublyOutput {
+        field_0: config_cipher,
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    if previous_config_cipher.nonce == config_cipher.nonce
+        && previous_config_cipher.ciphertexts == config_cipher.ciphertexts
+    {
+        return Err(ErrorCode::ComputationValidationFailed.into());
+    }
+
+    config.encrypted_state = EncryptedState::from(config_cipher);
+
+    Ok(())
+}
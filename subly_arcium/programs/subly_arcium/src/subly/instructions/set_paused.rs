@@ -0,0 +1,129 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig,
+};
+use crate::{SetPaused, SetPausedSublyCallback, SetPausedSublyOutput, SetPausedSublyOutputStruct0};
+
+const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
+const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SetPausedArgs {
+    pub paused: bool,
+}
+
+#[event]
+pub struct PausedSet {
+    pub paused: bool,
+    pub encrypted_update_queued: bool,
+}
+
+#[event]
+pub struct EncryptedPausedReconciled {
+    pub encrypted_paused: bool,
+    pub paused: bool,
+}
+
+/// The plaintext `paused` flag is what every instruction checks, so it takes
+/// effect here even while readers or another config computation are in
+/// flight. The encrypted copy is only written once the config is free; until
+/// then the authority retries to queue it.
+pub fn handler(
+    ctx: Context<SetPaused>,
+    computation_offset: u64,
+    args: SetPausedArgs,
+) -> Result<()> {
+    require_keys_eq!(
+        ctx.accounts.authority.key(),
+        ctx.accounts.config.authority,
+        ErrorCode::UnauthorizedAuthority
+    );
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+
+    ctx.accounts.config.paused = args.paused;
+
+    let encrypted_update_queued = ctx.accounts.config.pending_config_offset.is_none()
+        && ctx.accounts.config.admit_writer(true, Clock::get()?.slot);
+    emit!(PausedSet {
+        paused: args.paused,
+        encrypted_update_queued,
+    });
+    if !encrypted_update_queued {
+        return Ok(());
+    }
+
+    let config_nonce = ctx.accounts.config.encrypted_state.nonce;
+    let config_key = ctx.accounts.config.key();
+
+    let arguments = vec![
+        Argument::PlaintextU128(config_nonce),
+        Argument::Account(config_key, CONFIG_CIPHERTEXT_OFFSET, CONFIG_CIPHERTEXT_LEN),
+        Argument::PlaintextBool(args.paused),
+    ];
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    let callback_accounts = [CallbackAccount {
+        pubkey: config_key,
+        is_writable: true,
+    }];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![SetPausedSublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    ctx.accounts.config.pending_config_offset = Some(computation_offset);
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<SetPausedSublyCallback>,
+    output: ComputationOutputs<SetPausedSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let config = &mut ctx.accounts.config;
+
+    release_pending(&mut config.pending_config_offset, computation_offset)?;
+
+    let previous_config_cipher = config.encrypted_state.clone();
+
+    let SetPausedSublyOutput {
+        field_0:
+            SetPausedSublyOutputStruct0 {
+                field_0: config_cipher,
+                field_1: paused,
+            },
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    if previous_config_cipher.nonce == config_cipher.nonce
+        && previous_config_cipher.ciphertexts == config_cipher.ciphertexts
+    {
+        return Err(ErrorCode::ComputationValidationFailed.into());
+    }
+
+    config.encrypted_state = EncryptedState::from(config_cipher);
+    // The plaintext flag already took effect in the handler and may have been
+    // flipped again since; a mismatch tells the authority to queue another
+    // update.
+    emit!(EncryptedPausedReconciled {
+        encrypted_paused: paused,
+        paused: config.paused,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::USER_POSITION_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig, UserStakeAccount,
+};
+use crate::{
+    SettleConfig, SettleConfigSublyCallback, SettleConfigSublyOutput,
+    SettleConfigSublyOutputStruct0, ID,
+};
+
+const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
+const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
+const USER_DELTA_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_DELTA_OFFSET as u32;
+const USER_DELTA_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_DELTA_LEN as u32;
+
+/// Folds one user's pending delta into the encrypted config. Anyone may
+/// settle any position; the computation only moves aggregate totals. Being
+/// permissionless, it never holds new readers back while it waits.
+pub fn handler(ctx: Context<SettleConfig>, computation_offset: u64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= 0, ErrorCode::ComputationValidationFailed);
+    let now_u64: u64 = now.try_into().map_err(|_| ErrorCode::ClockOverflow)?;
+
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+    require!(
+        ctx.accounts.config.pending_config_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+
+    let user_stake = &ctx.accounts.user_stake;
+    require!(
+        user_stake.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    require!(
+        !user_stake.is_delta_fresh(),
+        ErrorCode::ComputationValidationFailed
+    );
+
+    if !ctx.accounts.config.admit_writer(false, Clock::get()?.slot) {
+        return Ok(());
+    }
+
+    let config_nonce = ctx.accounts.config.encrypted_state.nonce;
+    let user_delta_nonce = user_stake.encrypted_delta.nonce;
+    let config_key = ctx.accounts.config.key();
+    let user_stake_key = user_stake.key();
+
+    let arguments = vec![
+        Argument::PlaintextU128(config_nonce),
+        Argument::Account(config_key, CONFIG_CIPHERTEXT_OFFSET, CONFIG_CIPHERTEXT_LEN),
+        Argument::PlaintextU128(user_delta_nonce),
+        Argument::Account(
+            user_stake_key,
+            USER_DELTA_CIPHERTEXT_OFFSET,
+            USER_DELTA_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU64(now_u64),
+        Argument::PlaintextBool(false),
+    ];
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    let callback_accounts = [
+        CallbackAccount {
+            pubkey: config_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: user_stake_key,
+            is_writable: true,
+        },
+    ];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![SettleConfigSublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    ctx.accounts.config.pending_config_offset = Some(computation_offset);
+    ctx.accounts.user_stake.pending_computation_offset = Some(computation_offset);
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<SettleConfigSublyCallback>,
+    output: ComputationOutputs<SettleConfigSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let config = &mut ctx.accounts.config;
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    release_pending(&mut config.pending_config_offset, computation_offset)?;
+    release_pending(
+        &mut user_stake.pending_computation_offset,
+        computation_offset,
+    )?;
+
+    let previous_config_cipher = config.encrypted_state.clone();
+
+    let SettleConfigSublyOutput {
+        field_0:
+            SettleConfigSublyOutputStruct0 {
+                field_0: config_cipher,
+                field_1: delta_cipher,
+            },
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    let expected_user_pda = Pubkey::create_program_address(
+        &[
+            USER_POSITION_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &UserStakeAccount::page_seed(user_stake.page_index),
+            &[user_stake.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidPositionOwner)?;
+    require_keys_eq!(
+        expected_user_pda,
+        user_stake.key(),
+        ErrorCode::InvalidPositionOwner
+    );
+
+    if previous_config_cipher.nonce == config_cipher.nonce
+        && previous_config_cipher.ciphertexts == config_cipher.ciphertexts
+    {
+        return Err(ErrorCode::ComputationValidationFailed.into());
+    }
+
+    config.encrypted_state = EncryptedState::from(config_cipher);
+    user_stake.encrypted_delta = EncryptedState::from(delta_cipher);
+
+    Ok(())
+}
@@ -3,20 +3,33 @@ use anchor_spl::token::{self, Transfer};
 use arcium_anchor::prelude::*;
 use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
 
-use crate::subly::constants::{MAX_STAKE_ENTRIES, USER_POSITION_SEED};
+use crate::subly::constants::{
+    CONFIG_SEED, MAX_STAKE_ENTRIES, USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED,
+};
 use crate::subly::error::ErrorCode;
-use crate::subly::state::{EncryptedState, SublyConfig, UserStakeAccount};
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig, UserStakeAccount,
+    UserSubscriptionsAccount,
+};
 use crate::{Stake, StakeSublyCallback, StakeSublyOutput, StakeSublyOutputStruct0, ID};
 
 const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
 const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
 const USER_STAKE_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_STATE_OFFSET as u32;
 const USER_STAKE_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_STATE_LEN as u32;
+const USER_DELTA_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_DELTA_OFFSET as u32;
+const USER_DELTA_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_DELTA_LEN as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET: u32 =
+    UserSubscriptionsAccount::ENCRYPTED_STATE_OFFSET as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_LEN: u32 = UserSubscriptionsAccount::ENCRYPTED_STATE_LEN as u32;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct StakeArgs {
     pub amount: u64,
     pub lock_option: u8,
+    /// Stake page that receives the tranche. A new page can only be opened
+    /// right after the last one.
+    pub page_index: u16,
 }
 
 pub fn handler(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) -> Result<()> {
@@ -35,13 +48,32 @@ pub fn handler(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) ->
         ctx.accounts.config.pending_config_offset.is_none(),
         ErrorCode::PendingComputationInProgress
     );
+    require!(
+        ctx.accounts.config.admits_readers(Clock::get()?.slot),
+        ErrorCode::ConfigWriterWaiting
+    );
 
     let user = &ctx.accounts.user;
     let user_key = user.key();
     let user_stake_nonce;
+    let user_delta_nonce;
+    let delta_fresh;
+    let stake_fresh;
     {
         let user_stake = &mut ctx.accounts.user_stake;
-        user_stake.ensure_owner(user_key, ctx.bumps.user_stake);
+        if user_stake.owner == Pubkey::default() && args.page_index > 0 {
+            let first_page = ctx
+                .accounts
+                .first_page
+                .as_mut()
+                .ok_or(ErrorCode::InvalidStakePage)?;
+            require!(
+                first_page.page_count == args.page_index,
+                ErrorCode::InvalidStakePage
+            );
+            first_page.page_count += 1;
+        }
+        user_stake.ensure_owner(user_key, ctx.bumps.user_stake, args.page_index);
         require_keys_eq!(user_stake.owner, user_key, ErrorCode::InvalidPositionOwner);
         require!(
             user_stake.pending_computation_offset.is_none(),
@@ -52,11 +84,38 @@ pub fn handler(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) ->
             ErrorCode::ComputationValidationFailed
         );
         user_stake_nonce = user_stake.encrypted_state.nonce;
+        user_delta_nonce = user_stake.encrypted_delta.nonce;
+        delta_fresh = user_stake.is_delta_fresh();
+        stake_fresh = user_stake.is_fresh();
     }
 
+    // Every stake adds to the principal total kept with the subscriptions,
+    // which is first seeded from page 0.
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+    user_subscriptions.ensure_owner(user_key, ctx.bumps.user_subscriptions);
+    require_keys_eq!(
+        user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+    require!(
+        user_subscriptions.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    let subscriptions_fresh = user_subscriptions.is_fresh();
+    require!(
+        !subscriptions_fresh || args.page_index == 0,
+        ErrorCode::InvalidStakePage
+    );
+    let user_subscriptions_nonce = user_subscriptions.encrypted_state.nonce;
+    let user_subscriptions_key = user_subscriptions.key();
+
     let config_nonce = ctx.accounts.config.encrypted_state.nonce;
     let config_key = ctx.accounts.config.key();
     let user_stake_key = ctx.accounts.user_stake.key();
+    let vault_key = ctx.accounts.vault.key();
+    let user_token_key = ctx.accounts.user_token_account.key();
+    let token_program_key = ctx.accounts.token_program.key();
 
     // Optimistic capacity check to fail early before CPI
     // Transfer funds into the vault prior to queuing the computation.
@@ -82,9 +141,25 @@ pub fn handler(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) ->
             USER_STAKE_CIPHERTEXT_OFFSET,
             USER_STAKE_CIPHERTEXT_LEN,
         ),
+        Argument::PlaintextU128(user_delta_nonce),
+        Argument::Account(
+            user_stake_key,
+            USER_DELTA_CIPHERTEXT_OFFSET,
+            USER_DELTA_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU128(user_subscriptions_nonce),
+        Argument::Account(
+            user_subscriptions_key,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_LEN,
+        ),
         Argument::PlaintextU64(args.amount),
         Argument::PlaintextU8(args.lock_option),
         Argument::PlaintextU64(now_u64),
+        Argument::PlaintextBool(delta_fresh),
+        Argument::PlaintextBool(stake_fresh),
+        Argument::PlaintextU16(args.page_index),
+        Argument::PlaintextBool(subscriptions_fresh),
     ];
 
     ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
@@ -98,6 +173,22 @@ pub fn handler(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) ->
             pubkey: user_stake_key,
             is_writable: true,
         },
+        CallbackAccount {
+            pubkey: user_subscriptions_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: vault_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: user_token_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: token_program_key,
+            is_writable: false,
+        },
     ];
 
     queue_computation(
@@ -108,8 +199,15 @@ pub fn handler(ctx: Context<Stake>, computation_offset: u64, args: StakeArgs) ->
         vec![StakeSublyCallback::callback_ix(&callback_accounts)],
     )?;
 
-    ctx.accounts.config.pending_config_offset = Some(computation_offset);
-    ctx.accounts.user_stake.pending_computation_offset = Some(computation_offset);
+    let config = &mut ctx.accounts.config;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let user_stake = &mut ctx.accounts.user_stake;
+    user_stake.pending_computation_offset = Some(computation_offset);
+    user_stake.pending_stake_amount = args.amount;
+    ctx.accounts.user_subscriptions.pending_computation_offset = Some(computation_offset);
 
     Ok(())
 }
@@ -118,34 +216,29 @@ pub fn callback(
     ctx: Context<StakeSublyCallback>,
     output: ComputationOutputs<StakeSublyOutput>,
 ) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
     let config = &mut ctx.accounts.config;
     let user_stake = &mut ctx.accounts.user_stake;
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
 
-    if config.pending_config_offset.take().is_none()
-        || user_stake.pending_computation_offset.take().is_none()
-    {
-        return Err(ErrorCode::PendingComputationMismatch.into());
-    }
-
-    let previous_user_cipher = user_stake.encrypted_state.clone();
-
-    let StakeSublyOutput {
-        field_0:
-            StakeSublyOutputStruct0 {
-                field_0: config_cipher,
-                field_1: stake_cipher,
-                field_2: entry_count,
-                field_3: _next_tranche_id,
-            },
-    } = match output {
-        ComputationOutputs::Success(payload) => payload,
-        ComputationOutputs::Failure => return Err(ErrorCode::AbortedComputation.into()),
-    };
+    release_pending(
+        &mut user_stake.pending_computation_offset,
+        computation_offset,
+    )?;
+    release_pending(
+        &mut user_subscriptions.pending_computation_offset,
+        computation_offset,
+    )?;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
 
     let expected_user_pda = Pubkey::create_program_address(
         &[
             USER_POSITION_SEED.as_bytes(),
             user_stake.owner.as_ref(),
+            &UserStakeAccount::page_seed(user_stake.page_index),
             &[user_stake.bump],
         ],
         &ID,
@@ -157,17 +250,81 @@ pub fn callback(
         ErrorCode::InvalidPositionOwner
     );
 
+    let deposit = std::mem::take(&mut user_stake.pending_stake_amount);
+
+    // A tranche that was not placed, or a computation that failed, leaves the
+    // position untouched and the deposit goes back to the user.
+    let placed_output = match output {
+        ComputationOutputs::Success(StakeSublyOutput {
+            field_0:
+                StakeSublyOutputStruct0 {
+                    field_0: stake_cipher,
+                    field_1: delta_cipher,
+                    field_2: subscriptions_cipher,
+                    field_3: placed,
+                    field_4: entry_count,
+                    field_5: _next_tranche_id,
+                },
+        }) if placed => Some((
+            stake_cipher,
+            delta_cipher,
+            subscriptions_cipher,
+            entry_count,
+        )),
+        _ => None,
+    };
+
+    let Some((stake_cipher, delta_cipher, subscriptions_cipher, entry_count)) = placed_output
+    else {
+        let config_seed = CONFIG_SEED.as_bytes();
+        let bump_bytes = [config.bump];
+        let signer_seeds_slice: &[&[u8]] = &[config_seed, &bump_bytes];
+        let signer_seeds = &[signer_seeds_slice];
+
+        let transfer_accounts = Transfer {
+            from: ctx.accounts.vault.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: config.to_account_info(),
+        };
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            deposit,
+        )?;
+
+        return Ok(());
+    };
+
+    let previous_user_cipher = &user_stake.encrypted_state;
     if previous_user_cipher.nonce == stake_cipher.nonce
         && previous_user_cipher.ciphertexts == stake_cipher.ciphertexts
     {
         return Err(ErrorCode::ComputationValidationFailed.into());
     }
 
-    config.encrypted_state = EncryptedState::from(config_cipher);
-    config.paused = false;
+    let expected_subscriptions_pda = Pubkey::create_program_address(
+        &[
+            USER_SUBSCRIPTIONS_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &[user_subscriptions.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+    require_keys_eq!(
+        expected_subscriptions_pda,
+        user_subscriptions.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
 
     user_stake.encrypted_state = EncryptedState::from(stake_cipher);
+    user_stake.encrypted_delta = EncryptedState::from(delta_cipher);
     user_stake.entry_count = entry_count;
+    user_subscriptions.encrypted_state = EncryptedState::from(subscriptions_cipher);
 
     Ok(())
 }
@@ -0,0 +1,281 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::{USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED};
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig,
+    SubscriptionRegistry, UserStakeAccount, UserSubscriptionsAccount, SERVICE_PROOF_CT_LEN,
+};
+use crate::{
+    Subscribe, SubscribeSublyCallback, SubscribeSublyOutput, SubscribeSublyOutputStruct0, ID,
+};
+
+const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
+const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
+const USER_STAKE_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_STATE_OFFSET as u32;
+const USER_STAKE_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_STATE_LEN as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET: u32 =
+    UserSubscriptionsAccount::ENCRYPTED_STATE_OFFSET as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_LEN: u32 = UserSubscriptionsAccount::ENCRYPTED_STATE_LEN as u32;
+const REGISTRY_CIPHERTEXT_OFFSET: u32 = SubscriptionRegistry::ENCRYPTED_STATE_OFFSET as u32;
+const REGISTRY_CIPHERTEXT_LEN: u32 = SubscriptionRegistry::ENCRYPTED_STATE_LEN as u32;
+
+/// The client encrypts a proof of the chosen service to the MXE with a shared
+/// secret derived from `encryption_pubkey`: service id, monthly price, then
+/// the low and high lanes of the sibling nodes from the leaf up to the
+/// services root. Neither the service nor its path appears in plaintext.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SubscribeArgs {
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub proof_ciphertexts: [[u8; 32]; SERVICE_PROOF_CT_LEN],
+}
+
+#[event]
+pub struct SubscribeProcessed {
+    pub user: Pubkey,
+    pub accepted: bool,
+}
+
+pub fn handler(
+    ctx: Context<Subscribe>,
+    computation_offset: u64,
+    args: SubscribeArgs,
+) -> Result<()> {
+    require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+    require!(
+        ctx.accounts.config.pending_config_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    require!(
+        ctx.accounts.config.admits_readers(Clock::get()?.slot),
+        ErrorCode::ConfigWriterWaiting
+    );
+    require!(
+        ctx.accounts
+            .subscription_registry
+            .pending_registry_offset
+            .is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    require!(
+        ctx.accounts
+            .subscription_registry
+            .admits_readers(Clock::get()?.slot),
+        ErrorCode::RegistryWriterWaiting
+    );
+
+    let user_key = ctx.accounts.user.key();
+    require_keys_eq!(
+        ctx.accounts.user_stake.owner,
+        user_key,
+        ErrorCode::InvalidPositionOwner
+    );
+    require!(
+        ctx.accounts.user_stake.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    require!(
+        !ctx.accounts.user_stake.is_fresh(),
+        ErrorCode::InvalidStakePage
+    );
+
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+    user_subscriptions.ensure_owner(user_key, ctx.bumps.user_subscriptions);
+    require_keys_eq!(
+        user_subscriptions.owner,
+        user_key,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+    require!(
+        user_subscriptions.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    // The budget comes from the principal total every stake and unstake keeps
+    // with the subscriptions. A fresh total is seeded from page 0.
+    require!(
+        !user_subscriptions.is_fresh() || ctx.accounts.user_stake.page_index == 0,
+        ErrorCode::InvalidStakePage
+    );
+
+    let config_nonce = ctx.accounts.config.encrypted_state.nonce;
+    let user_stake_nonce = ctx.accounts.user_stake.encrypted_state.nonce;
+    let user_subscriptions_nonce = ctx.accounts.user_subscriptions.encrypted_state.nonce;
+    let fresh = ctx.accounts.user_subscriptions.is_fresh();
+    let config_key = ctx.accounts.config.key();
+    let user_stake_key = ctx.accounts.user_stake.key();
+    let user_subscriptions_key = ctx.accounts.user_subscriptions.key();
+    let registry_nonce = ctx.accounts.subscription_registry.encrypted_registry.nonce;
+    let registry_key = ctx.accounts.subscription_registry.key();
+
+    let mut arguments = vec![
+        Argument::ArcisPubkey(args.encryption_pubkey),
+        Argument::PlaintextU128(args.nonce),
+    ];
+    arguments.extend(
+        args.proof_ciphertexts
+            .iter()
+            .map(|ciphertext| Argument::EncryptedU64(*ciphertext)),
+    );
+    arguments.extend([
+        Argument::PlaintextU128(config_nonce),
+        Argument::Account(config_key, CONFIG_CIPHERTEXT_OFFSET, CONFIG_CIPHERTEXT_LEN),
+        Argument::PlaintextU128(user_stake_nonce),
+        Argument::Account(
+            user_stake_key,
+            USER_STAKE_CIPHERTEXT_OFFSET,
+            USER_STAKE_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU128(user_subscriptions_nonce),
+        Argument::Account(
+            user_subscriptions_key,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU128(registry_nonce),
+        Argument::Account(
+            registry_key,
+            REGISTRY_CIPHERTEXT_OFFSET,
+            REGISTRY_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextBool(fresh),
+    ]);
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    // The config, stake and registry are only read, but they are locked as
+    // well so their ciphertexts cannot change under the queued nonces.
+    let callback_accounts = [
+        CallbackAccount {
+            pubkey: config_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: registry_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: user_stake_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: user_subscriptions_key,
+            is_writable: true,
+        },
+    ];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![SubscribeSublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    let config = &mut ctx.accounts.config;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let registry = &mut ctx.accounts.subscription_registry;
+    registry.pending_reader_count = registry
+        .pending_reader_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    ctx.accounts.user_stake.pending_computation_offset = Some(computation_offset);
+    ctx.accounts.user_subscriptions.pending_computation_offset = Some(computation_offset);
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<SubscribeSublyCallback>,
+    output: ComputationOutputs<SubscribeSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let config = &mut ctx.accounts.config;
+    let registry = &mut ctx.accounts.subscription_registry;
+    let user_stake = &mut ctx.accounts.user_stake;
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+
+    release_pending(
+        &mut user_stake.pending_computation_offset,
+        computation_offset,
+    )?;
+    release_pending(
+        &mut user_subscriptions.pending_computation_offset,
+        computation_offset,
+    )?;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
+    registry.pending_reader_count = registry
+        .pending_reader_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
+
+    let SubscribeSublyOutput {
+        field_0:
+            SubscribeSublyOutputStruct0 {
+                field_0: subscriptions_cipher,
+                field_1: accepted,
+            },
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    let expected_user_pda = Pubkey::create_program_address(
+        &[
+            USER_POSITION_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &UserStakeAccount::page_seed(user_stake.page_index),
+            &[user_stake.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidPositionOwner)?;
+    require_keys_eq!(
+        expected_user_pda,
+        user_stake.key(),
+        ErrorCode::InvalidPositionOwner
+    );
+
+    let expected_subscriptions_pda = Pubkey::create_program_address(
+        &[
+            USER_SUBSCRIPTIONS_SEED.as_bytes(),
+            user_subscriptions.owner.as_ref(),
+            &[user_subscriptions.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+    require_keys_eq!(
+        expected_subscriptions_pda,
+        user_subscriptions.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
+    require_keys_eq!(
+        user_subscriptions.owner,
+        user_stake.owner,
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    // Rejected requests come back re-encrypted but unchanged, which also
+    // initializes a fresh account.
+    user_subscriptions.encrypted_state = EncryptedState::from(subscriptions_cipher);
+
+    emit!(SubscribeProcessed {
+        user: user_subscriptions.owner,
+        accepted,
+    });
+
+    Ok(())
+}
@@ -3,15 +3,34 @@ use anchor_spl::token::{self, Transfer};
 use arcium_anchor::prelude::*;
 use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
 
-use crate::subly::constants::{CONFIG_SEED, USER_POSITION_SEED, VAULT_SEED};
+use crate::subly::constants::{
+    CONFIG_SEED, USER_POSITION_SEED, USER_SUBSCRIPTIONS_SEED, VAULT_SEED,
+};
 use crate::subly::error::ErrorCode;
-use crate::subly::state::{EncryptedState, SublyConfig, UserStakeAccount};
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, SublyConfig, UserStakeAccount,
+    UserSubscriptionsAccount,
+};
 use crate::{Unstake, UnstakeSublyCallback, UnstakeSublyOutput, UnstakeSublyOutputStruct0, ID};
 
 const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
 const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
 const USER_STAKE_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_STATE_OFFSET as u32;
 const USER_STAKE_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_STATE_LEN as u32;
+const USER_DELTA_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_DELTA_OFFSET as u32;
+const USER_DELTA_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_DELTA_LEN as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET: u32 =
+    UserSubscriptionsAccount::ENCRYPTED_STATE_OFFSET as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_LEN: u32 = UserSubscriptionsAccount::ENCRYPTED_STATE_LEN as u32;
+
+#[event]
+pub struct UnstakeProcessed {
+    pub user: Pubkey,
+    pub page_index: u16,
+    pub withdrawn_principal: u64,
+    /// Unrealized yield given up with the tranche; it stays in the reward pool.
+    pub forfeited_yield: u64,
+}
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct UnstakeArgs {
@@ -32,6 +51,10 @@ pub fn handler(ctx: Context<Unstake>, computation_offset: u64, args: UnstakeArgs
         ctx.accounts.config.pending_config_offset.is_none(),
         ErrorCode::PendingComputationInProgress
     );
+    require!(
+        ctx.accounts.config.admits_readers(Clock::get()?.slot),
+        ErrorCode::ConfigWriterWaiting
+    );
 
     let user = &ctx.accounts.user;
     let user_stake = &ctx.accounts.user_stake;
@@ -44,12 +67,38 @@ pub fn handler(ctx: Context<Unstake>, computation_offset: u64, args: UnstakeArgs
         user_stake.pending_computation_offset.is_none(),
         ErrorCode::PendingComputationInProgress
     );
+    require!(!user_stake.is_fresh(), ErrorCode::NothingToUnstake);
+
+    // The circuit keeps the tranche staked when the principal left across
+    // all pages would no longer cover the user's subscriptions.
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+    user_subscriptions.ensure_owner(user.key(), ctx.bumps.user_subscriptions);
+    require_keys_eq!(
+        user_subscriptions.owner,
+        user.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
+    require!(
+        user_subscriptions.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    // A fresh principal total is seeded from the page being unstaked, which
+    // is only right for page 0.
+    require!(
+        !user_subscriptions.is_fresh() || user_stake.page_index == 0,
+        ErrorCode::InvalidStakePage
+    );
 
     let config_nonce = ctx.accounts.config.encrypted_state.nonce;
     let user_stake_nonce = ctx.accounts.user_stake.encrypted_state.nonce;
+    let user_delta_nonce = ctx.accounts.user_stake.encrypted_delta.nonce;
+    let delta_fresh = ctx.accounts.user_stake.is_delta_fresh();
     let config_key = ctx.accounts.config.key();
     let user_stake_key = ctx.accounts.user_stake.key();
     let vault_key = ctx.accounts.vault.key();
+    let user_subscriptions_nonce = ctx.accounts.user_subscriptions.encrypted_state.nonce;
+    let subscriptions_fresh = ctx.accounts.user_subscriptions.is_fresh();
+    let user_subscriptions_key = ctx.accounts.user_subscriptions.key();
     let user_token_key = ctx.accounts.user_token_account.key();
     let token_program_key = ctx.accounts.token_program.key();
 
@@ -62,8 +111,22 @@ pub fn handler(ctx: Context<Unstake>, computation_offset: u64, args: UnstakeArgs
             USER_STAKE_CIPHERTEXT_OFFSET,
             USER_STAKE_CIPHERTEXT_LEN,
         ),
+        Argument::PlaintextU128(user_delta_nonce),
+        Argument::Account(
+            user_stake_key,
+            USER_DELTA_CIPHERTEXT_OFFSET,
+            USER_DELTA_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU128(user_subscriptions_nonce),
+        Argument::Account(
+            user_subscriptions_key,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_LEN,
+        ),
         Argument::PlaintextU64(args.tranche_id),
         Argument::PlaintextU64(now_u64),
+        Argument::PlaintextBool(delta_fresh),
+        Argument::PlaintextBool(subscriptions_fresh),
     ];
 
     ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
@@ -77,6 +140,10 @@ pub fn handler(ctx: Context<Unstake>, computation_offset: u64, args: UnstakeArgs
             pubkey: user_stake_key,
             is_writable: true,
         },
+        CallbackAccount {
+            pubkey: user_subscriptions_key,
+            is_writable: true,
+        },
         CallbackAccount {
             pubkey: vault_key,
             is_writable: true,
@@ -99,8 +166,13 @@ pub fn handler(ctx: Context<Unstake>, computation_offset: u64, args: UnstakeArgs
         vec![UnstakeSublyCallback::callback_ix(&callback_accounts)],
     )?;
 
-    ctx.accounts.config.pending_config_offset = Some(computation_offset);
+    let config = &mut ctx.accounts.config;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
     ctx.accounts.user_stake.pending_computation_offset = Some(computation_offset);
+    ctx.accounts.user_subscriptions.pending_computation_offset = Some(computation_offset);
 
     Ok(())
 }
@@ -109,36 +181,52 @@ pub fn callback(
     ctx: Context<UnstakeSublyCallback>,
     output: ComputationOutputs<UnstakeSublyOutput>,
 ) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
     let config = &mut ctx.accounts.config;
     let user_stake = &mut ctx.accounts.user_stake;
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
 
-    if config.pending_config_offset.take().is_none()
-        || user_stake.pending_computation_offset.take().is_none()
-    {
-        return Err(ErrorCode::PendingComputationMismatch.into());
-    }
+    release_pending(
+        &mut user_stake.pending_computation_offset,
+        computation_offset,
+    )?;
+    release_pending(
+        &mut user_subscriptions.pending_computation_offset,
+        computation_offset,
+    )?;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
 
     let prev_user_cipher = user_stake.encrypted_state.clone();
 
     let UnstakeSublyOutput {
         field_0:
             UnstakeSublyOutputStruct0 {
-                field_0: config_cipher,
-                field_1: stake_cipher,
-                field_2: withdrawn_principal,
-                field_3: entry_count,
+                field_0: stake_cipher,
+                field_1: delta_cipher,
+                field_2: subscriptions_cipher,
+                field_3: withdrawn_principal,
+                field_4: forfeited_yield,
+                field_5: entry_count,
             },
     } = match output {
         ComputationOutputs::Success(payload) => payload,
-        ComputationOutputs::Failure => return Err(ErrorCode::AbortedComputation.into()),
+        ComputationOutputs::Failure => return Ok(()),
     };
 
-    require!(withdrawn_principal > 0, ErrorCode::NothingToUnstake);
+    // A tranche that is missing, still locked, holding claimable yield or
+    // backing subscriptions comes back unchanged.
+    if withdrawn_principal == 0 {
+        return Ok(());
+    }
 
     let expected_user_pda = Pubkey::create_program_address(
         &[
             USER_POSITION_SEED.as_bytes(),
             user_stake.owner.as_ref(),
+            &UserStakeAccount::page_seed(user_stake.page_index),
             &[user_stake.bump],
         ],
         &ID,
@@ -156,11 +244,25 @@ pub fn callback(
         return Err(ErrorCode::ComputationValidationFailed.into());
     }
 
-    config.encrypted_state = EncryptedState::from(config_cipher);
-    config.paused = false;
+    let expected_subscriptions_pda = Pubkey::create_program_address(
+        &[
+            USER_SUBSCRIPTIONS_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &[user_subscriptions.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+    require_keys_eq!(
+        expected_subscriptions_pda,
+        user_subscriptions.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
 
     user_stake.encrypted_state = EncryptedState::from(stake_cipher);
+    user_stake.encrypted_delta = EncryptedState::from(delta_cipher);
     user_stake.entry_count = entry_count;
+    user_subscriptions.encrypted_state = EncryptedState::from(subscriptions_cipher);
 
     let vault = &ctx.accounts.vault;
     let expected_vault =
@@ -188,5 +290,12 @@ pub fn callback(
         withdrawn_principal,
     )?;
 
+    emit!(UnstakeProcessed {
+        user: user_stake.owner,
+        page_index: user_stake.page_index,
+        withdrawn_principal,
+        forfeited_yield,
+    });
+
     Ok(())
 }
@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::USER_SUBSCRIPTIONS_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, EncryptedState, UserSubscriptionsAccount,
+};
+use crate::{
+    Unsubscribe, UnsubscribeSublyCallback, UnsubscribeSublyOutput, UnsubscribeSublyOutputStruct0,
+    ID,
+};
+
+const USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET: u32 =
+    UserSubscriptionsAccount::ENCRYPTED_STATE_OFFSET as u32;
+const USER_SUBSCRIPTIONS_CIPHERTEXT_LEN: u32 = UserSubscriptionsAccount::ENCRYPTED_STATE_LEN as u32;
+
+/// Same encoding as `SubscribeArgs`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UnsubscribeArgs {
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub service_id_ciphertext: [u8; 32],
+}
+
+#[event]
+pub struct UnsubscribeProcessed {
+    pub user: Pubkey,
+    pub removed: bool,
+}
+
+pub fn handler(
+    ctx: Context<Unsubscribe>,
+    computation_offset: u64,
+    args: UnsubscribeArgs,
+) -> Result<()> {
+    let user_subscriptions = &ctx.accounts.user_subscriptions;
+    require_keys_eq!(
+        user_subscriptions.owner,
+        ctx.accounts.user.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
+    require!(
+        user_subscriptions.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    // Writing a fresh account here would skip seeding its principal total.
+    require!(
+        !user_subscriptions.is_fresh(),
+        ErrorCode::SubscriptionNotFound
+    );
+
+    let user_subscriptions_nonce = user_subscriptions.encrypted_state.nonce;
+    let fresh = user_subscriptions.is_fresh();
+    let user_subscriptions_key = user_subscriptions.key();
+
+    let arguments = vec![
+        Argument::ArcisPubkey(args.encryption_pubkey),
+        Argument::PlaintextU128(args.nonce),
+        Argument::EncryptedU64(args.service_id_ciphertext),
+        Argument::PlaintextU128(user_subscriptions_nonce),
+        Argument::Account(
+            user_subscriptions_key,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_OFFSET,
+            USER_SUBSCRIPTIONS_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextBool(fresh),
+    ];
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    let callback_accounts = [CallbackAccount {
+        pubkey: user_subscriptions_key,
+        is_writable: true,
+    }];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![UnsubscribeSublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    ctx.accounts.user_subscriptions.pending_computation_offset = Some(computation_offset);
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<UnsubscribeSublyCallback>,
+    output: ComputationOutputs<UnsubscribeSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let user_subscriptions = &mut ctx.accounts.user_subscriptions;
+
+    release_pending(
+        &mut user_subscriptions.pending_computation_offset,
+        computation_offset,
+    )?;
+
+    let UnsubscribeSublyOutput {
+        field_0:
+            UnsubscribeSublyOutputStruct0 {
+                field_0: subscriptions_cipher,
+                field_1: removed,
+            },
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    let expected_subscriptions_pda = Pubkey::create_program_address(
+        &[
+            USER_SUBSCRIPTIONS_SEED.as_bytes(),
+            user_subscriptions.owner.as_ref(),
+            &[user_subscriptions.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidSubscriptionAccount)?;
+    require_keys_eq!(
+        expected_subscriptions_pda,
+        user_subscriptions.key(),
+        ErrorCode::InvalidSubscriptionAccount
+    );
+
+    user_subscriptions.encrypted_state = EncryptedState::from(subscriptions_cipher);
+
+    emit!(UnsubscribeProcessed {
+        user: user_subscriptions.owner,
+        removed,
+    });
+
+    Ok(())
+}
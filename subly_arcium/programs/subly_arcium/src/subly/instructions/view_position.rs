@@ -0,0 +1,176 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{Argument, CallbackAccount};
+
+use crate::subly::constants::USER_POSITION_SEED;
+use crate::subly::error::ErrorCode;
+use crate::subly::state::{
+    callback_computation_offset, release_pending, SublyConfig, UserStakeAccount,
+    POSITION_SUMMARY_CT_LEN,
+};
+use crate::{ViewPosition, ViewPositionSublyCallback, ViewPositionSublyOutput, ID};
+
+const CONFIG_CIPHERTEXT_OFFSET: u32 = SublyConfig::ENCRYPTED_STATE_OFFSET as u32;
+const CONFIG_CIPHERTEXT_LEN: u32 = SublyConfig::ENCRYPTED_STATE_LEN as u32;
+const USER_STAKE_CIPHERTEXT_OFFSET: u32 = UserStakeAccount::ENCRYPTED_STATE_OFFSET as u32;
+const USER_STAKE_CIPHERTEXT_LEN: u32 = UserStakeAccount::ENCRYPTED_STATE_LEN as u32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ViewPositionArgs {
+    /// X25519 public key the summary is encrypted to.
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+}
+
+/// Summary of one stake page encrypted to the key passed in
+/// `ViewPositionArgs`. The ciphertexts decrypt to the page's principal,
+/// unrealized yield, claimable yield, claimed yield, next unlock timestamp
+/// and entry count, in that order. Clients view every page up to
+/// `page_count` and add them up for the whole position.
+#[event]
+pub struct PositionViewed {
+    pub user: Pubkey,
+    pub page_index: u16,
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub ciphertexts: [[u8; 32]; POSITION_SUMMARY_CT_LEN],
+}
+
+pub fn handler(
+    ctx: Context<ViewPosition>,
+    computation_offset: u64,
+    args: ViewPositionArgs,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= 0, ErrorCode::ComputationValidationFailed);
+    let now_u64: u64 = now.try_into().map_err(|_| ErrorCode::ClockOverflow)?;
+
+    require!(
+        ctx.accounts.config.pending_initialize_offset.is_none(),
+        ErrorCode::PendingComputationMismatch
+    );
+    require!(
+        ctx.accounts.config.pending_config_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    require!(
+        ctx.accounts.config.admits_readers(Clock::get()?.slot),
+        ErrorCode::ConfigWriterWaiting
+    );
+
+    let user = &ctx.accounts.user;
+    let user_stake = &ctx.accounts.user_stake;
+    require_keys_eq!(
+        user_stake.owner,
+        user.key(),
+        ErrorCode::InvalidPositionOwner
+    );
+    require!(
+        user_stake.pending_computation_offset.is_none(),
+        ErrorCode::PendingComputationInProgress
+    );
+    require!(!user_stake.is_fresh(), ErrorCode::InvalidStakePage);
+
+    let config_nonce = ctx.accounts.config.encrypted_state.nonce;
+    let user_stake_nonce = ctx.accounts.user_stake.encrypted_state.nonce;
+    let config_key = ctx.accounts.config.key();
+    let user_stake_key = ctx.accounts.user_stake.key();
+
+    let arguments = vec![
+        Argument::ArcisPubkey(args.encryption_pubkey),
+        Argument::PlaintextU128(args.nonce),
+        Argument::PlaintextU128(config_nonce),
+        Argument::Account(config_key, CONFIG_CIPHERTEXT_OFFSET, CONFIG_CIPHERTEXT_LEN),
+        Argument::PlaintextU128(user_stake_nonce),
+        Argument::Account(
+            user_stake_key,
+            USER_STAKE_CIPHERTEXT_OFFSET,
+            USER_STAKE_CIPHERTEXT_LEN,
+        ),
+        Argument::PlaintextU64(now_u64),
+    ];
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    // The view leaves the encrypted state untouched. The position is locked
+    // and the config is counted as read so neither ciphertext changes under
+    // the queued nonces.
+    let callback_accounts = [
+        CallbackAccount {
+            pubkey: config_key,
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: user_stake_key,
+            is_writable: true,
+        },
+    ];
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        arguments,
+        None,
+        vec![ViewPositionSublyCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    let config = &mut ctx.accounts.config;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    ctx.accounts.user_stake.pending_computation_offset = Some(computation_offset);
+
+    Ok(())
+}
+
+pub fn callback(
+    ctx: Context<ViewPositionSublyCallback>,
+    output: ComputationOutputs<ViewPositionSublyOutput>,
+) -> Result<()> {
+    let computation_offset = callback_computation_offset(&ctx.accounts.instructions_sysvar)?;
+    let config = &mut ctx.accounts.config;
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    release_pending(
+        &mut user_stake.pending_computation_offset,
+        computation_offset,
+    )?;
+    config.pending_reader_count = config
+        .pending_reader_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
+
+    let ViewPositionSublyOutput {
+        field_0: summary_cipher,
+    } = match output {
+        ComputationOutputs::Success(payload) => payload,
+        ComputationOutputs::Failure => return Ok(()),
+    };
+
+    let expected_user_pda = Pubkey::create_program_address(
+        &[
+            USER_POSITION_SEED.as_bytes(),
+            user_stake.owner.as_ref(),
+            &UserStakeAccount::page_seed(user_stake.page_index),
+            &[user_stake.bump],
+        ],
+        &ID,
+    )
+    .map_err(|_| ErrorCode::InvalidPositionOwner)?;
+    require_keys_eq!(
+        expected_user_pda,
+        user_stake.key(),
+        ErrorCode::InvalidPositionOwner
+    );
+
+    emit!(PositionViewed {
+        user: user_stake.owner,
+        page_index: user_stake.page_index,
+        encryption_pubkey: summary_cipher.encryption_key,
+        nonce: summary_cipher.nonce,
+        ciphertexts: summary_cipher.ciphertexts,
+    });
+
+    Ok(())
+}
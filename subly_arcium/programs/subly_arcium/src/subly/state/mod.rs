@@ -1,9 +1,20 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
 use arcium_anchor::prelude::MXEEncryptedStruct;
 
+use crate::subly::constants::{SERVICE_TREE_DEPTH, WRITER_WAIT_SLOTS};
+use crate::subly::error::ErrorCode;
+
 pub const CONFIG_CT_LEN: usize = 6;
 pub const REGISTRY_CT_LEN: usize = 3;
 pub const USER_STAKE_CT_LEN: usize = 164;
+pub const POSITION_SUMMARY_CT_LEN: usize = 6;
+pub const USER_SUBSCRIPTIONS_CT_LEN: usize = 26;
+pub const CONFIG_DELTA_CT_LEN: usize = 2;
+/// Service id, price and both sibling lanes of every tree level.
+pub const SERVICE_PROOF_CT_LEN: usize = 2 + 2 * SERVICE_TREE_DEPTH;
 
 const fn encrypted_block_len(ciphertexts: usize) -> usize {
     16 + (ciphertexts * 32)
@@ -46,6 +57,36 @@ impl<const LEN: usize> Default for EncryptedState<LEN> {
     }
 }
 
+/// Offset of the computation whose callback is running. The Arcium
+/// instruction right before the callback, checked by `validate_callback_ixs`,
+/// takes it as its first argument.
+pub fn callback_computation_offset(instructions_sysvar: &AccountInfo) -> Result<u64> {
+    let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+    let arcium_index = current_index
+        .checked_sub(1)
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
+    let arcium_ix = load_instruction_at_checked(arcium_index, instructions_sysvar)?;
+    let offset_bytes: [u8; 8] = arcium_ix
+        .data
+        .get(8..16)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ErrorCode::PendingComputationMismatch)?;
+    Ok(u64::from_le_bytes(offset_bytes))
+}
+
+/// Releases a lock held for `computation_offset`. The callback of a cancelled
+/// computation finds the lock released, or taken by a newer computation, and
+/// is rejected. Callbacks release their locks before looking at the output,
+/// so a failed computation only has to return without changing anything.
+pub fn release_pending(pending: &mut Option<u64>, computation_offset: u64) -> Result<()> {
+    require!(
+        *pending == Some(computation_offset),
+        ErrorCode::PendingComputationMismatch
+    );
+    *pending = None;
+    Ok(())
+}
+
 #[account]
 pub struct SublyConfig {
     pub authority: Pubkey,
@@ -57,6 +98,25 @@ pub struct SublyConfig {
     pub paused: bool,
     pub bump: u8,
     pub vault_bump: u8,
+    /// Queued computations that read the encrypted config without writing
+    /// it. Settling and other config writers wait until this drops to zero.
+    pub pending_reader_count: u32,
+    /// Deposit held in the vault for the queued `fund_rewards`, returned to
+    /// `pending_reward_refund` if it is not added to the pool.
+    pub pending_reward_deposit: u64,
+    pub pending_reward_refund: Pubkey,
+    /// An authority or lock-holding config writer found readers queued. New
+    /// readers are turned away until the next writer queues or
+    /// `WRITER_WAIT_SLOTS` pass, so writers cannot be starved and a stuck
+    /// reader cannot halt everyone else.
+    pub writer_waiting: bool,
+    /// Slot at which `writer_waiting` was raised.
+    pub writer_waiting_slot: u64,
+}
+
+#[event]
+pub struct ConfigWriteDeferred {
+    pub pending_reader_count: u32,
 }
 
 impl SublyConfig {
@@ -71,12 +131,44 @@ impl SublyConfig {
         + 8  // config offset value
         + 1  // paused flag
         + 1  // bump
-        + 1; // vault bump
+        + 1  // vault bump
+        + 4  // pending reader count
+        + 8  // pending reward deposit
+        + 32 // pending reward refund account
+        + 1  // writer waiting flag
+        + 8; // writer waiting slot
 
     pub fn blank_state() -> EncryptedState<CONFIG_CT_LEN> {
         EncryptedState::blank()
     }
 
+    /// Whether a config writer may queue now. With readers still queued the
+    /// writer should retry once they finish. Only `priority` writers, those
+    /// gated on the authority or on a lock they hold, hold new readers back
+    /// meanwhile; permissionless writers could otherwise stall every user.
+    /// The hold is raised once per wait, so repeated retries cannot extend
+    /// it past `WRITER_WAIT_SLOTS`.
+    pub fn admit_writer(&mut self, priority: bool, slot: u64) -> bool {
+        if self.pending_reader_count > 0 {
+            if priority && !self.writer_waiting {
+                self.writer_waiting = true;
+                self.writer_waiting_slot = slot;
+            }
+            emit!(ConfigWriteDeferred {
+                pending_reader_count: self.pending_reader_count,
+            });
+            return false;
+        }
+        self.writer_waiting = false;
+        true
+    }
+
+    /// Whether a reader may queue at `slot`, i.e. no writer is holding new
+    /// readers back.
+    pub fn admits_readers(&self, slot: u64) -> bool {
+        !self.writer_waiting || slot >= self.writer_waiting_slot.saturating_add(WRITER_WAIT_SLOTS)
+    }
+
     pub const ENCRYPTED_STATE_OFFSET: usize = 8 + 32 + 32 + 32;
     pub const ENCRYPTED_STATE_LEN: usize = encrypted_block_len(CONFIG_CT_LEN);
 }
@@ -85,16 +177,66 @@ impl SublyConfig {
 pub struct SubscriptionRegistry {
     pub encrypted_registry: EncryptedState<REGISTRY_CT_LEN>,
     pub bump: u8,
+    /// Services registered so far, which is also the next service id.
+    pub service_count: u16,
+    pub pending_registry_offset: Option<u64>,
+    pub pending_service_price: u64,
+    /// Queued subscribe computations proving against the services root.
+    pub pending_reader_count: u32,
+    /// A service registration found subscriptions queued. New subscriptions
+    /// are turned away until it queues or `WRITER_WAIT_SLOTS` pass.
+    pub writer_waiting: bool,
+    /// Slot at which `writer_waiting` was raised.
+    pub writer_waiting_slot: u64,
+}
+
+#[event]
+pub struct RegistryWriteDeferred {
+    pub pending_reader_count: u32,
 }
 
 impl SubscriptionRegistry {
     pub const LEN: usize = 8 // discriminator
         + encrypted_block_len(REGISTRY_CT_LEN)
-        + 1; // bump
+        + 1  // bump
+        + 2  // service_count
+        + 1  // pending offset option tag
+        + 8  // pending offset value
+        + 8  // pending service price
+        + 4  // pending reader count
+        + 1  // writer waiting flag
+        + 8; // writer waiting slot
 
     pub fn blank_state() -> EncryptedState<REGISTRY_CT_LEN> {
         EncryptedState::blank()
     }
+
+    /// Whether a service registration may queue now. With subscriptions
+    /// still queued it holds new ones back, once per wait, and should be
+    /// retried when they finish.
+    pub fn admit_writer(&mut self, slot: u64) -> bool {
+        if self.pending_reader_count > 0 {
+            if !self.writer_waiting {
+                self.writer_waiting = true;
+                self.writer_waiting_slot = slot;
+            }
+            emit!(RegistryWriteDeferred {
+                pending_reader_count: self.pending_reader_count,
+            });
+            return false;
+        }
+        self.writer_waiting = false;
+        true
+    }
+
+    /// Whether a subscription may queue at `slot`, i.e. no registration is
+    /// holding new ones back.
+    pub fn admits_readers(&self, slot: u64) -> bool {
+        !self.writer_waiting || slot >= self.writer_waiting_slot.saturating_add(WRITER_WAIT_SLOTS)
+    }
+
+    pub const ENCRYPTED_STATE_OFFSET: usize = 8;
+    pub const ENCRYPTED_STATE_LEN: usize = encrypted_block_len(REGISTRY_CT_LEN);
 }
 
 #[account]
@@ -102,8 +244,18 @@ pub struct UserStakeAccount {
     pub owner: Pubkey,
     pub entry_count: u8,
     pub encrypted_state: EncryptedState<USER_STAKE_CT_LEN>,
+    /// This user's unsettled changes to the aggregate config.
+    pub encrypted_delta: EncryptedState<CONFIG_DELTA_CT_LEN>,
     pub pending_computation_offset: Option<u64>,
     pub bump: u8,
+    /// Deposit held in the vault for the queued stake, returned if the
+    /// tranche is not placed.
+    pub pending_stake_amount: u64,
+    /// Position of this page among the user's stake pages. Page 0 lives at
+    /// the original position address.
+    pub page_index: u16,
+    /// Pages opened so far. Only maintained on page 0.
+    pub page_count: u16,
 }
 
 impl UserStakeAccount {
@@ -111,24 +263,93 @@ impl UserStakeAccount {
         + 32 // owner
         + 1  // entry_count
         + encrypted_block_len(USER_STAKE_CT_LEN)
+        + encrypted_block_len(CONFIG_DELTA_CT_LEN)
         + 1  // pending offset option tag
         + 8  // pending offset value
-        + 1; // bump
+        + 1  // bump
+        + 8  // pending stake amount
+        + 2  // page_index
+        + 2; // page_count
 
     pub fn blank_state() -> EncryptedState<USER_STAKE_CT_LEN> {
         EncryptedState::blank()
     }
 
-    pub fn ensure_owner(&mut self, owner: Pubkey, bump: u8) {
+    pub fn ensure_owner(&mut self, owner: Pubkey, bump: u8, page_index: u16) {
         if self.owner == Pubkey::default() {
             self.owner = owner;
             self.entry_count = 0;
             self.pending_computation_offset = None;
             self.bump = bump;
             self.encrypted_state = Self::blank_state();
+            self.encrypted_delta = EncryptedState::blank();
+            self.pending_stake_amount = 0;
+            self.page_index = page_index;
+            self.page_count = if page_index == 0 { 1 } else { 0 };
         }
     }
 
+    /// Extra seed for a stake page. Empty for page 0 so it keeps the
+    /// original `[USER_POSITION_SEED, owner]` address.
+    pub fn page_seed(page_index: u16) -> Vec<u8> {
+        if page_index == 0 {
+            Vec::new()
+        } else {
+            page_index.to_le_bytes().to_vec()
+        }
+    }
+
+    /// Nothing has been encrypted into this page yet.
+    pub fn is_fresh(&self) -> bool {
+        self.encrypted_state.nonce == 0
+    }
+
+    /// No delta has been encrypted into the account since it was created.
+    pub fn is_delta_fresh(&self) -> bool {
+        self.encrypted_delta.nonce == 0
+    }
+
     pub const ENCRYPTED_STATE_OFFSET: usize = 8 + 32 + 1;
     pub const ENCRYPTED_STATE_LEN: usize = encrypted_block_len(USER_STAKE_CT_LEN);
+    pub const ENCRYPTED_DELTA_OFFSET: usize =
+        Self::ENCRYPTED_STATE_OFFSET + Self::ENCRYPTED_STATE_LEN;
+    pub const ENCRYPTED_DELTA_LEN: usize = encrypted_block_len(CONFIG_DELTA_CT_LEN);
+}
+
+#[account]
+pub struct UserSubscriptionsAccount {
+    pub owner: Pubkey,
+    pub encrypted_state: EncryptedState<USER_SUBSCRIPTIONS_CT_LEN>,
+    pub pending_computation_offset: Option<u64>,
+    pub bump: u8,
+}
+
+impl UserSubscriptionsAccount {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // owner
+        + encrypted_block_len(USER_SUBSCRIPTIONS_CT_LEN)
+        + 1  // pending offset option tag
+        + 8  // pending offset value
+        + 1; // bump
+
+    pub fn blank_state() -> EncryptedState<USER_SUBSCRIPTIONS_CT_LEN> {
+        EncryptedState::blank()
+    }
+
+    pub fn ensure_owner(&mut self, owner: Pubkey, bump: u8) {
+        if self.owner == Pubkey::default() {
+            self.owner = owner;
+            self.pending_computation_offset = None;
+            self.bump = bump;
+            self.encrypted_state = Self::blank_state();
+        }
+    }
+
+    /// Nothing has been encrypted into the account yet.
+    pub fn is_fresh(&self) -> bool {
+        self.encrypted_state.nonce == 0
+    }
+
+    pub const ENCRYPTED_STATE_OFFSET: usize = 8 + 32;
+    pub const ENCRYPTED_STATE_LEN: usize = encrypted_block_len(USER_SUBSCRIPTIONS_CT_LEN);
 }
@@ -1,16 +1,594 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import { PublicKey, Transaction } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import {
+  RescueCipher,
+  awaitComputationFinalization,
+  deserializeLE,
+  getArciumEnv,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMempoolAccAddress,
+  getMXEAccAddress,
+  getMXEPublicKey,
+  uploadCircuit,
+  x25519,
+} from "@arcium-hq/client";
+import { randomBytes } from "crypto";
+import * as fs from "fs";
+import { expect } from "chai";
+
 import { SublyArcium } from "../target/types/subly_arcium";
 
+const STAKE_AMOUNT = 25_000_000;
+const REWARD_AMOUNT = 5_000_000;
+// Every lock option is at least 30 days, which a localnet cannot skip, so
+// tranches staked here stay locked for the whole run.
+const LOCK_OPTION_30_DAYS = 0;
+const INVALID_LOCK_OPTION = 9;
+// Well inside the monthly budget of the tranche staked below.
+const SERVICE_PRICE = 100_000;
+
+// Mirrors `compress_nodes` and `service_leaf` in encrypted-ixs.
+const SERVICE_FIELD_MODULUS = (1n << 61n) - 1n;
+const SERVICE_TREE_DEPTH = 8;
+const SERVICE_HASH_ROUNDS = 34;
+const SERVICE_HASH_ROUND_STEP = 2_177_342_782_468_422_681n;
+const SERVICE_LEAF_TAG = 1n;
+
+type ServiceNode = [bigint, bigint];
+
+const EMPTY_NODE: ServiceNode = [0n, 0n];
+
+const compressNodes = (left: ServiceNode, right: ServiceNode): ServiceNode => {
+  const [leftLow, leftHigh, rightLow, rightHigh] = [...left, ...right].map(
+    (element) => element % SERVICE_FIELD_MODULUS
+  );
+  if (
+    leftLow === 0n &&
+    leftHigh === 0n &&
+    rightLow === 0n &&
+    rightHigh === 0n
+  ) {
+    return EMPTY_NODE;
+  }
+  let [a, b, c, d] = [leftLow, leftHigh, rightLow, rightHigh];
+  for (let round = 0n; round < BigInt(SERVICE_HASH_ROUNDS); round++) {
+    const roundConstant =
+      ((round + 1n) * SERVICE_HASH_ROUND_STEP) % SERVICE_FIELD_MODULUS;
+    const shifted = (a + roundConstant) % SERVICE_FIELD_MODULUS;
+    let power = shifted;
+    for (let squaring = 0; squaring < 4; squaring++) {
+      power = (power * power) % SERVICE_FIELD_MODULUS;
+    }
+    power = (power * shifted) % SERVICE_FIELD_MODULUS;
+    [a, b, c, d] = [(b + power) % SERVICE_FIELD_MODULUS, c, d, a];
+  }
+  return [
+    (a + leftLow + rightHigh) % SERVICE_FIELD_MODULUS,
+    (b + leftHigh + rightLow) % SERVICE_FIELD_MODULUS,
+  ];
+};
+
+const serviceLeaf = (serviceId: number, price: number): ServiceNode =>
+  compressNodes([BigInt(serviceId), BigInt(price)], [SERVICE_LEAF_TAG, 0n]);
+
+// Sibling nodes from leaf `serviceId` up to the root of a tree holding
+// `prices`, indexed by service id.
+const serviceSiblings = (prices: number[], serviceId: number) => {
+  let level: ServiceNode[] = Array.from(
+    { length: 1 << SERVICE_TREE_DEPTH },
+    (_, id) => (id < prices.length ? serviceLeaf(id, prices[id]) : EMPTY_NODE)
+  );
+  const siblings: ServiceNode[] = [];
+  let position = serviceId;
+  while (level.length > 1) {
+    siblings.push(level[position ^ 1]);
+    level = Array.from({ length: level.length / 2 }, (_, idx) =>
+      compressNodes(level[2 * idx], level[2 * idx + 1])
+    );
+    position >>= 1;
+  }
+  return siblings;
+};
+
+const expectAnchorError = async (promise: Promise<unknown>, code: string) => {
+  try {
+    await promise;
+    expect.fail(`Expected Anchor error ${code}`);
+  } catch (err: any) {
+    const anchorError = err?.error ?? err;
+    const received =
+      anchorError?.errorCode?.code ?? anchorError?.error?.errorCode?.code;
+    expect(received).to.eq(code);
+  }
+};
+
 describe("subly_arcium", () => {
-  // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
+  const wallet = provider.wallet as anchor.Wallet;
   const program = anchor.workspace.sublyArcium as Program<SublyArcium>;
+  const arciumEnv = getArciumEnv();
+
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+  const [vaultPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("vault")],
+    program.programId
+  );
+  const [userStakePda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("user_position"), wallet.publicKey.toBuffer()],
+    program.programId
+  );
+  const [userSubscriptionsPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("user_subscriptions"), wallet.publicKey.toBuffer()],
+    program.programId
+  );
+  const [registryPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("subscription_registry")],
+    program.programId
+  );
+
+  let mint: PublicKey;
+  let walletTokenAccount: PublicKey;
+
+  const compDefOffset = (name: string) =>
+    Buffer.from(getCompDefAccOffset(name)).readUInt32LE();
+
+  const newComputationOffset = () => new anchor.BN(randomBytes(8), "hex");
+
+  const arciumAccounts = (name: string, computationOffset: anchor.BN) => ({
+    computationAccount: getComputationAccAddress(
+      program.programId,
+      computationOffset
+    ),
+    clusterAccount: arciumEnv.arciumClusterPubkey,
+    mxeAccount: getMXEAccAddress(program.programId),
+    mempoolAccount: getMempoolAccAddress(program.programId),
+    executingPool: getExecutingPoolAccAddress(program.programId),
+    compDefAccount: getCompDefAccAddress(
+      program.programId,
+      compDefOffset(name)
+    ),
+  });
+
+  const awaitFinalization = (computationOffset: anchor.BN) =>
+    awaitComputationFinalization(
+      provider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+  const awaitEvent = async <E extends keyof anchor.IdlEvents<SublyArcium>>(
+    eventName: E
+  ): Promise<anchor.IdlEvents<SublyArcium>[E]> => {
+    let listenerId: number;
+    const event = await new Promise<anchor.IdlEvents<SublyArcium>[E]>(
+      (resolve) => {
+        listenerId = program.addEventListener(eventName, (event) =>
+          resolve(event)
+        );
+      }
+    );
+    await program.removeEventListener(listenerId);
+    return event;
+  };
+
+  const tokenBalance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account)).amount);
+
+  const initCompDef = async (name: string, init: () => any) => {
+    const compDefAccount = getCompDefAccAddress(
+      program.programId,
+      compDefOffset(name)
+    );
+    if (await provider.connection.getAccountInfo(compDefAccount)) {
+      return;
+    }
+    await init()
+      .accounts({
+        compDefAccount,
+        payer: wallet.publicKey,
+        mxeAccount: getMXEAccAddress(program.programId),
+      })
+      .rpc({ commitment: "confirmed" });
+    await uploadCircuit(
+      provider,
+      name,
+      program.programId,
+      fs.readFileSync(`build/${name}.arcis`),
+      true
+    );
+  };
+
+  const stakeBuilder = (
+    amount: number,
+    lockOption: number,
+    computationOffset: anchor.BN
+  ) =>
+    program.methods
+      .stake(computationOffset, {
+        amount: new anchor.BN(amount),
+        lockOption,
+        pageIndex: 0,
+      })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        config: configPda,
+        user: wallet.publicKey,
+        userStake: userStakePda,
+        firstPage: null,
+        userSubscriptions: userSubscriptionsPda,
+        userTokenAccount: walletTokenAccount,
+        vault: vaultPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        ...arciumAccounts("stake_subly", computationOffset),
+      });
+
+  const stake = async (amount: number, lockOption: number) => {
+    const computationOffset = newComputationOffset();
+    await stakeBuilder(amount, lockOption, computationOffset).rpc({
+      commitment: "confirmed",
+    });
+    return computationOffset;
+  };
+
+  const cancelBuilder = (computationOffset: anchor.BN) =>
+    program.methods
+      .cancelPendingComputation(computationOffset)
+      .accountsPartial({
+        caller: wallet.publicKey,
+        config: configPda,
+        computationAccount: getComputationAccAddress(
+          program.programId,
+          computationOffset
+        ),
+        userStake: userStakePda,
+        userSubscriptions: userSubscriptionsPda,
+        subscriptionRegistry: null,
+        vault: vaultPda,
+        refundTokenAccount: walletTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      });
+
+  const subscribeBuilder = async (
+    serviceId: number,
+    price: number,
+    computationOffset: anchor.BN
+  ) => {
+    const siblings = serviceSiblings(registeredPrices, serviceId);
+    const privateKey = x25519.utils.randomPrivateKey();
+    const mxePublicKey = await getMXEPublicKey(provider, program.programId);
+    const cipher = new RescueCipher(
+      x25519.getSharedSecret(privateKey, mxePublicKey)
+    );
+    const nonce = randomBytes(16);
+    const proofCiphertexts = cipher.encrypt(
+      [
+        BigInt(serviceId),
+        BigInt(price),
+        ...siblings.map(([low]) => low),
+        ...siblings.map(([, high]) => high),
+      ],
+      nonce
+    );
+
+    return program.methods
+      .subscribe(computationOffset, {
+        encryptionPubkey: Array.from(x25519.getPublicKey(privateKey)),
+        nonce: new anchor.BN(deserializeLE(nonce).toString()),
+        proofCiphertexts,
+      })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        config: configPda,
+        user: wallet.publicKey,
+        userStake: userStakePda,
+        userSubscriptions: userSubscriptionsPda,
+        subscriptionRegistry: registryPda,
+        ...arciumAccounts("subscribe_subly", computationOffset),
+      });
+  };
+
+  const subscribe = async (serviceId: number, price: number) => {
+    const computationOffset = newComputationOffset();
+    const processed = awaitEvent("subscribeProcessed");
+    await (
+      await subscribeBuilder(serviceId, price, computationOffset)
+    ).rpc({ commitment: "confirmed" });
+    await awaitFinalization(computationOffset);
+    return (await processed).accepted;
+  };
+
+  const registerBuilder = (price: number, computationOffset: anchor.BN) => {
+    const siblings = serviceSiblings(registeredPrices, registeredPrices.length);
+    return program.methods
+      .registerSubscriptionService(computationOffset, {
+        monthlyPriceUsdc: new anchor.BN(price),
+        siblingsLow: siblings.map(([low]) => new anchor.BN(low.toString())),
+        siblingsHigh: siblings.map(
+          ([, high]) => new anchor.BN(high.toString())
+        ),
+      })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        config: configPda,
+        authority: wallet.publicKey,
+        subscriptionRegistry: registryPda,
+        ...arciumAccounts("register_service_subly", computationOffset),
+      });
+  };
+
+  // Prices of the services registered so far, indexed by service id.
+  const registeredPrices: number[] = [];
+
+  before(async () => {
+    mint = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    walletTokenAccount = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        wallet.payer,
+        mint,
+        wallet.publicKey
+      )
+    ).address;
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      mint,
+      walletTokenAccount,
+      wallet.publicKey,
+      1_000_000_000
+    );
+
+    await initCompDef("initialize_subly", () =>
+      program.methods.initInitializeSublyCompDef()
+    );
+    await initCompDef("stake_subly", () =>
+      program.methods.initStakeSublyCompDef()
+    );
+    await initCompDef("unstake_subly", () =>
+      program.methods.initUnstakeSublyCompDef()
+    );
+    await initCompDef("claim_user_subly", () =>
+      program.methods.initClaimUserSublyCompDef()
+    );
+    await initCompDef("fund_rewards_subly", () =>
+      program.methods.initFundRewardsSublyCompDef()
+    );
+    await initCompDef("register_service_subly", () =>
+      program.methods.initRegisterServiceSublyCompDef()
+    );
+    await initCompDef("subscribe_subly", () =>
+      program.methods.initSubscribeSublyCompDef()
+    );
+
+    const computationOffset = newComputationOffset();
+    await program.methods
+      .initialize(computationOffset, { authority: wallet.publicKey })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        usdcMint: mint,
+        ...arciumAccounts("initialize_subly", computationOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await awaitFinalization(computationOffset);
+  });
+
+  it("refunds a stake whose tranche is not placed", async () => {
+    const walletBefore = await tokenBalance(walletTokenAccount);
+    const vaultBefore = await tokenBalance(vaultPda);
+
+    const computationOffset = await stake(STAKE_AMOUNT, INVALID_LOCK_OPTION);
+    expect(await tokenBalance(vaultPda)).to.eq(vaultBefore + STAKE_AMOUNT);
+    await awaitFinalization(computationOffset);
+
+    expect(await tokenBalance(walletTokenAccount)).to.eq(walletBefore);
+    expect(await tokenBalance(vaultPda)).to.eq(vaultBefore);
+    const position = await program.account.userStakeAccount.fetch(userStakePda);
+    expect(position.pendingComputationOffset).to.eq(null);
+    expect(position.pendingStakeAmount.toNumber()).to.eq(0);
+    expect(position.entryCount).to.eq(0);
+    const config = await program.account.sublyConfig.fetch(configPda);
+    expect(config.pendingReaderCount).to.eq(0);
+  });
+
+  it("rejects cancelling a computation whose callback already landed", async () => {
+    const computationOffset = await stake(STAKE_AMOUNT, LOCK_OPTION_30_DAYS);
+    await awaitFinalization(computationOffset);
+    const vaultBefore = await tokenBalance(vaultPda);
+
+    await expectAnchorError(
+      cancelBuilder(computationOffset).rpc({ commitment: "confirmed" }),
+      "PendingComputationMismatch"
+    );
+
+    // The placed tranche keeps its deposit; nothing is refunded twice.
+    expect(await tokenBalance(vaultPda)).to.eq(vaultBefore);
+    const position = await program.account.userStakeAccount.fetch(userStakePda);
+    expect(position.entryCount).to.eq(1);
+  });
+
+  // Needs the program built with the `localnet` feature, which lets a queued
+  // computation expire in the slot it was queued.
+  it("cancels a stuck stake and refunds its deposit", async () => {
+    const walletBefore = await tokenBalance(walletTokenAccount);
+    const vaultBefore = await tokenBalance(vaultPda);
+    const entriesBefore = (
+      await program.account.userStakeAccount.fetch(userStakePda)
+    ).entryCount;
+
+    // Cancelling in the same transaction leaves the cluster no chance to
+    // call back first, as if it never would.
+    const computationOffset = newComputationOffset();
+    const tx = new Transaction().add(
+      await stakeBuilder(
+        STAKE_AMOUNT,
+        LOCK_OPTION_30_DAYS,
+        computationOffset
+      ).instruction(),
+      await cancelBuilder(computationOffset).instruction()
+    );
+    const cancelled = awaitEvent("pendingComputationCancelled");
+    await provider.sendAndConfirm(tx, [], { commitment: "confirmed" });
+
+    const event = await cancelled;
+    expect(event.computationOffset.eq(computationOffset)).to.eq(true);
+    expect(event.refunded.toNumber()).to.eq(STAKE_AMOUNT);
+    expect(await tokenBalance(walletTokenAccount)).to.eq(walletBefore);
+    expect(await tokenBalance(vaultPda)).to.eq(vaultBefore);
+    const position = await program.account.userStakeAccount.fetch(userStakePda);
+    expect(position.pendingComputationOffset).to.eq(null);
+    expect(position.pendingStakeAmount.toNumber()).to.eq(0);
+    expect(position.entryCount).to.eq(entriesBefore);
+    const subscriptions = await program.account.userSubscriptionsAccount.fetch(
+      userSubscriptionsPda
+    );
+    expect(subscriptions.pendingComputationOffset).to.eq(null);
+    const config = await program.account.sublyConfig.fetch(configPda);
+    expect(config.pendingReaderCount).to.eq(0);
+  });
+
+  it("releases the position when unstaking right after a claim", async () => {
+    const fundOffset = newComputationOffset();
+    await program.methods
+      .fundRewards(fundOffset, { amount: new anchor.BN(REWARD_AMOUNT) })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        config: configPda,
+        funder: wallet.publicKey,
+        funderTokenAccount: walletTokenAccount,
+        vault: vaultPda,
+        ...arciumAccounts("fund_rewards_subly", fundOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await awaitFinalization(fundOffset);
+
+    const claimOffset = newComputationOffset();
+    await program.methods
+      .claimUser(claimOffset, { amount: new anchor.BN(0) })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        config: configPda,
+        user: wallet.publicKey,
+        userStake: userStakePda,
+        vault: vaultPda,
+        userTokenAccount: walletTokenAccount,
+        ...arciumAccounts("claim_user_subly", claimOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await awaitFinalization(claimOffset);
+
+    const vaultBefore = await tokenBalance(vaultPda);
+    const unstakeOffset = newComputationOffset();
+    await program.methods
+      .unstake(unstakeOffset, { trancheId: new anchor.BN(0) })
+      .accountsPartial({
+        payer: wallet.publicKey,
+        config: configPda,
+        user: wallet.publicKey,
+        userStake: userStakePda,
+        userSubscriptions: userSubscriptionsPda,
+        vault: vaultPda,
+        userTokenAccount: walletTokenAccount,
+        ...arciumAccounts("unstake_subly", unstakeOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await awaitFinalization(unstakeOffset);
+
+    // The tranche is still locked, so nothing is withdrawn, but the callback
+    // lands and the position is free for the next computation.
+    expect(await tokenBalance(vaultPda)).to.eq(vaultBefore);
+    const position = await program.account.userStakeAccount.fetch(userStakePda);
+    expect(position.pendingComputationOffset).to.eq(null);
+    expect(position.entryCount).to.eq(1);
+    const config = await program.account.sublyConfig.fetch(configPda);
+    expect(config.pendingReaderCount).to.eq(0);
+    expect(config.pendingConfigOffset).to.eq(null);
+  });
+
+  it("accepts a registered service price and rejects a forged one", async () => {
+    const registry = await program.account.subscriptionRegistry.fetch(
+      registryPda
+    );
+    const serviceId = registry.serviceCount;
+    expect(serviceId).to.eq(registeredPrices.length);
+
+    const registerOffset = newComputationOffset();
+    await registerBuilder(SERVICE_PRICE, registerOffset).rpc({
+      commitment: "confirmed",
+    });
+    await awaitFinalization(registerOffset);
+    registeredPrices.push(SERVICE_PRICE);
+    expect(
+      (await program.account.subscriptionRegistry.fetch(registryPda))
+        .serviceCount
+    ).to.eq(serviceId + 1);
+
+    // A cheaper price for the same service does not hash to the root.
+    expect(await subscribe(serviceId, 1)).to.eq(false);
+    expect(await subscribe(serviceId, SERVICE_PRICE)).to.eq(true);
+  });
+
+  it("holds new subscriptions back while a registration waits", async () => {
+    const serviceCount = (
+      await program.account.subscriptionRegistry.fetch(registryPda)
+    ).serviceCount;
+
+    // Queued behind a subscription, the registration is deferred rather
+    // than failed, and it turns later subscriptions away.
+    const subscribeOffset = newComputationOffset();
+    const tx = new Transaction().add(
+      await (
+        await subscribeBuilder(0, SERVICE_PRICE, subscribeOffset)
+      ).instruction(),
+      await registerBuilder(SERVICE_PRICE, newComputationOffset()).instruction()
+    );
+    await provider.sendAndConfirm(tx, [], { commitment: "confirmed" });
+
+    let registry = await program.account.subscriptionRegistry.fetch(
+      registryPda
+    );
+    expect(registry.writerWaiting).to.eq(true);
+    expect(registry.pendingRegistryOffset).to.eq(null);
+    expect(registry.serviceCount).to.eq(serviceCount);
+    await expectAnchorError(
+      (
+        await subscribeBuilder(0, SERVICE_PRICE, newComputationOffset())
+      ).rpc({ commitment: "confirmed" }),
+      "RegistryWriterWaiting"
+    );
+
+    await awaitFinalization(subscribeOffset);
+    const registerOffset = newComputationOffset();
+    await registerBuilder(SERVICE_PRICE, registerOffset).rpc({
+      commitment: "confirmed",
+    });
+    await awaitFinalization(registerOffset);
+    registeredPrices.push(SERVICE_PRICE);
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+    registry = await program.account.subscriptionRegistry.fetch(registryPda);
+    expect(registry.writerWaiting).to.eq(false);
+    expect(registry.serviceCount).to.eq(serviceCount + 1);
   });
 });